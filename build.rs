@@ -28,10 +28,14 @@ fn gen_items(out: &mut String) {
     )
     .unwrap();
     for (id, def) in itemdb.iter() {
+        if def.key.is_empty() {
+            println!("cargo:warning=Item '{}' has no key", def.name);
+            continue;
+        }
         writeln!(
             out,
             "pub const {}: ItemId = ItemId({});",
-            id_ify(&def.name),
+            id_ify(&def.key),
             id.0
         )
         .unwrap();
@@ -48,10 +52,14 @@ fn gen_tiles(out: &mut String) {
     )
     .unwrap();
     for (i, def) in tiledb.bg.iter().enumerate() {
+        if def.key.is_empty() {
+            println!("cargo:warning=Tile '{}' has no key", def.graphic_name);
+            continue;
+        }
         writeln!(
             out,
             "pub const {}: BgTileId = TileId({}, PhantomData);",
-            id_ify(&def.graphic_name),
+            id_ify(&def.key),
             i + 1
         )
         .unwrap();
@@ -63,10 +71,14 @@ fn gen_tiles(out: &mut String) {
     )
     .unwrap();
     for (i, def) in tiledb.mid.iter().enumerate() {
+        if def.key.is_empty() {
+            println!("cargo:warning=Tile '{}' has no key", def.graphic_name);
+            continue;
+        }
         writeln!(
             out,
             "pub const {}: MidTileId = TileId({}, PhantomData);",
-            id_ify(&def.graphic_name),
+            id_ify(&def.key),
             i + 1
         )
         .unwrap();
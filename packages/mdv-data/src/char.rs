@@ -2,7 +2,7 @@ use {
     crate::{ron_pretty_cfg, LoadError},
     mdv_math::types::ScreenVec,
     serde::{Deserialize, Serialize},
-    std::collections::HashMap,
+    std::{collections::HashMap, path::Path},
 };
 
 /// Character (graphics) database
@@ -13,19 +13,25 @@ pub struct CharDb {
 }
 
 impl CharDb {
-    pub fn save(&self) -> Result<(), LoadError> {
+    pub fn save(&self, data_path: impl AsRef<Path>) -> Result<(), LoadError> {
         let s = ron::ser::to_string_pretty(self, ron_pretty_cfg())?;
-        std::fs::write("data/char.ron", s.as_bytes())?;
+        std::fs::write(data_path.as_ref().join(PATH), s.as_bytes())?;
         Ok(())
     }
-    pub fn load() -> Result<Self, LoadError> {
-        match std::fs::read_to_string("data/char.ron") {
+    pub fn load(data_path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        match std::fs::read_to_string(data_path.as_ref().join(PATH)) {
             Ok(data) => Ok(ron::from_str(&data)?),
             Err(_) => Ok(CharDb::default()),
         }
     }
+    /// Offsets of `other` override the offsets for the same graphic
+    pub fn merge(&mut self, other: CharDb) {
+        self.graphic_offsets.extend(other.graphic_offsets);
+    }
 }
 
+const PATH: &str = "char.ron";
+
 #[derive(Serialize, Deserialize)]
 pub struct Offset {
     /// Offset when facing left
@@ -2,9 +2,11 @@ use {
     crate::{
         ron_pretty_cfg,
         tile::{BgTileId, LayerAccess, MidTileId},
+        LoadError,
     },
     mdv_math::types::{IntRect, ScreenVec},
     serde::{Deserialize, Serialize},
+    std::path::Path,
 };
 
 /// We won't have more than 65535 different items
@@ -19,10 +21,17 @@ impl ItemId {
 pub type ItemQty = u16;
 
 /// A stack of items (one or more item of a kind)
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ItemStack {
     pub id: ItemId,
     pub qty: ItemQty,
+    /// How much of the durability of the item has been used up
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub wear: u16,
+}
+
+fn is_zero(wear: &u16) -> bool {
+    *wear == 0
 }
 
 impl ItemStack {
@@ -33,12 +42,18 @@ impl ItemStack {
         Self {
             id: ItemId::EMPTY,
             qty: 0,
+            wear: 0,
         }
     }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ItemDef {
+    /// Stable identifier of the item.
+    ///
+    /// Ids are positional, so saves refer to items by this key instead,
+    /// and build.rs generates the item constants from it.
+    pub key: String,
     pub name: String,
     pub graphic_name: String,
     #[serde(skip)]
@@ -50,6 +65,129 @@ pub struct ItemDef {
     pub use2: UseAction,
     #[serde(alias = "consumable")]
     pub stackable: bool,
+    /// How many of a stackable item fit into a single slot
+    #[serde(default = "default_max_stack")]
+    pub max_stack: ItemQty,
+    /// How many successful uses the item survives. 0 means it never wears out.
+    #[serde(default)]
+    pub durability: u16,
+    /// Makes the item wearable in an equipment slot
+    #[serde(default)]
+    pub equip: Option<Equippable>,
+    /// Counts as money, which the "drop coins" death rule drops
+    #[serde(default)]
+    pub currency: bool,
+}
+
+fn default_max_stack() -> ItemQty {
+    999
+}
+
+impl ItemDef {
+    /// How many of this item fit into a single slot
+    pub fn stack_limit(&self) -> ItemQty {
+        if self.stackable {
+            self.max_stack.max(1)
+        } else {
+            1
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Equippable {
+    pub slot: EquipSlot,
+    #[serde(default)]
+    pub mods: StatMods,
+    /// Graphic drawn over the character while worn.
+    ///
+    /// Positioned by the character database offsets of the graphic.
+    #[serde(default)]
+    pub worn_graphic: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EquipSlot {
+    Head,
+    Body,
+    Legs,
+    Accessory,
+}
+
+impl EquipSlot {
+    pub const ALL: [Self; 4] = [Self::Head, Self::Body, Self::Legs, Self::Accessory];
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Head => "Head",
+            Self::Body => "Body",
+            Self::Legs => "Legs",
+            Self::Accessory => "Accessory",
+        }
+    }
+}
+
+/// Modifiers of an entity's stats. Equipped items add theirs together.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StatMods {
+    /// Subtracted from incoming damage
+    #[serde(default)]
+    pub defense: f32,
+    /// Extra mining speed. 0.5 means 50% more power and 50% less delay between hits.
+    #[serde(default)]
+    pub mining_speed: f32,
+    /// Light emitted at the wearer, like a held torch (0-255)
+    #[serde(default)]
+    pub light: u8,
+}
+
+impl StatMods {
+    /// Stack the modifiers of `other` on top of these
+    pub fn add(&mut self, other: &Self) {
+        self.defense += other.defense;
+        self.mining_speed += other.mining_speed;
+        // Light sources don't add up, the brightest one wins
+        self.light = self.light.max(other.light);
+    }
+    /// The mining power and delay of a tool after applying `mining_speed`
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "The delay stays a small positive number"
+    )]
+    pub fn mining(&self, power: f32, delay: u64) -> (f32, u64) {
+        let mul = (1.0 + self.mining_speed).max(0.1);
+        (power * mul, (delay as f32 / mul).round() as u64)
+    }
+}
+
+/// A timed effect on an entity, like poison
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: EffectKind,
+    /// Damage or healing per second, or extra speed for [`EffectKind::Speed`]
+    pub strength: f32,
+    /// How long the effect lasts, in ticks
+    pub duration: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EffectKind {
+    Poison,
+    Burning,
+    Regeneration,
+    Speed,
+}
+
+impl EffectKind {
+    pub const ALL: [Self; 4] = [Self::Poison, Self::Burning, Self::Regeneration, Self::Speed];
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Poison => "Poison",
+            Self::Burning => "Burning",
+            Self::Regeneration => "Regeneration",
+            Self::Speed => "Speed",
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
@@ -69,11 +207,72 @@ pub enum UseAction {
     MineTile {
         power: f32,
         delay: u64,
+        /// Tiles with a higher `min_tier` can't be mined
+        #[serde(default)]
+        tier: u8,
     },
     /// Mine background wall
     MineBgTile {
         power: f32,
         delay: u64,
+        /// Tiles with a higher `min_tier` can't be mined
+        #[serde(default)]
+        tier: u8,
+    },
+    /// Eat or drink the item, restoring survival stats and health
+    Consume {
+        #[serde(default)]
+        food: f32,
+        #[serde(default)]
+        stamina: f32,
+        #[serde(default)]
+        breath: f32,
+        #[serde(default)]
+        health: f32,
+        /// Status effects applied to the consumer
+        #[serde(default)]
+        effects: Vec<StatusEffect>,
+    },
+    /// Throw the item towards the cursor
+    Throw {
+        speed: f32,
+        /// Damage dealt to whatever the item hits while flying
+        #[serde(default)]
+        damage: f32,
+        #[serde(default)]
+        knockback: f32,
+    },
+    /// Hit everything within `reach` pixels, in an `arc` (in degrees) centered on the cursor
+    Melee {
+        damage: f32,
+        reach: u16,
+        arc: f32,
+        #[serde(default)]
+        knockback: f32,
+        /// Ticks to wait between attacks
+        cooldown: u64,
+    },
+    /// Shoot an `ammo` item from the inventory towards the cursor
+    Shoot {
+        ammo: ItemId,
+        speed: f32,
+        damage: f32,
+        #[serde(default)]
+        knockback: f32,
+        /// Ticks to wait between shots
+        cooldown: u64,
+    },
+    /// Place an object that isn't bound to the tile grid, like a rope or a ladder
+    SpawnEntity {
+        graphic: String,
+        size: ScreenVec,
+        /// Whether entities can climb up and down on it
+        #[serde(default)]
+        climbable: bool,
+    },
+    /// Run a game script hook with the given name
+    Script {
+        name: String,
     },
 }
 impl UseAction {
@@ -90,21 +289,40 @@ pub struct ItemDb {
 const PATH: &str = "items.ron";
 
 impl ItemDb {
-    pub fn load_or_default(data_path: &str) -> Self {
-        match std::fs::read_to_string(format!("{data_path}/{PATH}")) {
-            Ok(data) => match ron::from_str(&data) {
-                Ok(db) => db,
-                Err(e) => {
-                    log::warn!("Failed to load item database: {e}\nCreating default.");
-                    Default::default()
-                }
-            },
+    pub fn load(data_path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        let data = std::fs::read_to_string(data_path.as_ref().join(PATH))?;
+        Ok(ron::from_str(&data)?)
+    }
+    pub fn load_or_default(data_path: impl AsRef<Path>) -> Self {
+        match Self::load(data_path) {
+            Ok(db) => db,
             Err(e) => {
                 log::warn!("Failed to load item database: {e}\nCreating default.");
                 Default::default()
             }
         }
     }
+    /// Merge the definitions of `other` into this database.
+    ///
+    /// Definitions with a key that already exists replace the existing definition,
+    /// the rest get appended.
+    pub fn merge(&mut self, other: ItemDb) {
+        for def in other.db {
+            match self
+                .db
+                .iter_mut()
+                .find(|existing| !def.key.is_empty() && existing.key == def.key)
+            {
+                Some(existing) => *existing = def,
+                None => self.db.push(def),
+            }
+        }
+    }
+    pub fn id_by_key(&self, key: &str) -> Option<ItemId> {
+        self.iter()
+            .find(|(_, def)| def.key == key)
+            .map(|(id, _)| id)
+    }
     pub fn get(&self, id: ItemId) -> Option<&ItemDef> {
         if id == ItemId::EMPTY {
             None
@@ -112,9 +330,9 @@ impl ItemDb {
             self.db.get(id.0 as usize - 1)
         }
     }
-    pub fn try_save(&self, data_path: &str) {
+    pub fn try_save(&self, data_path: impl AsRef<Path>) {
         match ron::ser::to_string_pretty(self, ron_pretty_cfg()) {
-            Ok(str) => match std::fs::write(format!("{data_path}/{PATH}"), str) {
+            Ok(str) => match std::fs::write(data_path.as_ref().join(PATH), str) {
                 Ok(()) => {}
                 Err(e) => log::warn!("Failed to save item db: {e}"),
             },
@@ -133,3 +351,39 @@ impl ItemDb {
             .map(|(i, def)| (ItemId(i as u16 + 1), def))
     }
 }
+
+#[test]
+fn test_mining_speed() {
+    let mods = StatMods {
+        mining_speed: 1.0,
+        ..Default::default()
+    };
+    assert_eq!(mods.mining(2.0, 30), (4.0, 15));
+    assert_eq!(StatMods::default().mining(2.0, 30), (2.0, 30));
+}
+
+#[test]
+fn test_merge_overrides_by_key() {
+    let def = |key: &str, name: &str| ItemDef {
+        key: key.into(),
+        name: name.into(),
+        graphic_name: String::new(),
+        tex_rect: IntRect::default(),
+        draw_off: ScreenVec::default(),
+        use1: UseAction::Nothing,
+        use2: UseAction::Nothing,
+        stackable: true,
+        max_stack: 999,
+        durability: 0,
+        equip: None,
+        currency: false,
+    };
+    let mut db = ItemDb {
+        db: vec![def("dirt", "Dirt"), def("torch", "Torch")],
+    };
+    db.merge(ItemDb {
+        db: vec![def("torch", "Better torch"), def("rope", "Rope")],
+    });
+    let names: Vec<_> = db.db.iter().map(|def| def.name.as_str()).collect();
+    assert_eq!(names, ["Dirt", "Better torch", "Rope"]);
+}
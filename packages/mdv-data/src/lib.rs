@@ -1,7 +1,11 @@
 pub mod char;
 pub mod item;
+pub mod palette;
+pub mod prefab;
 pub mod recipe;
 pub mod tile;
+pub mod validate;
+pub mod worldgen;
 
 use {
     ron::{extensions::Extensions, ser::PrettyConfig},
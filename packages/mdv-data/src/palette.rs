@@ -0,0 +1,137 @@
+use {
+    crate::{
+        item::{ItemDb, ItemId},
+        tile::{TileDb, TileId},
+    },
+    serde::{Deserialize, Serialize},
+};
+
+/// The keys of the items and tiles a world was saved with.
+///
+/// Index `n` holds the key of id `n + 1`, as id 0 is always the empty id.
+/// Comparing this against the palette of the currently loaded databases tells us
+/// how to remap the ids in inventories and region files.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    pub items: Vec<String>,
+    pub bg: Vec<String>,
+    pub mid: Vec<String>,
+}
+
+impl Palette {
+    pub fn from_dbs(itemdb: &ItemDb, tiledb: &TileDb) -> Self {
+        Self {
+            items: itemdb.db.iter().map(|def| def.key.clone()).collect(),
+            bg: tiledb.bg.iter().map(|def| def.key.clone()).collect(),
+            mid: tiledb.mid.iter().map(|def| def.key.clone()).collect(),
+        }
+    }
+    /// Calculate how to get from the ids of this (saved) palette to the ids of `current`
+    pub fn remap_to(&self, current: &Palette) -> Remap {
+        Remap {
+            items: IdRemap::new(&self.items, &current.items, "item"),
+            bg: IdRemap::new(&self.bg, &current.bg, "bg tile"),
+            mid: IdRemap::new(&self.mid, &current.mid, "mid tile"),
+        }
+    }
+}
+
+/// Old id -> new id mappings for every kind of id
+#[derive(Debug)]
+pub struct Remap {
+    pub items: IdRemap,
+    pub bg: IdRemap,
+    pub mid: IdRemap,
+}
+
+impl Remap {
+    pub fn is_identity(&self) -> bool {
+        self.items.is_identity() && self.bg.is_identity() && self.mid.is_identity()
+    }
+}
+
+/// Old id -> new id mapping, indexed by the old id
+#[derive(Debug)]
+pub struct IdRemap(Vec<u16>);
+
+impl IdRemap {
+    #[expect(
+        clippy::cast_possible_truncation,
+        reason = "We won't have more than 65535 items or tiles"
+    )]
+    fn new(old: &[String], new: &[String], kind: &str) -> Self {
+        let mut map = vec![0];
+        for key in old {
+            match new
+                .iter()
+                .position(|new_key| !key.is_empty() && new_key == key)
+            {
+                Some(idx) => map.push(idx as u16 + 1),
+                None => {
+                    log::warn!("{kind} '{key}' no longer exists. Replacing it with empty.");
+                    map.push(0);
+                }
+            }
+        }
+        Self(map)
+    }
+    /// Ids unknown to the old palette are left alone
+    pub fn get(&self, old: u16) -> u16 {
+        self.0.get(usize::from(old)).copied().unwrap_or(old)
+    }
+    pub fn tile<Layer>(&self, old: TileId<Layer>) -> TileId<Layer> {
+        TileId::from_idx(self.get(old.0))
+    }
+    pub fn item(&self, old: ItemId) -> ItemId {
+        ItemId(self.get(old.0))
+    }
+    pub fn is_identity(&self) -> bool {
+        self.0
+            .iter()
+            .enumerate()
+            .all(|(old, &new)| usize::from(new) == old)
+    }
+}
+
+#[test]
+fn test_remap_identity() {
+    let pal = Palette {
+        items: vec!["dirt".into(), "torch".into()],
+        bg: vec!["dirtback".into()],
+        mid: vec!["dirt".into(), "stone".into()],
+    };
+    assert!(pal.remap_to(&pal.clone()).is_identity());
+}
+
+#[test]
+fn test_remap_reorder_and_delete() {
+    let old = Palette {
+        items: vec!["dirt".into(), "torch".into(), "coal".into()],
+        ..Default::default()
+    };
+    let new = Palette {
+        items: vec!["coal".into(), "pick".into(), "dirt".into()],
+        ..Default::default()
+    };
+    let remap = old.remap_to(&new);
+    assert!(!remap.is_identity());
+    assert_eq!(remap.items.get(0), 0);
+    assert_eq!(remap.items.get(1), 3);
+    assert_eq!(remap.items.get(2), 0);
+    assert_eq!(remap.items.get(3), 1);
+    // Not part of the old palette
+    assert_eq!(remap.items.get(4), 4);
+}
+
+#[test]
+fn test_remap_appended_is_identity() {
+    let old = Palette {
+        mid: vec!["dirt".into(), "stone".into()],
+        ..Default::default()
+    };
+    let new = Palette {
+        mid: vec!["dirt".into(), "stone".into(), "torch".into()],
+        ..Default::default()
+    };
+    assert!(old.remap_to(&new).is_identity());
+}
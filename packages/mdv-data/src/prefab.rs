@@ -0,0 +1,213 @@
+use {
+    crate::{
+        item::{ItemId, ItemStack},
+        palette::Remap,
+        ron_pretty_cfg,
+        tile::{BgTileId, MidTileId},
+        LoadError,
+    },
+    serde::{Deserialize, Serialize},
+    std::path::Path,
+};
+
+/// Directory of the prefabs inside a data directory, with one `.ron` file per prefab
+pub const DIR: &str = "prefabs";
+/// Tiles of a prefab with this character leave the world as it is
+pub const KEEP: char = '.';
+
+/// A hand-made structure stamped into the world, like a ruin or an abandoned mineshaft
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PrefabDef {
+    /// Name of the file, without extension. Data packs replace prefabs with the same name.
+    #[serde(skip)]
+    pub name: String,
+    /// What the characters of [`Self::rows`] (or the colors of [`Self::image`]) stand for
+    pub legend: Vec<PrefabKey>,
+    /// The tiles from top to bottom, one character per tile
+    #[serde(default)]
+    pub rows: Vec<String>,
+    /// Image next to the prefab file to fill [`Self::rows`] from instead, one pixel per tile.
+    ///
+    /// Pixels are matched to the legend by color. Transparent pixels leave the world as it is.
+    #[serde(default)]
+    pub image: Option<String>,
+    /// Items placed into the containers of the prefab
+    #[serde(default)]
+    pub containers: Vec<PrefabContainer>,
+    pub placement: Placement,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PrefabKey {
+    pub ch: char,
+    /// Color of the pixels standing for this key in prefab images
+    #[serde(default)]
+    pub color: Option<[u8; 3]>,
+    /// Keeps the background of the world if not set
+    #[serde(default)]
+    pub bg: Option<BgTileId>,
+    /// Keeps the mid tile of the world if not set. The empty tile (0) clears it.
+    #[serde(default)]
+    pub mid: Option<MidTileId>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PrefabContainer {
+    /// Position of the container tile in the prefab (column, row)
+    pub pos: [u16; 2],
+    pub items: Vec<ItemStack>,
+}
+
+/// Where and how often a prefab is placed
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Placement {
+    /// Depth range (in meters below the surface) of the bottom of the prefab.
+    ///
+    /// Negative depths are above the surface.
+    pub depth: [f32; 2],
+    pub frequency: Frequency,
+    /// Move the prefab down onto the first floor below where it would go,
+    /// and leave it out if there's none near
+    #[serde(default)]
+    pub on_floor: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum Frequency {
+    /// On average this many in a cell of 128x128 tiles within the depth range
+    PerCell(f32),
+    /// Exactly one in the world, at most `spread` meters to either side of the center
+    Unique { spread: f32 },
+}
+
+impl PrefabDef {
+    /// Load all the prefabs in the [`DIR`] directory of `data_path`, sorted by name.
+    ///
+    /// Images aren't loaded, see [`Self::rows_from_image`].
+    /// A missing directory just means there are no prefabs.
+    pub fn load_dir(data_path: impl AsRef<Path>) -> Result<Vec<Self>, LoadError> {
+        let dir = data_path.as_ref().join(DIR);
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut prefabs = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "ron") {
+                continue;
+            }
+            let mut prefab: Self = ron::from_str(&std::fs::read_to_string(&path)?)?;
+            prefab.name = path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into();
+            prefabs.push(prefab);
+        }
+        prefabs.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(prefabs)
+    }
+    /// Save into the [`DIR`] directory of `data_path`, with the tiles in [`Self::rows`]
+    /// instead of an image
+    pub fn save(&self, data_path: impl AsRef<Path>) -> Result<(), LoadError> {
+        let dir = data_path.as_ref().join(DIR);
+        std::fs::create_dir_all(&dir)?;
+        let prefab = Self {
+            image: None,
+            ..self.clone()
+        };
+        let s = ron::ser::to_string_pretty(&prefab, ron_pretty_cfg())?;
+        std::fs::write(dir.join(format!("{}.ron", self.name)), s.as_bytes())?;
+        Ok(())
+    }
+    pub fn width(&self) -> usize {
+        self.rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0)
+    }
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+    /// Remap the tile and item ids after the databases changed
+    pub fn remap_ids(&mut self, remap: &Remap) {
+        for key in &mut self.legend {
+            key.bg = key.bg.map(|id| remap.bg.tile(id));
+            key.mid = key.mid.map(|id| remap.mid.tile(id));
+        }
+        for stack in self.containers.iter_mut().flat_map(|c| &mut c.items) {
+            stack.id = remap.items.item(stack.id);
+            if stack.id == ItemId::EMPTY {
+                stack.qty = 0;
+            }
+        }
+    }
+    pub fn key(&self, ch: char) -> Option<&PrefabKey> {
+        self.legend.iter().find(|key| key.ch == ch)
+    }
+    /// The legend key of the tile at (`x`, `y`), if the tile changes the world
+    pub fn key_at(&self, x: usize, y: usize) -> Option<&PrefabKey> {
+        self.key(self.rows.get(y)?.chars().nth(x)?)
+    }
+    /// Fill [`Self::rows`] from the RGBA `pixels` of an image `width` pixels wide
+    pub fn rows_from_image(&mut self, width: usize, pixels: &[u8]) -> Result<(), String> {
+        if width == 0 {
+            return Err(format!("Prefab '{}' has an empty image", self.name));
+        }
+        let mut rows = Vec::new();
+        for line in pixels.chunks(width * 4) {
+            let mut row = String::new();
+            for px in line.chunks_exact(4) {
+                if px[3] == 0 {
+                    row.push(KEEP);
+                    continue;
+                }
+                let color = [px[0], px[1], px[2]];
+                match self.legend.iter().find(|key| key.color == Some(color)) {
+                    Some(key) => row.push(key.ch),
+                    None => {
+                        return Err(format!(
+                            "Prefab '{}' has pixels of color {color:?}, which isn't in the legend",
+                            self.name
+                        ))
+                    }
+                }
+            }
+            rows.push(row);
+        }
+        self.rows = rows;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_rows_from_image() {
+    let key = |ch, color| PrefabKey {
+        ch,
+        color: Some(color),
+        bg: None,
+        mid: Some(MidTileId::EMPTY),
+    };
+    let mut prefab = PrefabDef {
+        name: "test".into(),
+        legend: vec![key('#', [10, 10, 10]), key(' ', [255, 255, 255])],
+        rows: Vec::new(),
+        image: Some("test.png".into()),
+        containers: Vec::new(),
+        placement: Placement {
+            depth: [0.0, 10.0],
+            frequency: Frequency::PerCell(1.0),
+            on_floor: false,
+        },
+    };
+    let (wall, air, clear) = ([10, 10, 10, 255], [255, 255, 255, 255], [0, 0, 0, 0]);
+    let pixels = [wall, air, wall, clear, wall, wall].concat();
+    prefab.rows_from_image(3, &pixels).unwrap();
+    assert_eq!(prefab.rows, ["# #", ".##"]);
+    assert_eq!((prefab.width(), prefab.height()), (3, 2));
+    assert_eq!(prefab.key_at(1, 0).map(|key| key.ch), Some(' '));
+    assert!(prefab.key_at(0, 1).is_none());
+    let unknown = [1, 2, 3, 255];
+    assert!(prefab.rows_from_image(1, &unknown).is_err());
+}
@@ -1,6 +1,7 @@
 use {
-    crate::{item::ItemStack, ron_pretty_cfg},
+    crate::{item::ItemStack, ron_pretty_cfg, LoadError},
     serde::{Deserialize, Serialize},
+    std::path::Path,
 };
 
 /// Crafting station
@@ -28,24 +29,26 @@ pub struct RecipeDb {
 const PATH: &str = "recipes.ron";
 
 impl RecipeDb {
-    pub fn load_or_default(data_path: &str) -> Self {
-        match std::fs::read_to_string(format!("{data_path}/{PATH}")) {
-            Ok(data) => match ron::from_str(&data) {
-                Ok(db) => db,
-                Err(e) => {
-                    log::warn!("Failed to load recipe database: {e}\nCreating default.");
-                    Default::default()
-                }
-            },
+    pub fn load(data_path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        let data = std::fs::read_to_string(data_path.as_ref().join(PATH))?;
+        Ok(ron::from_str(&data)?)
+    }
+    pub fn load_or_default(data_path: impl AsRef<Path>) -> Self {
+        match Self::load(data_path) {
+            Ok(db) => db,
             Err(e) => {
                 log::warn!("Failed to load recipe database: {e}\nCreating default.");
                 Default::default()
             }
         }
     }
-    pub fn try_save(&self, data_path: &str) {
+    /// Recipes don't have keys, so merging just appends the recipes of `other`
+    pub fn merge(&mut self, other: RecipeDb) {
+        self.recipes.extend(other.recipes);
+    }
+    pub fn try_save(&self, data_path: impl AsRef<Path>) {
         match ron::ser::to_string_pretty(self, ron_pretty_cfg()) {
-            Ok(str) => match std::fs::write(format!("{data_path}/{PATH}"), str) {
+            Ok(str) => match std::fs::write(data_path.as_ref().join(PATH), str) {
                 Ok(()) => {}
                 Err(e) => log::warn!("Failed to save recipe db: {e}"),
             },
@@ -1,15 +1,21 @@
 use {
-    crate::{item::ItemId, ron_pretty_cfg},
+    crate::{item::ItemId, ron_pretty_cfg, LoadError},
     mdv_math::types::{IntRect, ScreenVec},
     serde::{Deserialize, Serialize},
     std::{
         marker::PhantomData,
         ops::{Index, RangeInclusive},
+        path::Path,
     },
 };
 
 #[derive(Serialize, Deserialize)]
 pub struct TileDef<Layer: TileLayer> {
+    /// Stable identifier of the tile.
+    ///
+    /// Ids are positional, so saves refer to tiles by this key instead,
+    /// and build.rs generates the tile constants from it.
+    pub key: String,
     /// Whether the tile emits light, and the light source offset
     pub light: Option<ScreenVec>,
     pub graphic_name: String,
@@ -33,6 +39,15 @@ pub struct TileDef<Layer: TileLayer> {
     /// Whether the tile can be "uprooted" by interacting with it (sticks/stones/plants, etc.)
     #[serde(default)]
     pub uprootable: bool,
+    /// Minimum tool tier required to mine this tile
+    #[serde(default)]
+    pub min_tier: u8,
+    /// No tool can mine this tile, like the bedrock at the edges of the world
+    #[serde(default)]
+    pub unbreakable: bool,
+    /// Color of the tile on maps. The average color of its graphic if not set.
+    #[serde(default)]
+    pub map_color: Option<[u8; 3]>,
 }
 impl TileDef<Mid> {
     /// Whether this tile is solid/impassable for entities
@@ -47,6 +62,7 @@ where
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("TileDef")
+            .field("key", &self.key)
             .field("light", &self.light)
             .field("graphic_name", &self.graphic_name)
             .field("tex_rect", &self.tex_rect)
@@ -83,7 +99,9 @@ pub trait TileLayer {
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TileDb {
+    #[serde(default = "Bg::unknown_def")]
     pub unknown_bg: TileDef<Bg>,
+    #[serde(default = "Mid::unknown_def")]
     pub unknown_mid: TileDef<Mid>,
     pub bg: Vec<TileDef<Bg>>,
     pub mid: Vec<TileDef<Mid>>,
@@ -124,6 +142,21 @@ pub struct MidDef {
     pub platform: bool,
     /// Collision bounding box
     pub bb: Option<TileBb>,
+    /// The tile stores items, like a chest
+    #[serde(default)]
+    pub container: Option<ContainerDef>,
+    /// Interacting with the tile makes the player respawn there, like a bed
+    #[serde(default)]
+    pub spawn_anchor: bool,
+    /// Part of a tree. Chopping it fells the tree tiles above it.
+    #[serde(default)]
+    pub tree: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContainerDef {
+    /// Number of inventory slots
+    pub slots: u16,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -132,6 +165,7 @@ pub struct TileId<Layer>(pub u16, #[serde(skip)] pub PhantomData<Layer>);
 impl Bg {
     pub fn unknown_def() -> TileDef<Self> {
         TileDef {
+            key: String::from("unknown_bg"),
             light: Some(ScreenVec { x: 0, y: 0 }),
             graphic_name: String::from("tiles/unknown_bg"),
             tex_rect: IntRect::default(),
@@ -143,6 +177,9 @@ impl Bg {
             item_drop: None,
             blend_list: vec![],
             uprootable: false,
+            min_tier: 0,
+            unbreakable: false,
+            map_color: None,
         }
     }
 }
@@ -150,6 +187,7 @@ impl Bg {
 impl Mid {
     pub fn unknown_def() -> TileDef<Self> {
         TileDef {
+            key: String::from("unknown_mid"),
             light: Some(ScreenVec { x: 0, y: 0 }),
             graphic_name: String::from("tiles/unknown_mid"),
             tex_rect: IntRect::default(),
@@ -162,6 +200,9 @@ impl Mid {
                     w: TILE_SIZE,
                     h: TILE_SIZE,
                 }),
+                container: None,
+                spawn_anchor: false,
+                tree: false,
             },
             neigh_aware: false,
             health: 10.0,
@@ -169,6 +210,9 @@ impl Mid {
             item_drop: None,
             blend_list: vec![],
             uprootable: false,
+            min_tier: 0,
+            unbreakable: false,
+            map_color: None,
         }
     }
 }
@@ -210,6 +254,7 @@ where
 {
     fn default() -> Self {
         Self {
+            key: Default::default(),
             light: Default::default(),
             graphic_name: Default::default(),
             tex_rect: Default::default(),
@@ -221,6 +266,9 @@ where
             item_drop: None,
             blend_list: vec![],
             uprootable: false,
+            min_tier: 0,
+            unbreakable: false,
+            map_color: None,
         }
     }
 }
@@ -247,27 +295,61 @@ impl Index<MidTileId> for TileDb {
     }
 }
 
+#[expect(
+    clippy::cast_possible_truncation,
+    reason = "We don't expect to have more than 65535 tiles"
+)]
+fn id_by_key<Layer: TileLayer>(defs: &[TileDef<Layer>], key: &str) -> Option<TileId<Layer>> {
+    defs.iter()
+        .position(|def| def.key == key)
+        .map(|idx| TileId::from_idx(idx as u16 + 1))
+}
+
+fn merge_defs<Layer: TileLayer>(defs: &mut Vec<TileDef<Layer>>, other: Vec<TileDef<Layer>>) {
+    for def in other {
+        match defs
+            .iter_mut()
+            .find(|existing| !def.key.is_empty() && existing.key == def.key)
+        {
+            Some(existing) => *existing = def,
+            None => defs.push(def),
+        }
+    }
+}
+
 const PATH: &str = "tiles.ron";
 
 impl TileDb {
-    pub fn load_or_default(data_path: &str) -> Self {
-        match std::fs::read_to_string(format!("{data_path}/{PATH}")) {
-            Ok(data) => match ron::from_str(&data) {
-                Ok(db) => db,
-                Err(e) => {
-                    log::warn!("Failed to load tile database: {e}\nCreating default.");
-                    Default::default()
-                }
-            },
+    pub fn bg_id_by_key(&self, key: &str) -> Option<BgTileId> {
+        id_by_key(&self.bg, key)
+    }
+    pub fn mid_id_by_key(&self, key: &str) -> Option<MidTileId> {
+        id_by_key(&self.mid, key)
+    }
+    pub fn load(data_path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        let data = std::fs::read_to_string(data_path.as_ref().join(PATH))?;
+        Ok(ron::from_str(&data)?)
+    }
+    pub fn load_or_default(data_path: impl AsRef<Path>) -> Self {
+        match Self::load(data_path) {
+            Ok(db) => db,
             Err(e) => {
                 log::warn!("Failed to load tile database: {e}\nCreating default.");
                 Default::default()
             }
         }
     }
-    pub fn try_save(&self, data_path: &str) {
+    /// Merge the definitions of `other` into this database.
+    ///
+    /// Definitions with a key that already exists replace the existing definition,
+    /// the rest get appended.
+    pub fn merge(&mut self, other: TileDb) {
+        merge_defs(&mut self.bg, other.bg);
+        merge_defs(&mut self.mid, other.mid);
+    }
+    pub fn try_save(&self, data_path: impl AsRef<Path>) {
         match ron::ser::to_string_pretty(self, ron_pretty_cfg()) {
-            Ok(str) => match std::fs::write(format!("{data_path}/{PATH}"), str) {
+            Ok(str) => match std::fs::write(data_path.as_ref().join(PATH), str) {
                 Ok(()) => {}
                 Err(e) => log::warn!("Failed to save tile db: {e}"),
             },
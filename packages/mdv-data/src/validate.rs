@@ -0,0 +1,272 @@
+use {
+    crate::{
+        item::{ItemDb, ItemId, UseAction},
+        prefab::{PrefabDef, KEEP},
+        recipe::RecipeDb,
+        tile::{TileDb, TileDef, TileLayer},
+        worldgen::{DecorationKind, WorldGenDb},
+    },
+    std::collections::HashSet,
+};
+
+/// Check the databases for duplicate keys and ids that point nowhere.
+///
+/// Returns a description of every problem found.
+pub fn validate(tile_db: &TileDb, item_db: &ItemDb, recipe_db: &RecipeDb) -> Vec<String> {
+    let mut problems = Vec::new();
+    check_keys("item", item_db.db.iter().map(|def| &def.key), &mut problems);
+    check_keys(
+        "bg tile",
+        tile_db.bg.iter().map(|def| &def.key),
+        &mut problems,
+    );
+    check_keys(
+        "mid tile",
+        tile_db.mid.iter().map(|def| &def.key),
+        &mut problems,
+    );
+    let item_ok = |id: ItemId| usize::from(id.0) <= item_db.db.len();
+    for def in &item_db.db {
+        for action in [&def.use1, &def.use2] {
+            match action {
+                UseAction::PlaceBgTile { id: tile_id }
+                    if usize::from(tile_id.0) > tile_db.bg.len() =>
+                {
+                    problems.push(format!(
+                        "Item '{}' places invalid bg tile {}",
+                        def.key, tile_id.0
+                    ));
+                }
+                UseAction::PlaceMidTile { id: tile_id }
+                    if usize::from(tile_id.0) > tile_db.mid.len() =>
+                {
+                    problems.push(format!(
+                        "Item '{}' places invalid mid tile {}",
+                        def.key, tile_id.0
+                    ));
+                }
+                UseAction::Shoot { ammo, .. } if !item_ok(*ammo) => {
+                    problems.push(format!("Item '{}' shoots invalid item {}", def.key, ammo.0));
+                }
+                UseAction::Script { name } if name.is_empty() => {
+                    problems.push(format!("Item '{}' runs a script without a name", def.key));
+                }
+                _ => {}
+            }
+        }
+    }
+    check_drops(&tile_db.bg, item_ok, &mut problems);
+    check_drops(&tile_db.mid, item_ok, &mut problems);
+    for (i, recipe) in recipe_db.recipes.iter().enumerate() {
+        for stack in recipe.input.iter().chain(std::iter::once(&recipe.output)) {
+            if !item_ok(stack.id) {
+                problems.push(format!("Recipe {i} refers to invalid item {}", stack.id.0));
+            }
+        }
+    }
+    problems
+}
+
+/// Check the world generator for unsorted strata, unknown biomes and tile ids that point nowhere
+pub fn validate_worldgen(worldgen: &WorldGenDb, tile_db: &TileDb) -> Vec<String> {
+    let mut problems = Vec::new();
+    if !worldgen.strata.is_sorted_by(|a, b| a.top <= b.top) {
+        problems.push("World generation strata aren't sorted by depth".into());
+    }
+    let mid_ok = |id: u16| usize::from(id) <= tile_db.mid.len();
+    let bg_ok = |id: u16| usize::from(id) <= tile_db.bg.len();
+    for stratum in &worldgen.strata {
+        let name = &stratum.name;
+        if !mid_ok(stratum.rock.0) {
+            problems.push(format!(
+                "Stratum '{name}' has invalid rock {}",
+                stratum.rock.0
+            ));
+        }
+        if !bg_ok(stratum.bg.0) {
+            problems.push(format!("Stratum '{name}' has invalid bg {}", stratum.bg.0));
+        }
+        for deposit in &stratum.deposits {
+            if !mid_ok(deposit.mid.0) {
+                problems.push(format!(
+                    "Stratum '{name}' has a deposit of invalid mid tile {}",
+                    deposit.mid.0
+                ));
+            }
+            match deposit.bg {
+                Some(bg) if !bg_ok(bg.0) => problems.push(format!(
+                    "Stratum '{name}' has a deposit with invalid bg tile {}",
+                    bg.0
+                )),
+                _ => {}
+            }
+        }
+    }
+    for biome in &worldgen.biomes {
+        let name = &biome.name;
+        let swapped = biome.swaps.iter().flat_map(|swap| [swap.from, swap.to]);
+        let decorations = biome
+            .decorations
+            .iter()
+            .flat_map(|decoration| match decoration.kind {
+                DecorationKind::Tile(id) => vec![id],
+                DecorationKind::Tree { trunk, leaves, .. } => vec![trunk, leaves],
+            });
+        for id in std::iter::once(biome.surface)
+            .chain(swapped)
+            .chain(decorations)
+        {
+            if !mid_ok(id.0) {
+                problems.push(format!(
+                    "Biome '{name}' refers to invalid mid tile {}",
+                    id.0
+                ));
+            }
+        }
+        for decoration in &biome.decorations {
+            match decoration.kind {
+                DecorationKind::Tree { height, .. } if height[0] > height[1] => {
+                    problems.push(format!(
+                        "Biome '{name}' has a tree with a height range of {}..={}",
+                        height[0], height[1]
+                    ));
+                }
+                _ => {}
+            }
+        }
+        let chances: f32 = biome.decorations.iter().map(|d| d.chance).sum();
+        if chances > 1.0 {
+            problems.push(format!(
+                "Biome '{name}' has decoration chances adding up to {chances}, more than 1"
+            ));
+        }
+    }
+    if let Some(bedrock) = &worldgen.bedrock {
+        if !mid_ok(bedrock.mid.0) || !bg_ok(bedrock.bg.0) {
+            problems.push("Bedrock refers to an invalid tile".into());
+        } else if bedrock.mid.empty() || !tile_db[bedrock.mid].unbreakable {
+            problems.push("Bedrock isn't made of an unbreakable tile".into());
+        }
+        if bedrock.depth < 0.0 || bedrock.wall < 0.0 || bedrock.roughness < 0.0 {
+            problems.push("Bedrock has a negative depth, wall or roughness".into());
+        }
+    }
+    for prefab in &worldgen.prefabs {
+        validate_prefab(prefab, mid_ok, bg_ok, &mut problems);
+    }
+    let layout = &worldgen.layout;
+    let spans = std::iter::once(&layout.center)
+        .chain(&layout.side_a)
+        .chain(&layout.side_b);
+    // Without a center, there's no layout at all, and the world has no biomes
+    for span in spans.filter(|_| !layout.center.biome.is_empty()) {
+        if worldgen.biome_idx_by_name(&span.biome).is_none() {
+            problems.push(format!(
+                "Biome layout refers to unknown biome '{}'",
+                span.biome
+            ));
+        }
+    }
+    problems
+}
+
+fn validate_prefab(
+    prefab: &PrefabDef,
+    mid_ok: impl Fn(u16) -> bool,
+    bg_ok: impl Fn(u16) -> bool,
+    problems: &mut Vec<String>,
+) {
+    let name = &prefab.name;
+    if prefab.rows.is_empty() {
+        problems.push(format!("Prefab '{name}' has no tiles"));
+    }
+    let mut seen = HashSet::new();
+    for key in &prefab.legend {
+        if key.ch == KEEP || !seen.insert(key.ch) {
+            problems.push(format!(
+                "Prefab '{name}' has a duplicate legend key '{}'",
+                key.ch
+            ));
+        }
+        if key.mid.is_some_and(|id| !mid_ok(id.0)) || key.bg.is_some_and(|id| !bg_ok(id.0)) {
+            problems.push(format!(
+                "Prefab '{name}' legend key '{}' refers to an invalid tile",
+                key.ch
+            ));
+        }
+    }
+    let unknown = prefab
+        .rows
+        .iter()
+        .flat_map(|row| row.chars())
+        .find(|&ch| ch != KEEP && prefab.key(ch).is_none());
+    if let Some(ch) = unknown {
+        problems.push(format!(
+            "Prefab '{name}' uses '{ch}', which isn't in the legend"
+        ));
+    }
+    for container in &prefab.containers {
+        let [x, y] = container.pos;
+        let key = prefab.key_at(usize::from(x), usize::from(y));
+        if key.is_none_or(|key| key.mid.is_none_or(|mid| mid.empty())) {
+            problems.push(format!(
+                "Prefab '{name}' has a container at {x},{y}, where it places no tile"
+            ));
+        }
+    }
+    let [top, bottom] = prefab.placement.depth;
+    if top > bottom {
+        problems.push(format!(
+            "Prefab '{name}' has a depth range of {top}..{bottom}"
+        ));
+    }
+}
+
+fn check_keys<'a>(kind: &str, keys: impl Iterator<Item = &'a String>, problems: &mut Vec<String>) {
+    let mut seen = HashSet::new();
+    for key in keys {
+        if key.is_empty() {
+            problems.push(format!("A {kind} has an empty key"));
+        } else if !seen.insert(key) {
+            problems.push(format!("Duplicate {kind} key '{key}'"));
+        }
+    }
+}
+
+fn check_drops<Layer: TileLayer>(
+    defs: &[TileDef<Layer>],
+    item_ok: impl Fn(ItemId) -> bool,
+    problems: &mut Vec<String>,
+) {
+    for def in defs {
+        if let Some(drop) = &def.item_drop {
+            if !item_ok(drop.id) {
+                problems.push(format!(
+                    "Tile '{}' drops invalid item {}",
+                    def.key, drop.id.0
+                ));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_validate_catches_dangling_ids() {
+    use crate::{
+        item::ItemStack,
+        recipe::{Recipe, Station},
+    };
+    let recipe_db = RecipeDb {
+        recipes: vec![Recipe {
+            stations: vec![Station::Player],
+            input: vec![],
+            output: ItemStack {
+                id: ItemId(3),
+                qty: 1,
+                wear: 0,
+            },
+        }],
+    };
+    let problems = validate(&TileDb::default(), &ItemDb::default(), &recipe_db);
+    assert_eq!(problems, ["Recipe 0 refers to invalid item 3"]);
+}
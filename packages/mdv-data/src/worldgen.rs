@@ -0,0 +1,546 @@
+use {
+    crate::{
+        palette::Remap,
+        prefab::PrefabDef,
+        ron_pretty_cfg,
+        tile::{BgTileId, MidTileId},
+        LoadError,
+    },
+    mdv_math::noise::{hash, hash_f32, NoiseParams},
+    serde::{Deserialize, Serialize},
+    std::path::Path,
+};
+
+/// Parameters of the world generator
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WorldGenDb {
+    /// Depth bands from the surface down, sorted by [`Stratum::top`]
+    pub strata: Vec<Stratum>,
+    /// How far (in meters) neighboring strata and biomes reach into each other at their boundary
+    pub blend: f32,
+    /// Noise that decides how far the boundaries are shifted within the blend distance
+    pub blend_noise: NoiseParams,
+    /// Shape of the surface
+    #[serde(default)]
+    pub terrain: Terrain,
+    /// Surface biomes, referred to by name from [`Self::layout`]
+    #[serde(default)]
+    pub biomes: Vec<BiomeDef>,
+    #[serde(default)]
+    pub layout: BiomeLayout,
+    /// The unbreakable bottom and sides of the world
+    #[serde(default)]
+    pub bedrock: Option<Bedrock>,
+    /// Hand-made structures, loaded from their own files (see [`crate::prefab::DIR`])
+    #[serde(skip)]
+    pub prefabs: Vec<PrefabDef>,
+}
+
+impl Default for WorldGenDb {
+    fn default() -> Self {
+        Self {
+            strata: Vec::new(),
+            blend: 8.0,
+            blend_noise: NoiseParams::default(),
+            terrain: Terrain::default(),
+            biomes: Vec::new(),
+            layout: BiomeLayout::default(),
+            bedrock: None,
+            prefabs: Vec::new(),
+        }
+    }
+}
+
+/// Hills and valleys of the surface
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Terrain {
+    /// Noise that shapes the hills along the surface
+    pub hills: NoiseParams,
+    /// How far (in meters) the highest hills rise above, and the deepest valleys sink below
+    /// the base surface level
+    pub height: f32,
+}
+
+impl Default for Terrain {
+    fn default() -> Self {
+        Self {
+            hills: NoiseParams {
+                scale: 256.0,
+                octaves: 4,
+                persistence: 0.5,
+            },
+            height: 0.0,
+        }
+    }
+}
+
+/// Unbreakable rock filling the world below a depth, and the columns at its left and right edges.
+///
+/// Keeps everything inside of the world.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Bedrock {
+    /// Should be an unbreakable tile
+    pub mid: MidTileId,
+    pub bg: BgTileId,
+    /// Depth (in meters below the base surface level) where the bedrock begins
+    pub depth: f32,
+    /// Thickness (in meters) of the walls at the left and right edges of the world
+    pub wall: f32,
+    /// How far (in meters) the ragged edge of the bedrock reaches into the world at most
+    pub roughness: f32,
+}
+
+/// A depth band with its own rock and deposits
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Stratum {
+    /// Unique name of the stratum. Data packs replace strata with the same name.
+    pub name: String,
+    /// Depth below the surface (in meters) where this stratum begins.
+    ///
+    /// It ends where the next stratum begins.
+    pub top: f32,
+    /// The mid tile that fills the stratum
+    pub rock: MidTileId,
+    /// The background tile behind everything in the stratum
+    pub bg: BgTileId,
+    /// Applied in order over the rock, so later deposits win over earlier ones
+    #[serde(default)]
+    pub deposits: Vec<Deposit>,
+    /// Caves starting in this stratum
+    #[serde(default)]
+    pub caves: Caves,
+}
+
+/// Blobs of a tile scattered around a stratum, like ores, pockets of dirt, or empty space
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Deposit {
+    /// The tile that makes up the deposit. The empty tile (0) carves out space.
+    pub mid: MidTileId,
+    /// Replaces the background of the stratum, if set
+    #[serde(default)]
+    pub bg: Option<BgTileId>,
+    pub noise: NoiseParams,
+    /// The deposit is placed where the noise (0..1) is above this.
+    ///
+    /// Higher values make for rarer, smaller deposits.
+    pub threshold: f32,
+}
+
+/// A horizontal stretch of the world with its own surface, and an underground variant below it
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BiomeDef {
+    /// Unique name of the biome. Data packs replace biomes with the same name.
+    pub name: String,
+    /// The topmost tile of the ground
+    pub surface: MidTileId,
+    /// What grows or lies on top of the ground. Each column of the surface gets at most one.
+    #[serde(default)]
+    pub decorations: Vec<Decoration>,
+    /// Tiles of the strata that are replaced with biome specific ones, like dirt with sand
+    #[serde(default)]
+    pub swaps: Vec<TileSwap>,
+    /// How deep (in meters) the underground variant of the biome reaches.
+    ///
+    /// Below it, only the strata decide what the world looks like.
+    pub depth: f32,
+    /// Drawn behind the surface. Key of an image in a `bg` resource directory, like `bg/sky`.
+    pub background: String,
+    /// Key of the music played on the surface, like `music/calm`
+    pub music: String,
+    /// Key of the music played in the underground variant
+    pub underground_music: String,
+}
+
+impl BiomeDef {
+    /// Pick the decoration for a `roll` in the 0..1 range.
+    ///
+    /// Each decoration takes up its chance worth of the range, the rest is left bare.
+    pub fn pick_decoration(&self, roll: f32) -> Option<&Decoration> {
+        let mut acc = 0.0;
+        self.decorations.iter().find(|decoration| {
+            acc += decoration.chance;
+            roll < acc
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct TileSwap {
+    pub from: MidTileId,
+    pub to: MidTileId,
+}
+
+/// Something growing or lying on top of the ground
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Decoration {
+    /// Chance (0..1) of a column of the surface getting this decoration
+    pub chance: f32,
+    pub kind: DecorationKind,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum DecorationKind {
+    /// A single tile, like grass, a flower, or a rock
+    Tile(MidTileId),
+    /// A trunk with a round canopy of leaves on top
+    Tree {
+        trunk: MidTileId,
+        leaves: MidTileId,
+        /// Shortest and tallest trunk, in tiles
+        height: [u8; 2],
+        /// Radius of the canopy, in tiles
+        canopy: u8,
+    },
+}
+
+/// Horizontal arrangement of the surface biomes, from the center of the world outwards
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BiomeLayout {
+    /// The biome in the middle of the world
+    pub center: BiomeSpan,
+    /// Biomes on one side of the center, going outwards.
+    ///
+    /// The world seed decides which side. The last biome reaches the edge of the world.
+    pub side_a: Vec<BiomeSpan>,
+    /// Biomes on the opposite side of [`Self::side_a`]
+    pub side_b: Vec<BiomeSpan>,
+    /// How much (0..1) the width of each span varies from world to world
+    pub jitter: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BiomeSpan {
+    /// Name of the [`BiomeDef`]
+    pub biome: String,
+    /// Width in meters, before jitter
+    pub width: f32,
+}
+
+/// Caves carved out of the world after the strata are filled in.
+///
+/// Caves start in cells of 128x128 tiles. A cave can reach at most 2 cells away from the
+/// cell it starts in, and gets cut off there.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Caves {
+    #[serde(default)]
+    pub worms: Option<Worms>,
+    #[serde(default)]
+    pub caverns: Option<Caverns>,
+    #[serde(default)]
+    pub shafts: Option<Shafts>,
+}
+
+/// Long, winding tunnels
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Worms {
+    /// Average number of worms starting in a cell
+    pub per_cell: f32,
+    /// Length in steps. Each step moves about half the radius forward.
+    pub length: u16,
+    /// Radius of the tunnel in tiles
+    pub radius: f32,
+    /// Most the direction can turn in one step, in radians
+    pub turn: f32,
+}
+
+/// Large, roughly elliptical rooms
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Caverns {
+    /// Average number of caverns in a cell
+    pub per_cell: f32,
+    /// Largest width and height in tiles. Caverns are at least half as big.
+    pub size: [f32; 2],
+}
+
+/// Vertical shafts going down
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Shafts {
+    /// Average number of shafts starting in a cell
+    pub per_cell: f32,
+    /// Longest length in tiles. Shafts are at least half as long.
+    pub length: u16,
+    /// Width in tiles
+    pub width: f32,
+}
+
+const PATH: &str = "worldgen.ron";
+
+impl WorldGenDb {
+    pub fn load(data_path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        let data = std::fs::read_to_string(data_path.as_ref().join(PATH))?;
+        Ok(ron::from_str(&data)?)
+    }
+    /// Save into `data_path`, along with the prefabs.
+    ///
+    /// The prefabs are saved with their tiles in rows, so they don't need their images anymore.
+    pub fn save(&self, data_path: impl AsRef<Path>) -> Result<(), LoadError> {
+        let data_path = data_path.as_ref();
+        let s = ron::ser::to_string_pretty(self, ron_pretty_cfg())?;
+        std::fs::write(data_path.join(PATH), s.as_bytes())?;
+        for prefab in &self.prefabs {
+            prefab.save(data_path)?;
+        }
+        Ok(())
+    }
+    pub fn load_or_default(data_path: impl AsRef<Path>) -> Self {
+        match Self::load(data_path) {
+            Ok(db) => db,
+            Err(e) => {
+                log::warn!("Failed to load world generation database: {e}\nCreating default.");
+                Default::default()
+            }
+        }
+    }
+    /// Strata, biomes and prefabs of `other` replace the ones with the same name,
+    /// the rest get added.
+    ///
+    /// The biome layout and the bedrock are replaced if `other` has them.
+    /// The blending parameters of the base game are kept.
+    pub fn merge(&mut self, other: WorldGenDb) {
+        for stratum in other.strata {
+            match self.strata.iter_mut().find(|s| s.name == stratum.name) {
+                Some(existing) => *existing = stratum,
+                None => self.strata.push(stratum),
+            }
+        }
+        self.strata.sort_by(|a, b| a.top.total_cmp(&b.top));
+        for biome in other.biomes {
+            match self.biomes.iter_mut().find(|b| b.name == biome.name) {
+                Some(existing) => *existing = biome,
+                None => self.biomes.push(biome),
+            }
+        }
+        if !other.layout.center.biome.is_empty() {
+            self.layout = other.layout;
+        }
+        if other.bedrock.is_some() {
+            self.bedrock = other.bedrock;
+        }
+        self.merge_prefabs(other.prefabs);
+    }
+    /// Remap the tile and item ids after the databases changed, like for the parameters
+    /// saved with a world
+    pub fn remap_ids(&mut self, remap: &Remap) {
+        let (bg, mid) = (&remap.bg, &remap.mid);
+        for stratum in &mut self.strata {
+            stratum.rock = mid.tile(stratum.rock);
+            stratum.bg = bg.tile(stratum.bg);
+            for deposit in &mut stratum.deposits {
+                deposit.mid = mid.tile(deposit.mid);
+                deposit.bg = deposit.bg.map(|id| bg.tile(id));
+            }
+        }
+        for biome in &mut self.biomes {
+            biome.surface = mid.tile(biome.surface);
+            for decoration in &mut biome.decorations {
+                match &mut decoration.kind {
+                    DecorationKind::Tile(id) => *id = mid.tile(*id),
+                    DecorationKind::Tree { trunk, leaves, .. } => {
+                        *trunk = mid.tile(*trunk);
+                        *leaves = mid.tile(*leaves);
+                    }
+                }
+            }
+            for swap in &mut biome.swaps {
+                swap.from = mid.tile(swap.from);
+                swap.to = mid.tile(swap.to);
+            }
+        }
+        if let Some(bedrock) = &mut self.bedrock {
+            bedrock.mid = mid.tile(bedrock.mid);
+            bedrock.bg = bg.tile(bedrock.bg);
+        }
+        for prefab in &mut self.prefabs {
+            prefab.remap_ids(remap);
+        }
+    }
+    /// Prefabs replace the ones with the same name, the rest get added
+    pub fn merge_prefabs(&mut self, prefabs: Vec<PrefabDef>) {
+        for prefab in prefabs {
+            match self.prefabs.iter_mut().find(|p| p.name == prefab.name) {
+                Some(existing) => *existing = prefab,
+                None => self.prefabs.push(prefab),
+            }
+        }
+        self.prefabs.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    /// Index of the stratum at `depth` meters below the surface.
+    ///
+    /// Depths above the first stratum count as the first stratum.
+    /// Returns `None` if there are no strata.
+    pub fn stratum_idx_at(&self, depth: f32) -> Option<usize> {
+        if self.strata.is_empty() {
+            return None;
+        }
+        Some(
+            self.strata
+                .partition_point(|s| s.top <= depth)
+                .saturating_sub(1),
+        )
+    }
+    pub fn biome_idx_by_name(&self, name: &str) -> Option<usize> {
+        self.biomes.iter().position(|b| b.name == name)
+    }
+    /// Index of the surface biome `x` meters from the center of the world
+    /// (negative is left of the center).
+    ///
+    /// Returns `None` if the layout refers to a biome that doesn't exist.
+    pub fn biome_idx_at(&self, seed: u32, x: f32) -> Option<usize> {
+        let layout = &self.layout;
+        // Salts, so the jitter of different spans isn't the same
+        const SIDE_A: u32 = 0xa;
+        const SIDE_B: u32 = 0xb;
+        let jittered = |span: &BiomeSpan, side: u32, i: u32| {
+            let rand = hash_f32(seed, side, i);
+            span.width * (1.0 + (rand * 2.0 - 1.0) * layout.jitter)
+        };
+        let a_is_left = hash(seed, SIDE_A, SIDE_B).is_multiple_of(2);
+        let (side, salt) = if (x < 0.0) == a_is_left {
+            (&layout.side_a, SIDE_A)
+        } else {
+            (&layout.side_b, SIDE_B)
+        };
+        let dist = x.abs();
+        let mut edge = jittered(&layout.center, 0, 0) / 2.0;
+        let mut span = &layout.center;
+        for (i, side_span) in (0u32..).zip(side) {
+            if dist < edge {
+                break;
+            }
+            span = side_span;
+            edge += jittered(side_span, salt, i);
+        }
+        self.biome_idx_by_name(&span.biome)
+    }
+}
+
+#[test]
+fn test_stratum_idx_at() {
+    let stratum = |name: &str, top| Stratum {
+        name: name.into(),
+        top,
+        rock: MidTileId::EMPTY,
+        bg: BgTileId::EMPTY,
+        deposits: Vec::new(),
+        caves: Caves::default(),
+    };
+    let mut db = WorldGenDb::default();
+    assert_eq!(db.stratum_idx_at(10.0), None);
+    db.strata = vec![stratum("soil", 0.0), stratum("deep", 300.0)];
+    db.merge(WorldGenDb {
+        strata: vec![stratum("mid", 100.0), stratum("soil", 0.0)],
+        ..Default::default()
+    });
+    let names: Vec<_> = db.strata.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(names, ["soil", "mid", "deep"]);
+    assert_eq!(db.stratum_idx_at(-5.0), Some(0));
+    assert_eq!(db.stratum_idx_at(99.9), Some(0));
+    assert_eq!(db.stratum_idx_at(100.0), Some(1));
+    assert_eq!(db.stratum_idx_at(35_000.0), Some(2));
+}
+
+#[test]
+fn test_biome_idx_at() {
+    let biome = |name: &str| BiomeDef {
+        name: name.into(),
+        surface: MidTileId::EMPTY,
+        decorations: Vec::new(),
+        swaps: Vec::new(),
+        depth: 0.0,
+        background: String::new(),
+        music: String::new(),
+        underground_music: String::new(),
+    };
+    let span = |name: &str, width| BiomeSpan {
+        biome: name.into(),
+        width,
+    };
+    let db = WorldGenDb {
+        biomes: ["forest", "jungle", "desert", "ice"].map(biome).into(),
+        layout: BiomeLayout {
+            center: span("forest", 100.0),
+            side_a: vec![span("jungle", 50.0), span("desert", 50.0)],
+            side_b: vec![span("ice", 50.0)],
+            jitter: 0.0,
+        },
+        ..Default::default()
+    };
+    for seed in 0..8 {
+        assert_eq!(db.biome_idx_at(seed, 0.0), Some(0));
+        assert_eq!(db.biome_idx_at(seed, -49.0), Some(0));
+        assert_eq!(db.biome_idx_at(seed, 49.0), Some(0));
+        let jungle_side = if db.biome_idx_at(seed, 60.0) == Some(1) {
+            1.0
+        } else {
+            -1.0
+        };
+        assert_eq!(db.biome_idx_at(seed, jungle_side * 60.0), Some(1));
+        assert_eq!(db.biome_idx_at(seed, jungle_side * 120.0), Some(2));
+        // The last biome of a side reaches the world edge
+        assert_eq!(db.biome_idx_at(seed, jungle_side * 10_000.0), Some(2));
+        assert_eq!(db.biome_idx_at(seed, -jungle_side * 60.0), Some(3));
+        assert_eq!(db.biome_idx_at(seed, -jungle_side * 10_000.0), Some(3));
+    }
+}
+
+#[test]
+fn test_save_load() {
+    let dir = std::env::temp_dir().join(format!("mdv-worldgen-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut prefab: PrefabDef = ron::from_str(
+        "(legend: [(ch: '#', mid: Some(TileId(2)))], rows: [\"#.#\"], image: Some(\"a.png\"), \
+         placement: (depth: (0.0, 10.0), frequency: PerCell(1.0)))",
+    )
+    .unwrap();
+    prefab.name = "arch".into();
+    let db = WorldGenDb {
+        blend: 3.0,
+        prefabs: vec![prefab],
+        ..Default::default()
+    };
+    db.save(&dir).unwrap();
+    let mut loaded = WorldGenDb::load(&dir).unwrap();
+    loaded.merge_prefabs(PrefabDef::load_dir(&dir).unwrap());
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(loaded.blend, 3.0);
+    let prefab = &loaded.prefabs[0];
+    assert_eq!(prefab.name, "arch");
+    assert_eq!(prefab.rows, ["#.#"]);
+    assert!(prefab.image.is_none());
+}
+
+#[test]
+fn test_remap_ids() {
+    use crate::palette::Palette;
+    let mut db: WorldGenDb = ron::from_str(
+        "(strata: [(name: \"stone\", top: 0.0, rock: TileId(2), bg: TileId(1))], blend: 8.0, \
+         blend_noise: (scale: 8.0, octaves: 1))",
+    )
+    .unwrap();
+    db.prefabs.push(
+        ron::from_str(
+            "(legend: [(ch: '#', mid: Some(TileId(1)))], rows: [\"#\"], \
+             containers: [(pos: (0, 0), items: [(id: (1), qty: 3), (id: (2), qty: 1)])], \
+             placement: (depth: (0.0, 10.0), frequency: PerCell(1.0)))",
+        )
+        .unwrap(),
+    );
+    let old = Palette {
+        items: vec!["coal".into(), "torch".into()],
+        bg: vec!["stoneback".into()],
+        mid: vec!["dirt".into(), "stone".into()],
+    };
+    let new = Palette {
+        items: vec!["torch".into(), "coal".into()],
+        bg: vec!["stoneback".into()],
+        mid: vec!["stone".into(), "dirt".into()],
+    };
+    db.remap_ids(&old.remap_to(&new));
+    assert_eq!(db.strata[0].rock, MidTileId::from_idx(1));
+    assert_eq!(db.strata[0].bg, BgTileId::from_idx(1));
+    let prefab = &db.prefabs[0];
+    assert_eq!(prefab.legend[0].mid, Some(MidTileId::from_idx(2)));
+    let items = &prefab.containers[0].items;
+    assert_eq!((items[0].id.0, items[0].qty), (2, 3));
+    assert_eq!((items[1].id.0, items[1].qty), (1, 1));
+}
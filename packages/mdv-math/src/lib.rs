@@ -1,3 +1,4 @@
 pub mod bitmanip;
+pub mod noise;
 pub mod types;
 pub mod util;
@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+
+/// Parameters of a multi-octave (fractal) noise field
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct NoiseParams {
+    /// Size of the largest features, in tiles
+    pub scale: f32,
+    /// How many layers of increasingly finer detail to add
+    pub octaves: u8,
+    /// How much the amplitude of each octave is multiplied by, relative to the previous one
+    #[serde(default = "default_persistence")]
+    pub persistence: f32,
+}
+
+fn default_persistence() -> f32 {
+    0.5
+}
+
+impl Default for NoiseParams {
+    fn default() -> Self {
+        Self {
+            scale: 32.0,
+            octaves: 3,
+            persistence: default_persistence(),
+        }
+    }
+}
+
+impl NoiseParams {
+    /// Sample the noise field at (`x`, `y`). The result is in the 0..1 range.
+    ///
+    /// Only depends on the arguments, so the same position always gives the same value,
+    /// no matter in which order the positions are sampled.
+    pub fn sample(&self, seed: u32, x: f64, y: f64) -> f32 {
+        let mut freq = 1.0 / f64::from(self.scale.max(1.0));
+        let mut amp = 1.0;
+        let mut sum = 0.0;
+        let mut amp_sum = 0.0;
+        for octave in 0..self.octaves.max(1) {
+            let seed = hash(seed, u32::from(octave), 0x5eed);
+            sum += value_noise(seed, x * freq, y * freq) * amp;
+            amp_sum += amp;
+            amp *= self.persistence;
+            freq *= 2.0;
+        }
+        sum / amp_sum
+    }
+}
+
+/// Smoothly interpolated lattice noise in the 0..1 range
+fn value_noise(seed: u32, x: f64, y: f64) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (smooth((x - x0) as f32), smooth((y - y0) as f32));
+    // Lattice coordinates wrap around, which is fine for noise
+    let (ix, iy) = (x0 as i64 as u32, y0 as i64 as u32);
+    let corner = |dx: u32, dy: u32| unit(hash(seed, ix.wrapping_add(dx), iy.wrapping_add(dy)));
+    let top = lerp(corner(0, 0), corner(1, 0), fx);
+    let bottom = lerp(corner(0, 1), corner(1, 1), fx);
+    lerp(top, bottom, fy)
+}
+
+fn smooth(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Map a hash to the 0..1 range
+fn unit(hash: u32) -> f32 {
+    (hash >> 8) as f32 / (1 << 24) as f32
+}
+
+/// Like [`hash`], but mapped to the 0..1 range
+pub fn hash_f32(seed: u32, x: u32, y: u32) -> f32 {
+    unit(hash(seed, x, y))
+}
+
+/// Mix three values into a well distributed hash
+pub fn hash(seed: u32, x: u32, y: u32) -> u32 {
+    let mut h = seed ^ x.wrapping_mul(0x27d4_eb2d) ^ y.wrapping_mul(0x1656_67b1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^ (h >> 16)
+}
+
+#[test]
+fn test_noise_deterministic_and_in_range() {
+    let params = NoiseParams {
+        scale: 16.0,
+        octaves: 4,
+        persistence: 0.5,
+    };
+    let mut min = f32::MAX;
+    let mut max = f32::MIN;
+    for y in 0..64 {
+        for x in 0..64 {
+            let (x, y) = (f64::from(x) + 41_950.0, f64::from(y) * 3.0);
+            let v = params.sample(7, x, y);
+            assert_eq!(v, params.sample(7, x, y));
+            min = min.min(v);
+            max = max.max(v);
+        }
+    }
+    assert!(min >= 0.0 && max <= 1.0);
+    // Should actually vary
+    assert!(max - min > 0.3);
+    assert_ne!(params.sample(7, 3.5, 3.5), params.sample(8, 3.5, 3.5));
+}
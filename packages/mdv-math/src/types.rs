@@ -8,7 +8,7 @@ use {
 pub type ScreenSc = i16;
 
 /// A 2d screen coordinate vector
-#[derive(Default, Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct ScreenVec {
     pub x: ScreenSc,
     pub y: ScreenSc,
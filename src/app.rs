@@ -3,25 +3,30 @@ use {
         audio::AudioCtx,
         command::CmdVec,
         config::Config,
+        data_pack,
         debug::{self, DebugState, DBG_OVR},
         game::{
             rendering::{self, RenderState},
             GameState,
         },
         graphics::{self, ScreenRes},
-        input::Input,
+        input::{gamepad::SfmlJoysticks, Input},
         light::{self, LightState, U16Vec},
         math::{ScreenVecExt, WPosSc, TILE_SIZE, WORLD_EXTENT_PX},
-        player::{Health, PlayerColors},
+        placed::{PlacedEnt, Tombstone},
+        player::{Equipment, Health, PlayerColors},
         res::{Res, ResAudio},
         save::{PlayerSav, Rgb, Save},
-        world::TilePos,
+        status::StatusEffects,
+        survival::Survival,
+        world::{GenVersion, TilePos},
         CliArgs,
     },
     anyhow::Context,
     directories::ProjectDirs,
     egui_sfml::{SfEgui, UserTexSource},
     gamedebug_core::{imm, imm_dbg},
+    mdv_data::palette::Palette,
     mdv_math::types::{ScreenSc, ScreenVec},
     rand::{thread_rng, Rng},
     sfml::{
@@ -86,19 +91,23 @@ impl App {
                 .unwrap_or(cfg.last_world.as_deref().unwrap_or("TestWorld"))
         };
         let wld_path = worlds_dir.join(wld_name);
+        // Music starts playing once the game knows what biome we're in
         let aud = AudioCtx::new();
-        aud.play_music(&res.surf_music);
         let mut debug = DebugState::default();
         if args.debug {
             debug.dbg_overlay = true;
             DBG_OVR.set_enabled(true);
         }
+        let mut input = Input::default();
+        // Actions added since the config was saved keep their default binding
+        input.key_bindings.extend(cfg.key_bindings.clone());
+        input.pad_bindings.extend(cfg.pad_bindings.clone());
         let mut this = Self {
             rw,
             should_quit: false,
-            game: GameState::new(wld_name.to_owned(), wld_path, res),
+            game: GameState::new(wld_name.to_owned(), wld_path, res, GenVersion::LATEST),
             sf_egui,
-            input: Input::default(),
+            input,
             debug,
             scale: cfg.scale,
             light_state: LightState {
@@ -136,50 +145,105 @@ impl App {
                 DBG_OVR.clear();
             }
         }
-        self.game.tile_db.try_save("data");
-        self.game.itemdb.try_save("data");
-        self.game.recipe_db.try_save("data");
-        self.game.char_db.save().unwrap();
-        self.game.world.save();
+        if res.packs.len() > 1 {
+            log::warn!(
+                "Data packs are loaded. Not saving databases, to avoid baking pack data into the base game."
+            );
+        } else {
+            data_pack::save_edited_dbs(
+                &res.packs[0],
+                &self.game.tile_db,
+                &self.game.itemdb,
+                &self.game.recipe_db,
+                &self.game.char_db,
+            );
+        }
+        self.save_game();
         std::fs::create_dir_all(self.project_dirs.config_dir()).unwrap();
         self.cfg.last_world = Some(self.game.world.name.clone());
         self.cfg.scale = self.scale;
         self.cfg.music_vol = self.aud.mus_vol;
         self.cfg.sfx_vol = self.aud.plr.sfx_vol;
+        self.cfg.key_bindings = self
+            .input
+            .key_bindings
+            .iter()
+            .map(|(&action, &binding)| (action, binding))
+            .collect();
+        self.cfg.pad_bindings = self
+            .input
+            .pad_bindings
+            .iter()
+            .map(|(&action, &button)| (action, button))
+            .collect();
         self.cfg.save(self.project_dirs.config_dir()).unwrap();
-        match self
+    }
+
+    /// Save the world and everything about the player in it
+    fn save_game(&mut self) {
+        self.game.world.save();
+        let placed = self
             .game
             .ecw
-            .query_one_mut::<(&mut PlayerColors, &mut Health)>(self.game.player_en)
+            .query_mut::<&PlacedEnt>()
+            .into_iter()
+            .map(|(_en, placed)| placed.clone())
+            .collect();
+        let tombstones = self
+            .game
+            .ecw
+            .query_mut::<&Tombstone>()
+            .into_iter()
+            .map(|(_en, tomb)| tomb.clone())
+            .collect();
+        let player_sav = match self.game.ecw.query_one_mut::<(
+            &mut PlayerColors,
+            &mut Health,
+            &mut Equipment,
+            &Survival,
+            &StatusEffects,
+        )>(self.game.player_en)
         {
-            Ok((colors, health)) => {
-                let player_sav = PlayerSav {
-                    skin_color: Rgb::from_sf(colors.skin),
-                    eye_color: Rgb::from_sf(colors.eye),
-                    hair_color: Rgb::from_sf(colors.hair),
-                    shirt_color: Rgb::from_sf(colors.shirt),
-                    pants_color: Rgb::from_sf(colors.pants),
-                    shoes_color: Rgb::from_sf(colors.shoes),
-                    health: std::mem::replace(
-                        health,
-                        Health {
-                            current: 0.,
-                            max: 0.,
-                            invulnerable: false,
-                        },
-                    ),
-                };
-                let result = Save {
-                    inventory: self.game.inventory,
-                    world_seed: self.game.world.seed,
-                    player: player_sav,
-                    world_ticks: self.game.world.ticks,
-                }
-                .save(&self.game.world.path);
-                log::info!("Save result: {result:?}");
-            }
-            Err(_) => log::error!("No player entity, can't save player data"),
+            Ok((colors, health, equipment, survival, effects)) => PlayerSav {
+                skin_color: Rgb::from_sf(colors.skin),
+                eye_color: Rgb::from_sf(colors.eye),
+                hair_color: Rgb::from_sf(colors.hair),
+                shirt_color: Rgb::from_sf(colors.shirt),
+                pants_color: Rgb::from_sf(colors.pants),
+                shoes_color: Rgb::from_sf(colors.shoes),
+                health: std::mem::replace(
+                    health,
+                    Health {
+                        current: 0.,
+                        max: 0.,
+                        invulnerable: false,
+                        iframes: 0,
+                    },
+                ),
+                equipment: std::mem::take(equipment),
+                survival: survival.clone(),
+                effects: effects.clone(),
+            },
+            // The player of a finished world is gone for good, but the world still gets saved
+            Err(_) if self.game.finished => PlayerSav::finished(),
+            // Dead and waiting to respawn. Save the player they'll respawn as.
+            Err(_) => PlayerSav::respawning(std::mem::take(&mut self.game.respawn_equipment)),
+        };
+        let result = Save {
+            inventory: self.game.inventory.clone(),
+            world_seed: self.game.world.seed,
+            player: player_sav,
+            world_ticks: self.game.world.ticks,
+            palette: Some(Palette::from_dbs(&self.game.itemdb, &self.game.tile_db)),
+            placed,
+            rules: self.game.rules,
+            finished: self.game.finished,
+            tombstones,
+            spawn_anchor: self.game.spawn_anchor,
+            waypoints: self.game.map.waypoints.clone(),
         }
+        .save(&self.game.world.path);
+        log::info!("Save result: {result:?}");
     }
 
     fn do_event_handling(&mut self) {
@@ -217,6 +281,12 @@ impl App {
                 _ => {}
             }
         }
+        let win_size = ScreenVec::from_sf_resolution(self.rw.size());
+        if let Some(pos) = self.input.poll_gamepad(&SfmlJoysticks, win_size) {
+            // Move the real cursor too, so it shows where the gamepad aims
+            self.rw
+                .set_mouse_position(Vector2::new(i32::from(pos.x), i32::from(pos.y)));
+        }
     }
 
     fn adapt_to_window_size_and_scale(&mut self, size: ScreenVec) {
@@ -1,13 +1,22 @@
 use {
     super::App,
     crate::{
-        command::Cmd,
+        command::{Cmd, TpTarget},
+        data_pack::PackDbs,
         game::GameState,
+        item::ItemDbExt,
+        map::TileColors,
         math::{ScreenVecExt, WorldPos, FPS_TARGET},
         player::{Health, MovingEnt},
         res::Res,
+        status::StatusEffects,
         texture_atlas::AtlasBundle,
         tiles::TileDbExt,
+        world::GenVersion,
+    },
+    mdv_data::{
+        palette::Palette,
+        validate::{validate, validate_worldgen},
     },
     mdv_math::types::ScreenVec,
     sfml::{graphics::RenderTarget, window::ContextSettings},
@@ -20,7 +29,7 @@ pub(super) fn dispatch(app: &mut App, res: &mut Res, mouse_world_pos: WorldPos)
         match cmd {
             Cmd::QuitApp => app.should_quit = true,
             Cmd::ToggleFreecam => app.debug.freecam ^= true,
-            Cmd::Teleport { pos, relative } => {
+            Cmd::Teleport(TpTarget::Pos { pos, relative }) => {
                 if let Ok(mov) = app
                     .game
                     .ecw
@@ -35,45 +44,65 @@ pub(super) fn dispatch(app: &mut App, res: &mut Res, mouse_world_pos: WorldPos)
                     }
                 }
             }
-            Cmd::TeleportCursor => {
+            Cmd::Teleport(TpTarget::Waypoint(name)) => {
+                if !cfg!(debug_assertions) {
+                    writeln!(
+                        &mut app.debug.console.log,
+                        "Teleporting to waypoints is only available in debug builds"
+                    )
+                    .unwrap();
+                    continue;
+                }
+                let Some(wp) = app.game.map.waypoints.iter().find(|wp| wp.name == name) else {
+                    writeln!(&mut app.debug.console.log, "No waypoint named '{name}'").unwrap();
+                    continue;
+                };
+                let pos = wp.pos.to_world();
                 if let Ok(mov) = app
                     .game
                     .ecw
                     .query_one_mut::<&mut MovingEnt>(app.game.controlled_en)
                 {
-                    mov.mob.en.pos = mouse_world_pos.to_s2dc();
+                    mov.mob.en.pos = pos.to_s2dc()
                 }
             }
-            Cmd::TeleportSpawn => {
+            Cmd::TeleportCursor => {
                 if let Ok(mov) = app
                     .game
                     .ecw
                     .query_one_mut::<&mut MovingEnt>(app.game.controlled_en)
                 {
-                    mov.mob.en.pos = app.game.spawn_point.to_s2dc()
+                    mov.mob.en.pos = mouse_world_pos.to_s2dc();
                 }
             }
-            Cmd::HurtCtrlEn(amount) => {
-                if let Ok(health) = app
+            Cmd::TeleportSpawn => {
+                let pos = app.game.respawn_pos();
+                if let Ok(mov) = app
                     .game
                     .ecw
-                    .query_one_mut::<&mut Health>(app.game.controlled_en)
+                    .query_one_mut::<&mut MovingEnt>(app.game.controlled_en)
                 {
-                    health.current -= amount;
+                    mov.mob.en.pos = pos.to_s2dc()
                 }
             }
+            Cmd::HurtCtrlEn(amount) => {
+                app.game.hurt(app.game.controlled_en, amount);
+            }
             Cmd::GiveItemByName { name, amount } => {
-                for (id, item) in app.game.itemdb.iter() {
-                    if item.name == name {
-                        app.game.inventory.add(id, amount, &app.game.itemdb);
-                        return;
-                    }
+                if let Some((id, _)) = app
+                    .game
+                    .itemdb
+                    .iter()
+                    .find(|(_, item)| item.name == name || item.key == name)
+                {
+                    app.game.give_item(id, amount);
+                } else {
+                    writeln!(
+                        &mut app.debug.console.log,
+                        "Item with name '{name}' not found"
+                    )
+                    .unwrap();
                 }
-                writeln!(
-                    &mut app.debug.console.log,
-                    "Item with name '{name}' not found"
-                )
-                .unwrap();
             }
             Cmd::ToggleTileDbEdit => app.debug.tiledb_edit.open ^= true,
             Cmd::SetScale(scale) => {
@@ -81,19 +110,42 @@ pub(super) fn dispatch(app: &mut App, res: &mut Res, mouse_world_pos: WorldPos)
                 app.adapt_to_window_size_and_scale(ScreenVec::from_sf_resolution(app.rw.size()));
             }
             Cmd::LoadWorld(name) => {
-                app.game.world.save();
+                app.save_game();
+                let path = app.worlds_dir.join(&name);
+                app.game = GameState::new(name, path, res, GenVersion::LATEST);
+            }
+            Cmd::CreateWorld {
+                name,
+                rules,
+                gen_version,
+            } => {
+                app.save_game();
                 let path = app.worlds_dir.join(&name);
-                app.game = GameState::new(name, path, res);
+                let is_new = !path.exists();
+                app.game = GameState::new(name, path, res, gen_version);
+                if is_new {
+                    app.game.rules = rules;
+                } else {
+                    log::warn!("World already exists. Keeping its rules.");
+                }
             }
             Cmd::ReloadGraphics => {
-                res.atlas = AtlasBundle::new(&app.cfg.res_folder_path).unwrap();
+                res.atlas = AtlasBundle::new(&res.packs).unwrap();
                 app.game.tile_db.update_rects(&res.atlas.rects);
             }
+            Cmd::ReloadData => reload_data(app, res),
+            Cmd::UpgradeWorldGen => upgrade_worldgen(app, res),
             Cmd::SetBgTileAtCursor(id) => {
                 app.game.world.tile_at_mut(app.last_mouse_tpos).bg = id;
+                app.game.world.mark_changed(app.last_mouse_tpos);
             }
             Cmd::SetMidTileAtCursor(id) => {
-                app.game.world.tile_at_mut(app.last_mouse_tpos).mid = id;
+                let pos = app.last_mouse_tpos;
+                if app.game.world.tile_at_mut(pos).mid != id {
+                    app.game.spill_container_at(pos);
+                    app.game.world.tile_at_mut(pos).mid = id;
+                    app.game.world.mark_changed(pos);
+                }
             }
             Cmd::MusVolInc => app.aud.inc_mus_vol(),
             Cmd::MusVolDec => app.aud.dec_mus_vol(),
@@ -106,6 +158,16 @@ pub(super) fn dispatch(app: &mut App, res: &mut Res, mouse_world_pos: WorldPos)
                     health.invulnerable ^= true;
                 }
             }
+            Cmd::ApplyEffect(effect) => {
+                let en = app.game.controlled_en;
+                if let Ok(effects) = app.game.ecw.query_one_mut::<&mut StatusEffects>(en) {
+                    effects.apply(effect);
+                } else if app.game.ecw.contains(en) {
+                    let mut effects = StatusEffects::default();
+                    effects.apply(effect);
+                    app.game.ecw.insert_one(en, effects).unwrap();
+                }
+            }
             Cmd::SfxVolDec => app.aud.plr.sfx_vol -= 0.1,
             Cmd::SfxVolInc => app.aud.plr.sfx_vol += 0.1,
             Cmd::DesktopFullscreen => {
@@ -117,3 +179,106 @@ pub(super) fn dispatch(app: &mut App, res: &mut Res, mouse_world_pos: WorldPos)
         }
     }
 }
+
+fn reload_data(app: &mut App, res: &Res) {
+    let log = &mut app.debug.console.log;
+    let mut dbs = match PackDbs::try_load(&res.packs) {
+        Ok(dbs) => dbs,
+        Err(e) => {
+            writeln!(log, "Failed to reload data, keeping old data:\n{e:#}").unwrap();
+            return;
+        }
+    };
+    let mut problems = validate(&dbs.tile, &dbs.item, &dbs.recipe);
+    problems.extend(validate_worldgen(&dbs.worldgen, &dbs.tile));
+    let old_palette = Palette::from_dbs(&app.game.itemdb, &app.game.tile_db);
+    if !old_palette
+        .remap_to(&Palette::from_dbs(&dbs.item, &dbs.tile))
+        .is_identity()
+    {
+        problems.push(
+            "Existing items or tiles were reordered or removed. \
+             Restart the game to remap the world."
+                .into(),
+        );
+    }
+    if !problems.is_empty() {
+        writeln!(log, "Invalid data, keeping old data:").unwrap();
+        for problem in problems {
+            writeln!(log, "- {problem}").unwrap();
+        }
+        return;
+    }
+    dbs.tile.update_rects(&res.atlas.rects);
+    dbs.item.update_rects(&res.atlas.rects);
+    app.game.tile_db = dbs.tile;
+    app.game.itemdb = dbs.item;
+    app.game.recipe_db = dbs.recipe;
+    app.game.char_db = dbs.char;
+    app.game
+        .map
+        .set_colors(TileColors::new(&app.game.tile_db, &res.packs));
+    for (pos, chk) in &app.game.world.chunks {
+        app.game.map.update_chunk(*pos, chk);
+    }
+    writeln!(log, "Data reloaded").unwrap();
+    let base = &res.packs[0];
+    for filename in [
+        "tiles.ron",
+        "items.ron",
+        "recipes.ron",
+        "char.ron",
+        "worldgen.ron",
+    ] {
+        let dir = base.db_dir(filename);
+        let from = if base.user_data_dir.as_deref() == Some(dir) {
+            "the in-game editors' copy"
+        } else {
+            "the shipped data"
+        };
+        writeln!(log, "- {filename} from {from} in {}", dir.display()).unwrap();
+        if base.edited_db_is_stale(filename) {
+            writeln!(
+                log,
+                "  WARNING: The shipped {filename} is newer than this copy. \
+                 Delete the copy to load the shipped one."
+            )
+            .unwrap();
+        }
+    }
+    writeln!(
+        log,
+        "World generation data only applies to this world after upgrading its generator"
+    )
+    .unwrap();
+}
+
+fn upgrade_worldgen(app: &mut App, res: &Res) {
+    let log = &mut app.debug.console.log;
+    let dbs = match PackDbs::try_load(&res.packs) {
+        Ok(dbs) => dbs,
+        Err(e) => {
+            writeln!(log, "Failed to load world generation data:\n{e:#}").unwrap();
+            return;
+        }
+    };
+    let problems = validate_worldgen(&dbs.worldgen, &app.game.tile_db);
+    if !problems.is_empty() {
+        writeln!(
+            log,
+            "Invalid world generation data, keeping the old generator:"
+        )
+        .unwrap();
+        for problem in problems {
+            writeln!(log, "- {problem}").unwrap();
+        }
+        return;
+    }
+    app.game.world.upgrade_gen(dbs.worldgen);
+    writeln!(
+        log,
+        "New chunks are generated with the {} generator now",
+        GenVersion::LATEST.name()
+    )
+    .unwrap();
+}
@@ -1,7 +1,13 @@
 use {
-    crate::{command::Cmd, debug::DebugState, math::WorldPos},
+    crate::{
+        command::{Cmd, TpTarget},
+        debug::DebugState,
+        math::{WorldPos, FPS_TARGET},
+    },
     clap::Parser,
+    mdv_data::item::{EffectKind, StatusEffect},
     splitty::SplitUnquotedChar,
+    std::fmt::Write,
 };
 
 #[derive(Parser)]
@@ -13,7 +19,7 @@ pub enum CmdLine {
     Freecam,
     /// Clear the console log
     Clear,
-    /// Teleport player to coordinates
+    /// Teleport player to coordinates, or to a waypoint (debug builds only)
     Tp(Tp),
     /// Teleport player to cursor
     #[clap(alias = "tc")]
@@ -47,25 +53,48 @@ pub enum CmdLine {
     Atlas,
     /// Reload graphics
     Greload,
+    /// Reload tile, item, recipe and char databases
+    ReloadData,
     /// Toggle entity list
     Entlist,
     /// Toggle god mode for controlled entity
     God,
+    /// Put a status effect on the controlled entity
+    Effect {
+        /// Poison, burning, regeneration or speed
+        kind: String,
+        #[arg(default_value_t = 1.0)]
+        strength: f32,
+        /// Duration in seconds
+        #[arg(default_value_t = 10)]
+        secs: u32,
+    },
 }
 
 #[derive(Parser)]
 pub struct Tp {
-    x: u32,
-    y: u32,
+    /// X coordinate, or the name of a waypoint
+    x: String,
+    /// Y coordinate. Left out when teleporting to a waypoint.
+    y: Option<u32>,
     /// Relative to current position
     #[arg(short, long)]
     rel: bool,
 }
 impl Tp {
-    fn to_world_pos(&self) -> WorldPos {
-        WorldPos {
-            x: self.x,
-            y: self.y,
+    fn target(self, log: &mut String) -> Option<TpTarget> {
+        let Some(y) = self.y else {
+            return Some(TpTarget::Waypoint(self.x));
+        };
+        match self.x.parse() {
+            Ok(x) => Some(TpTarget::Pos {
+                pos: WorldPos { x, y },
+                relative: self.rel,
+            }),
+            Err(e) => {
+                writeln!(log, "Invalid x coordinate '{}': {e}", self.x).unwrap();
+                None
+            }
         }
     }
 }
@@ -90,10 +119,10 @@ impl CmdLine {
             CmdLine::Quit => Dispatch::Cmd(Cmd::QuitApp),
             CmdLine::Freecam => Dispatch::Cmd(Cmd::ToggleFreecam),
             CmdLine::Clear => Dispatch::ClearConsole,
-            CmdLine::Tp(tp) => Dispatch::Cmd(Cmd::Teleport {
-                pos: tp.to_world_pos(),
-                relative: tp.rel,
-            }),
+            CmdLine::Tp(tp) => match tp.target(&mut debug.console.log) {
+                Some(target) => Dispatch::Cmd(Cmd::Teleport(target)),
+                None => Dispatch::Noop,
+            },
             CmdLine::Tpc => Dispatch::Cmd(Cmd::TeleportCursor),
             CmdLine::Spawn => Dispatch::Cmd(Cmd::TeleportSpawn),
             CmdLine::Give { name, amount } => Dispatch::Cmd(Cmd::GiveItemByName { name, amount }),
@@ -113,6 +142,7 @@ impl CmdLine {
             }
             CmdLine::Atlas => Dispatch::ToggleAtlas,
             CmdLine::Greload => Dispatch::Cmd(Cmd::ReloadGraphics),
+            CmdLine::ReloadData => Dispatch::Cmd(Cmd::ReloadData),
             CmdLine::Itemdb => {
                 debug.itemdb_edit.open ^= true;
                 Dispatch::Noop
@@ -126,6 +156,24 @@ impl CmdLine {
                 Dispatch::Noop
             }
             CmdLine::God => Dispatch::Cmd(Cmd::GodToggle),
+            CmdLine::Effect {
+                kind,
+                strength,
+                secs,
+            } => {
+                let Some(kind) = EffectKind::ALL
+                    .into_iter()
+                    .find(|k| k.name().eq_ignore_ascii_case(&kind))
+                else {
+                    writeln!(&mut debug.console.log, "Unknown status effect: {kind}").unwrap();
+                    return Dispatch::Noop;
+                };
+                Dispatch::Cmd(Cmd::ApplyEffect(StatusEffect {
+                    kind,
+                    strength,
+                    duration: secs * u32::from(FPS_TARGET),
+                }))
+            }
         }
     }
 }
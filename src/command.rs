@@ -1,6 +1,9 @@
 use {
-    crate::math::WorldPos,
-    mdv_data::tile::{BgTileId, MidTileId},
+    crate::{math::WorldPos, save::WorldRules, world::GenVersion},
+    mdv_data::{
+        item::StatusEffect,
+        tile::{BgTileId, MidTileId},
+    },
 };
 
 /// A command that can change application or game state
@@ -8,10 +11,7 @@ pub enum Cmd {
     /// Quit the application
     QuitApp,
     ToggleFreecam,
-    Teleport {
-        pos: WorldPos,
-        relative: bool,
-    },
+    Teleport(TpTarget),
     TeleportSpawn,
     GiveItemByName {
         name: String,
@@ -20,7 +20,18 @@ pub enum Cmd {
     ToggleTileDbEdit,
     SetScale(u8),
     LoadWorld(String),
+    /// Load a world, or create it with `rules` and `gen_version` if it doesn't exist yet
+    CreateWorld {
+        name: String,
+        rules: WorldRules,
+        gen_version: GenVersion,
+    },
+    /// Generate the chunks of the current world that don't exist yet
+    /// with the latest generator and world generation data
+    UpgradeWorldGen,
     ReloadGraphics,
+    /// Reload the databases, keeping the old ones if the new data is broken
+    ReloadData,
     SetBgTileAtCursor(BgTileId),
     SetMidTileAtCursor(MidTileId),
     TeleportCursor,
@@ -28,9 +39,21 @@ pub enum Cmd {
     MusVolInc,
     MusVolDec,
     GodToggle,
+    /// Put a status effect on the controlled entity
+    ApplyEffect(StatusEffect),
     SfxVolDec,
     SfxVolInc,
     DesktopFullscreen,
 }
 
+/// Where [`Cmd::Teleport`] takes the controlled entity
+pub enum TpTarget {
+    Pos {
+        pos: WorldPos,
+        relative: bool,
+    },
+    /// The waypoint with this name. Only works in debug builds.
+    Waypoint(String),
+}
+
 pub type CmdVec = Vec<Cmd>;
@@ -1,13 +1,15 @@
 use {
+    crate::input::{Binding, InputAction},
     mdv_data::ron_pretty_cfg,
     serde::{Deserialize, Serialize},
-    std::path::Path,
+    std::{collections::BTreeMap, path::Path},
 };
 
 #[derive(Serialize, Deserialize)]
 pub struct Config {
     pub scale: u8,
     pub last_world: Option<String>,
+    /// Relative paths are relative to the directory of the shipped game data
     #[serde(default = "default_res_folder_path")]
     pub res_folder_path: String,
     #[serde(default)]
@@ -16,6 +18,17 @@ pub struct Config {
     pub music_vol: f32,
     #[serde(default)]
     pub sfx_vol: f32,
+    /// Data pack directories, loaded in order on top of the base game.
+    ///
+    /// Relative paths are relative to the user data directory.
+    #[serde(default)]
+    pub data_packs: Vec<String>,
+    /// Key bindings, applied on top of the defaults
+    #[serde(default)]
+    pub key_bindings: BTreeMap<InputAction, Binding>,
+    /// Gamepad button bindings, applied on top of the defaults
+    #[serde(default)]
+    pub pad_bindings: BTreeMap<InputAction, u32>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -99,6 +112,9 @@ impl Default for Config {
             ui: UiConfig::default(),
             music_vol: 1.0,
             sfx_vol: 1.0,
+            data_packs: Vec::new(),
+            key_bindings: BTreeMap::new(),
+            pad_bindings: BTreeMap::new(),
         }
     }
 }
@@ -0,0 +1,347 @@
+use {
+    crate::{config::Config, res_locator::ResLocator},
+    anyhow::Context,
+    mdv_data::{
+        char::CharDb,
+        item::ItemDb,
+        prefab::{self, PrefabDef},
+        recipe::RecipeDb,
+        tile::TileDb,
+        worldgen::WorldGenDb,
+        LoadError,
+    },
+    serde::Serialize,
+    sfml::graphics::Image,
+    std::{
+        path::{Path, PathBuf},
+        time::SystemTime,
+    },
+};
+
+/// A directory that can add tiles, items, recipes, world generation strata, prefabs,
+/// graphics and sounds to the game.
+///
+/// The base game is always the first pack. Later packs override entries of earlier
+/// packs that have the same key, and append the rest.
+///
+/// Ids inside a pack's data (e.g. `PlaceMidTile { id }`) refer to the databases
+/// as merged up to and including that pack.
+#[derive(Debug)]
+pub struct DataPack {
+    pub name: String,
+    /// Directory of the `.ron` databases
+    pub data_dir: PathBuf,
+    /// Directory of `graphics/` and `sfx/`
+    pub res_dir: PathBuf,
+    /// Where the in-game editors save their copies of the databases.
+    ///
+    /// Only the base game has one. Copies found here take precedence over `data_dir`,
+    /// as long as they are newer than the file they were made from.
+    pub user_data_dir: Option<PathBuf>,
+}
+
+impl DataPack {
+    /// The base game, followed by the packs listed in the config, in order
+    pub fn list(cfg: &Config, locator: &ResLocator) -> Vec<Self> {
+        let mut packs = vec![Self {
+            name: "base".into(),
+            data_dir: locator.shipped_data_dir(),
+            res_dir: locator.shipped_dir.join(&cfg.res_folder_path),
+            user_data_dir: Some(locator.user_data_dir()),
+        }];
+        for dir in &cfg.data_packs {
+            let dir = locator.user_dir.join(dir);
+            if !dir.is_dir() {
+                log::warn!(
+                    "Data pack directory {} doesn't exist. Skipping.",
+                    dir.display()
+                );
+                continue;
+            }
+            packs.push(Self {
+                name: dir
+                    .file_name()
+                    .map_or_else(|| dir.display().to_string(), |s| s.to_string_lossy().into()),
+                data_dir: dir.clone(),
+                res_dir: dir,
+                user_data_dir: None,
+            });
+        }
+        packs
+    }
+    fn has_db(&self, filename: &str) -> bool {
+        self.db_dir(filename).join(filename).exists()
+    }
+    /// Directory to load the database file `filename` from.
+    ///
+    /// The copy saved by the in-game editors always wins over the shipped file.
+    /// If the shipped file is newer, it's loaded anyway, but with a warning, because it
+    /// hides game updates and hand edits of the shipped data.
+    pub(crate) fn db_dir(&self, filename: &str) -> &Path {
+        match &self.user_data_dir {
+            Some(dir) if dir.join(filename).exists() => {
+                if self.edited_db_is_stale(filename) {
+                    log::warn!(
+                        "Loading edited {filename} from {}, but the shipped one in {} is newer. \
+                         Delete the edited one to get the shipped data.",
+                        dir.display(),
+                        self.data_dir.display()
+                    );
+                }
+                dir
+            }
+            _ => &self.data_dir,
+        }
+    }
+    /// Whether the shipped database file `filename` changed after the in-game editors
+    /// saved their copy of it
+    pub(crate) fn edited_db_is_stale(&self, filename: &str) -> bool {
+        let Some(dir) = &self.user_data_dir else {
+            return false;
+        };
+        match (
+            modified(&dir.join(filename)),
+            modified(&self.data_dir.join(filename)),
+        ) {
+            (Some(edited), Some(shipped)) => edited < shipped,
+            _ => false,
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// Save the databases the in-game editors changed into the user data dir of `base`.
+///
+/// Databases that are the same as what would be loaded anyway aren't written,
+/// so untouched copies don't pile up in the user data dir.
+pub fn save_edited_dbs(
+    base: &DataPack,
+    tile: &TileDb,
+    item: &ItemDb,
+    recipe: &RecipeDb,
+    char: &CharDb,
+) {
+    let Some(dir) = &base.user_data_dir else {
+        return;
+    };
+    let loaded = PackDbs::load(std::slice::from_ref(base));
+    let tile_changed = differs(tile, &loaded.tile);
+    let item_changed = differs(item, &loaded.item);
+    let recipe_changed = differs(recipe, &loaded.recipe);
+    let char_changed = differs(char, &loaded.char);
+    if !(tile_changed || item_changed || recipe_changed || char_changed) {
+        return;
+    }
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        log::error!("Failed to create user data dir: {e}");
+        return;
+    }
+    if tile_changed {
+        tile.try_save(dir);
+    }
+    if item_changed {
+        item.try_save(dir);
+    }
+    if recipe_changed {
+        recipe.try_save(dir);
+    }
+    if char_changed && let Err(e) = char.save(dir) {
+        log::error!("Failed to save char db: {e}");
+    }
+}
+
+fn differs<T: Serialize>(a: &T, b: &T) -> bool {
+    ron::to_string(a).ok() != ron::to_string(b).ok()
+}
+
+/// The databases of all loaded packs, merged together
+pub struct PackDbs {
+    pub tile: TileDb,
+    pub item: ItemDb,
+    pub recipe: RecipeDb,
+    pub char: CharDb,
+    pub worldgen: WorldGenDb,
+}
+
+impl PackDbs {
+    /// Load the databases, falling back to defaults for broken base game data,
+    /// and skipping broken pack data
+    pub fn load(packs: &[DataPack]) -> Self {
+        let (base, rest) = packs.split_first().expect("Base pack is always present");
+        let mut dbs = Self {
+            tile: TileDb::load_or_default(base.db_dir("tiles.ron")),
+            item: ItemDb::load_or_default(base.db_dir("items.ron")),
+            recipe: RecipeDb::load_or_default(base.db_dir("recipes.ron")),
+            char: CharDb::load(base.db_dir("char.ron")).unwrap(),
+            worldgen: WorldGenDb::load_or_default(base.db_dir("worldgen.ron")),
+        };
+        for pack in rest {
+            if let Some(db) = load_pack_db(pack, "tiles.ron", |dir| TileDb::load(dir)) {
+                dbs.tile.merge(db);
+            }
+            if let Some(db) = load_pack_db(pack, "items.ron", |dir| ItemDb::load(dir)) {
+                dbs.item.merge(db);
+            }
+            if let Some(db) = load_pack_db(pack, "recipes.ron", |dir| RecipeDb::load(dir)) {
+                dbs.recipe.merge(db);
+            }
+            if let Some(db) = load_pack_db(pack, "char.ron", |dir| CharDb::load(dir)) {
+                dbs.char.merge(db);
+            }
+            if let Some(db) = load_pack_db(pack, "worldgen.ron", |dir| WorldGenDb::load(dir)) {
+                dbs.worldgen.merge(db);
+            }
+        }
+        for pack in packs {
+            match load_prefabs(pack) {
+                Ok(prefabs) => dbs.worldgen.merge_prefabs(prefabs),
+                Err(e) => log::error!("{e:#}"),
+            }
+        }
+        dbs
+    }
+    /// Like [`Self::load`], but fails on the first broken file instead of working around it
+    pub fn try_load(packs: &[DataPack]) -> anyhow::Result<Self> {
+        let (base, rest) = packs.split_first().expect("Base pack is always present");
+        let mut dbs = Self {
+            tile: try_load_db(base, "tiles.ron", |dir| TileDb::load(dir))?,
+            item: try_load_db(base, "items.ron", |dir| ItemDb::load(dir))?,
+            recipe: try_load_db(base, "recipes.ron", |dir| RecipeDb::load(dir))?,
+            char: try_load_db(base, "char.ron", |dir| CharDb::load(dir))?,
+            worldgen: try_load_db(base, "worldgen.ron", |dir| WorldGenDb::load(dir))?,
+        };
+        for pack in rest {
+            if pack.has_db("tiles.ron") {
+                dbs.tile
+                    .merge(try_load_db(pack, "tiles.ron", |dir| TileDb::load(dir))?);
+            }
+            if pack.has_db("items.ron") {
+                dbs.item
+                    .merge(try_load_db(pack, "items.ron", |dir| ItemDb::load(dir))?);
+            }
+            if pack.has_db("recipes.ron") {
+                dbs.recipe
+                    .merge(try_load_db(pack, "recipes.ron", |dir| RecipeDb::load(dir))?);
+            }
+            if pack.has_db("char.ron") {
+                dbs.char
+                    .merge(try_load_db(pack, "char.ron", |dir| CharDb::load(dir))?);
+            }
+            if pack.has_db("worldgen.ron") {
+                dbs.worldgen.merge(try_load_db(pack, "worldgen.ron", |dir| {
+                    WorldGenDb::load(dir)
+                })?);
+            }
+        }
+        for pack in packs {
+            dbs.worldgen.merge_prefabs(load_prefabs(pack)?);
+        }
+        Ok(dbs)
+    }
+}
+
+fn try_load_db<T>(
+    pack: &DataPack,
+    filename: &str,
+    load: impl FnOnce(&Path) -> Result<T, LoadError>,
+) -> anyhow::Result<T> {
+    let dir = pack.db_dir(filename);
+    load(dir).with_context(|| {
+        format!(
+            "Data pack '{}': {}",
+            pack.name,
+            dir.join(filename).display()
+        )
+    })
+}
+
+/// Load the prefabs of `pack`, filling in the tiles of the ones drawn as images
+fn load_prefabs(pack: &DataPack) -> anyhow::Result<Vec<PrefabDef>> {
+    let mut prefabs = PrefabDef::load_dir(&pack.data_dir)
+        .with_context(|| format!("Data pack '{}': prefabs", pack.name))?;
+    for prefab in &mut prefabs {
+        let Some(image) = &prefab.image else {
+            continue;
+        };
+        let path = pack.data_dir.join(prefab::DIR).join(image);
+        let img = path
+            .to_str()
+            .and_then(|path| Image::from_file(path).ok())
+            .with_context(|| {
+                format!(
+                    "Data pack '{}': failed to load prefab image {}",
+                    pack.name,
+                    path.display()
+                )
+            })?;
+        let width = usize::try_from(img.size().x).unwrap();
+        prefab
+            .rows_from_image(width, img.pixel_data())
+            .map_err(|e| anyhow::anyhow!("Data pack '{}': {e}", pack.name))?;
+    }
+    Ok(prefabs)
+}
+
+/// Packs don't have to provide every database, so a missing file is not an error
+fn load_pack_db<T>(
+    pack: &DataPack,
+    filename: &str,
+    load: impl FnOnce(&Path) -> Result<T, LoadError>,
+) -> Option<T> {
+    if !pack.has_db(filename) {
+        return None;
+    }
+    match try_load_db(pack, filename, load) {
+        Ok(db) => Some(db),
+        Err(e) => {
+            log::error!("{e:#}");
+            None
+        }
+    }
+}
+
+/// Resource subdirectories (`graphics`, `sfx`, ...) of every pack that has them
+pub fn res_subdirs<'a>(
+    packs: &'a [DataPack],
+    subdir: &'a str,
+) -> impl Iterator<Item = PathBuf> + 'a {
+    packs
+        .iter()
+        .map(move |pack| pack.res_dir.join(subdir))
+        .filter(|dir| Path::is_dir(dir))
+}
+
+#[test]
+fn test_edited_db_dir() {
+    let root = std::env::temp_dir().join("mdv-test-edited-db-dir");
+    let (shipped, edited) = (root.join("data"), root.join("edited"));
+    std::fs::create_dir_all(&shipped).unwrap();
+    std::fs::create_dir_all(&edited).unwrap();
+    let pack = DataPack {
+        name: "base".into(),
+        data_dir: shipped.clone(),
+        res_dir: shipped.clone(),
+        user_data_dir: Some(edited.clone()),
+    };
+    let touch = |path: PathBuf, secs: u64| {
+        let f = std::fs::File::create(path).unwrap();
+        f.set_modified(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs))
+            .unwrap();
+    };
+    touch(shipped.join("tiles.ron"), 100);
+    assert_eq!(pack.db_dir("tiles.ron"), shipped);
+    assert!(!pack.edited_db_is_stale("tiles.ron"));
+    touch(edited.join("tiles.ron"), 200);
+    assert_eq!(pack.db_dir("tiles.ron"), edited);
+    assert!(!pack.edited_db_is_stale("tiles.ron"));
+    // The shipped data was updated after the edit. The edit still wins, but is stale.
+    touch(shipped.join("tiles.ron"), 300);
+    assert_eq!(pack.db_dir("tiles.ron"), edited);
+    assert!(pack.edited_db_is_stale("tiles.ron"));
+    std::fs::remove_dir_all(root).unwrap();
+}
@@ -1,7 +1,9 @@
 use {
     crate::{
         game::GameState,
+        math::FPS_TARGET,
         player::{MoveExtra, MovingEnt},
+        status::StatusEffects,
     },
     egui_sfml::egui,
 };
@@ -27,6 +29,16 @@ impl EntityList {
                             return;
                         }
                     }
+                    if let Some(effects) = en.get::<&StatusEffects>() {
+                        for eff in &effects.effects {
+                            ui.label(format!(
+                                "{} {:.1} ({}s left)",
+                                eff.kind.name(),
+                                eff.strength,
+                                eff.duration / u32::from(FPS_TARGET)
+                            ));
+                        }
+                    }
                     if let Some(mut mov) = en.get::<&mut MovingEnt>() {
                         ui.add(egui::DragValue::new(&mut mov.vspeed).prefix("vspeed"));
                         ui.add(egui::DragValue::new(&mut mov.mob.en.bb.x).prefix("bb w"));
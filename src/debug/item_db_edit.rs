@@ -4,7 +4,9 @@ use {
     egui_sfml::egui,
     extension_traits::extension,
     mdv_data::{
-        item::{ItemDef, ItemId, UseAction},
+        item::{
+            EffectKind, EquipSlot, Equippable, ItemDef, ItemId, StatMods, StatusEffect, UseAction,
+        },
         tile::LayerAccess,
     },
     mdv_math::types::{IntRect, ScreenVec},
@@ -39,6 +41,7 @@ impl ItemDbEdit {
                         ui.set_max_width(150.0);
                         if ui.button("New item").clicked() {
                             game.itemdb.db.push(ItemDef {
+                                key: String::new(),
                                 name: "New item".into(),
                                 graphic_name: "".into(),
                                 tex_rect: IntRect::default(),
@@ -46,6 +49,10 @@ impl ItemDbEdit {
                                 use1: UseAction::Nothing,
                                 use2: UseAction::Nothing,
                                 stackable: false,
+                                max_stack: 1,
+                                durability: 0,
+                                equip: None,
+                                currency: false,
                             })
                         }
                         ui.separator();
@@ -73,6 +80,10 @@ impl ItemDbEdit {
                             ui.label("No item selected (or out of bounds)");
                             return;
                         };
+                        ui.horizontal(|ui| {
+                            ui.label("Key");
+                            ui.text_edit_singleline(&mut def.key);
+                        });
                         ui.horizontal(|ui| {
                             ui.label("Name");
                             ui.text_edit_singleline(&mut def.name);
@@ -93,15 +104,30 @@ impl ItemDbEdit {
                             ui.add(egui::DragValue::new(&mut def.draw_off.x));
                             ui.add(egui::DragValue::new(&mut def.draw_off.y));
                         });
-                        ui.checkbox(&mut def.stackable, "Stackable");
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut def.stackable, "Stackable");
+                            if def.stackable {
+                                ui.add(
+                                    egui::DragValue::new(&mut def.max_stack).prefix("max stack: "),
+                                );
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Durability");
+                            ui.add(egui::DragValue::new(&mut def.durability));
+                            if def.durability == 0 {
+                                ui.label("(never wears out)");
+                            }
+                        });
+                        ui.checkbox(&mut def.currency, "Currency");
                         use_dropdown_combo(&mut def.use1, ui, "Primary use");
                         use_dropdown_combo(&mut def.use2, ui, "Secondary use");
+                        equip_ui(&mut def.equip, ui);
                         ui.horizontal(|ui| {
                             if ui.button("Give").clicked() {
-                                game.inventory.add(
+                                game.give_item(
                                     ItemId(u16::try_from(self.sel_idx + 1).unwrap()),
                                     self.give_amount,
-                                    &game.itemdb,
                                 );
                             }
                             ui.add(egui::DragValue::new(&mut self.give_amount).prefix("amount: "));
@@ -112,6 +138,48 @@ impl ItemDbEdit {
     }
 }
 
+fn equip_ui(equip: &mut Option<Equippable>, ui: &mut egui::Ui) {
+    let Some(eq) = equip else {
+        if ui.button("Make equippable").clicked() {
+            *equip = Some(Equippable {
+                slot: EquipSlot::Head,
+                mods: StatMods::default(),
+                worn_graphic: None,
+            });
+        }
+        return;
+    };
+    let mut remove = false;
+    ui.horizontal(|ui| {
+        egui::ComboBox::new("equip_slot", "Equip slot")
+            .selected_text(eq.slot.name())
+            .show_ui(ui, |ui| {
+                for slot in EquipSlot::ALL {
+                    ui.selectable_value(&mut eq.slot, slot, slot.name());
+                }
+            });
+        remove = ui.button("Not equippable").clicked();
+    });
+    ui.horizontal(|ui| {
+        ui.label("Defense");
+        ui.add(egui::DragValue::new(&mut eq.mods.defense).speed(0.1));
+        ui.label("Mining speed");
+        ui.add(egui::DragValue::new(&mut eq.mods.mining_speed).speed(0.01));
+        ui.label("Light");
+        ui.add(egui::DragValue::new(&mut eq.mods.light));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Worn graphic");
+        let mut graphic = eq.worn_graphic.clone().unwrap_or_default();
+        if ui.text_edit_singleline(&mut graphic).changed() {
+            eq.worn_graphic = (!graphic.is_empty()).then_some(graphic);
+        }
+    });
+    if remove {
+        *equip = None;
+    }
+}
+
 fn use_dropdown_combo(use_field: &mut UseAction, ui: &mut egui::Ui, label: &str) {
     egui::ComboBox::new(label, label)
         .selected_text(use_field.text())
@@ -122,22 +190,24 @@ fn use_dropdown_combo(use_field: &mut UseAction, ui: &mut egui::Ui, label: &str)
             let v = UseAction::MineTile {
                 power: 1.,
                 delay: 1,
+                tier: 0,
             };
             let text = v.text();
             ui.selectable_value(use_field, v, text);
             let v = UseAction::MineBgTile {
                 power: 1.,
                 delay: 1,
+                tier: 0,
             };
             let text = v.text();
             ui.selectable_value(use_field, v, text);
             let v = UseAction::PlaceBgTile {
-                id: data::tile::bg::TILES_DIRTBACK,
+                id: data::tile::bg::DIRTBACK,
             };
             let text = v.text();
             ui.selectable_value(use_field, v, text);
             let v = UseAction::PlaceMidTile {
-                id: data::tile::mid::TILES_DIRT,
+                id: data::tile::mid::DIRT,
             };
             let text = v.text();
             ui.selectable_value(use_field, v, text);
@@ -146,6 +216,52 @@ fn use_dropdown_combo(use_field: &mut UseAction, ui: &mut egui::Ui, label: &str)
             };
             let text = v.text();
             ui.selectable_value(use_field, v, text);
+            let v = UseAction::Consume {
+                food: 0.,
+                stamina: 0.,
+                breath: 0.,
+                health: 0.,
+                effects: Vec::new(),
+            };
+            let text = v.text();
+            ui.selectable_value(use_field, v, text);
+            let v = UseAction::Throw {
+                speed: 8.,
+                damage: 0.,
+                knockback: 0.,
+            };
+            let text = v.text();
+            ui.selectable_value(use_field, v, text);
+            let v = UseAction::SpawnEntity {
+                graphic: String::new(),
+                size: ScreenVec { x: 8, y: 32 },
+                climbable: false,
+            };
+            let text = v.text();
+            ui.selectable_value(use_field, v, text);
+            let v = UseAction::Script {
+                name: String::new(),
+            };
+            let text = v.text();
+            ui.selectable_value(use_field, v, text);
+            let v = UseAction::Melee {
+                damage: 5.,
+                reach: 48,
+                arc: 90.,
+                knockback: 4.,
+                cooldown: 20,
+            };
+            let text = v.text();
+            ui.selectable_value(use_field, v, text);
+            let v = UseAction::Shoot {
+                ammo: data::item::SMALL_ROCK,
+                speed: 12.,
+                damage: 5.,
+                knockback: 2.,
+                cooldown: 30,
+            };
+            let text = v.text();
+            ui.selectable_value(use_field, v, text);
         });
     match use_field {
         UseAction::PlaceBgTile { id } => {
@@ -161,22 +277,134 @@ fn use_dropdown_combo(use_field: &mut UseAction, ui: &mut egui::Ui, label: &str)
             ui.selectable_value(layer, LayerAccess::Bg, "Bg");
             ui.selectable_value(layer, LayerAccess::Mid, "Mid");
         }
-        UseAction::MineTile { power, delay } => {
+        UseAction::MineTile { power, delay, tier }
+        | UseAction::MineBgTile { power, delay, tier } => {
             ui.label("Power");
             ui.add(egui::DragValue::new(power));
             ui.label("Delay");
             ui.add(egui::DragValue::new(delay));
+            ui.label("Tier");
+            ui.add(egui::DragValue::new(tier));
         }
-        UseAction::MineBgTile { power, delay } => {
-            ui.label("Power");
-            ui.add(egui::DragValue::new(power));
-            ui.label("Delay");
-            ui.add(egui::DragValue::new(delay));
+        UseAction::Consume {
+            food,
+            stamina,
+            breath,
+            health,
+            effects,
+        } => {
+            ui.label("Food");
+            ui.add(egui::DragValue::new(food));
+            ui.label("Stamina");
+            ui.add(egui::DragValue::new(stamina));
+            ui.label("Breath");
+            ui.add(egui::DragValue::new(breath));
+            ui.label("Health");
+            ui.add(egui::DragValue::new(health));
+            effects_ui(effects, ui, label);
+        }
+        UseAction::Throw {
+            speed,
+            damage,
+            knockback,
+        } => {
+            ui.label("Speed");
+            ui.add(egui::DragValue::new(speed));
+            ui.label("Damage");
+            ui.add(egui::DragValue::new(damage));
+            ui.label("Knockback");
+            ui.add(egui::DragValue::new(knockback));
+        }
+        UseAction::Melee {
+            damage,
+            reach,
+            arc,
+            knockback,
+            cooldown,
+        } => {
+            ui.label("Damage");
+            ui.add(egui::DragValue::new(damage));
+            ui.label("Reach");
+            ui.add(egui::DragValue::new(reach));
+            ui.label("Arc");
+            ui.add(egui::DragValue::new(arc));
+            ui.label("Knockback");
+            ui.add(egui::DragValue::new(knockback));
+            ui.label("Cooldown");
+            ui.add(egui::DragValue::new(cooldown));
+        }
+        UseAction::Shoot {
+            ammo,
+            speed,
+            damage,
+            knockback,
+            cooldown,
+        } => {
+            ui.label("Ammo item");
+            ui.add(egui::DragValue::new(&mut ammo.0));
+            ui.label("Speed");
+            ui.add(egui::DragValue::new(speed));
+            ui.label("Damage");
+            ui.add(egui::DragValue::new(damage));
+            ui.label("Knockback");
+            ui.add(egui::DragValue::new(knockback));
+            ui.label("Cooldown");
+            ui.add(egui::DragValue::new(cooldown));
+        }
+        UseAction::SpawnEntity {
+            graphic,
+            size,
+            climbable,
+        } => {
+            ui.label("Graphic");
+            ui.text_edit_singleline(graphic);
+            ui.label("Size");
+            ui.add(egui::DragValue::new(&mut size.x));
+            ui.add(egui::DragValue::new(&mut size.y));
+            ui.checkbox(climbable, "Climbable");
+        }
+        UseAction::Script { name } => {
+            ui.label("Script name");
+            ui.text_edit_singleline(name);
         }
         UseAction::Nothing => {}
     }
 }
 
+fn effects_ui(effects: &mut Vec<StatusEffect>, ui: &mut egui::Ui, label: &str) {
+    ui.vertical(|ui| {
+        let mut remove = None;
+        for (i, eff) in effects.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                egui::ComboBox::new((label, "effect", i), "")
+                    .selected_text(eff.kind.name())
+                    .show_ui(ui, |ui| {
+                        for kind in EffectKind::ALL {
+                            ui.selectable_value(&mut eff.kind, kind, kind.name());
+                        }
+                    });
+                ui.label("Strength");
+                ui.add(egui::DragValue::new(&mut eff.strength).speed(0.1));
+                ui.label("Ticks");
+                ui.add(egui::DragValue::new(&mut eff.duration));
+                if ui.button("x").clicked() {
+                    remove = Some(i);
+                }
+            });
+        }
+        if let Some(i) = remove {
+            effects.remove(i);
+        }
+        if ui.button("Add effect").clicked() {
+            effects.push(StatusEffect {
+                kind: EffectKind::Regeneration,
+                strength: 1.0,
+                duration: 600,
+            });
+        }
+    });
+}
+
 #[extension(trait UseActionExt)]
 impl UseAction {
     fn text(&self) -> &'static str {
@@ -186,6 +414,12 @@ impl UseAction {
             UseAction::RemoveTile { .. } => "Remove tile",
             UseAction::MineTile { .. } => "Mine tile",
             UseAction::MineBgTile { .. } => "Mine bg tile",
+            UseAction::Consume { .. } => "Consume",
+            UseAction::Throw { .. } => "Throw",
+            UseAction::SpawnEntity { .. } => "Spawn entity",
+            UseAction::Script { .. } => "Script",
+            UseAction::Melee { .. } => "Melee attack",
+            UseAction::Shoot { .. } => "Shoot",
             UseAction::Nothing => "Nothing",
         }
     }
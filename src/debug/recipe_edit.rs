@@ -54,6 +54,7 @@ impl RecdbEd {
                 output: ItemStack {
                     id: data::item::TORCH,
                     qty: 1,
+                    wear: 0,
                 },
             });
         }
@@ -91,6 +92,7 @@ impl RecdbEd {
                 rec.input.push(ItemStack {
                     id: data::item::TORCH,
                     qty: 1,
+                    wear: 0,
                 });
             }
         });
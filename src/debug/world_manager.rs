@@ -2,9 +2,14 @@ use {
     crate::{
         command::{Cmd, CmdVec},
         game::GameState,
-        save,
+        save::{self, DeathRule, WorldRules},
+        world::GenVersion,
     },
     egui_sfml::egui,
+    mdv_data::{
+        prefab::{Frequency, PrefabDef, PrefabKey},
+        tile::{TileDb, TileDef, TileId, TileLayer},
+    },
     rand::{thread_rng, Rng},
     std::path::{Path, PathBuf},
 };
@@ -15,6 +20,10 @@ pub struct WorldManager {
     just_opened: bool,
     world_dirs: Vec<PathBuf>,
     new_world_name: String,
+    new_world_rules: WorldRules,
+    new_world_gen: GenVersion,
+    /// Index of the prefab shown in the prefab preview
+    selected_prefab: usize,
 }
 impl WorldManager {
     pub fn ui(
@@ -33,6 +42,32 @@ impl WorldManager {
                     self.just_opened = false;
                 }
                 ui.label(format!("Current world: {}", game.world.name));
+                ui.label(format!(
+                    "Death rule: {}{}",
+                    game.rules.death.name(),
+                    if game.rules.hardcore {
+                        ", hardcore"
+                    } else {
+                        ""
+                    }
+                ));
+                if game.finished {
+                    ui.label("This world is finished");
+                }
+                ui.horizontal(|ui| {
+                    ui.label(format!("Generator: {}", game.world.gen_version.name()));
+                    if ui
+                        .button("Upgrade")
+                        .on_hover_text(
+                            "Generate the chunks that don't exist yet with the latest generator \
+                             and world generation data.\n\
+                             Existing chunks stay as they are, expect seams where they meet.",
+                        )
+                        .clicked()
+                    {
+                        cmd.push(Cmd::UpgradeWorldGen);
+                    }
+                });
                 ui.separator();
                 let mut refresh = false;
                 egui::ScrollArea::vertical()
@@ -61,6 +96,25 @@ impl WorldManager {
                     }
                     refresh = true;
                 }
+                ui.horizontal(|ui| {
+                    let rules = &mut self.new_world_rules;
+                    egui::ComboBox::new("death_rule_combo", "On death")
+                        .selected_text(rules.death.name())
+                        .show_ui(ui, |ui| {
+                            for rule in DeathRule::ALL {
+                                ui.selectable_value(&mut rules.death, rule, rule.name());
+                            }
+                        });
+                    ui.checkbox(&mut rules.hardcore, "Hardcore");
+                    let gen_version = &mut self.new_world_gen;
+                    egui::ComboBox::new("gen_version_combo", "Generator")
+                        .selected_text(gen_version.name())
+                        .show_ui(ui, |ui| {
+                            for version in GenVersion::ALL {
+                                ui.selectable_value(gen_version, version, version.name());
+                            }
+                        });
+                });
                 ui.horizontal(|ui| {
                     ui.add(
                         egui::TextEdit::singleline(&mut self.new_world_name)
@@ -70,12 +124,20 @@ impl WorldManager {
                         .add_enabled(!self.new_world_name.is_empty(), egui::Button::new("Create"))
                         .clicked()
                     {
-                        cmd.push(Cmd::LoadWorld(self.new_world_name.clone()));
+                        cmd.push(Cmd::CreateWorld {
+                            name: self.new_world_name.clone(),
+                            rules: self.new_world_rules,
+                            gen_version: self.new_world_gen,
+                        });
                         refresh = true;
                     }
                     if ui.button("Create random").clicked() {
                         let n: u32 = thread_rng().gen();
-                        cmd.push(Cmd::LoadWorld(n.to_string()));
+                        cmd.push(Cmd::CreateWorld {
+                            name: n.to_string(),
+                            rules: self.new_world_rules,
+                            gen_version: self.new_world_gen,
+                        });
                         refresh = true;
                     }
                 });
@@ -83,12 +145,112 @@ impl WorldManager {
                 if refresh {
                     self.world_dirs = save::world_dirs(worlds_path);
                 }
+                ui.separator();
+                ui.collapsing("Prefabs", |ui| self.prefab_ui(ui, game));
             });
         self.open = open;
     }
 
+    fn prefab_ui(&mut self, ui: &mut egui::Ui, game: &GameState) {
+        let prefabs = &game.world.worldgen.prefabs;
+        let Some(prefab) = prefabs.get(self.selected_prefab) else {
+            ui.label("The world has no prefabs");
+            return;
+        };
+        egui::ComboBox::new("prefab_combo", "Prefab")
+            .selected_text(prefab.name.as_str())
+            .show_ui(ui, |ui| {
+                for (i, prefab) in prefabs.iter().enumerate() {
+                    ui.selectable_value(&mut self.selected_prefab, i, prefab.name.as_str());
+                }
+            });
+        let placement = &prefab.placement;
+        ui.label(format!(
+            "{}x{} tiles, bottom at {}..{} m, {}{}",
+            prefab.width(),
+            prefab.height(),
+            placement.depth[0],
+            placement.depth[1],
+            match placement.frequency {
+                Frequency::PerCell(n) => format!("{n} per cell"),
+                Frequency::Unique { spread } => format!("unique, within {spread} m of the center"),
+            },
+            if placement.on_floor { ", on floor" } else { "" }
+        ));
+        prefab_grid(ui, prefab, &game.tile_db);
+    }
+
     pub(crate) fn toggle(&mut self) {
         self.open ^= true;
         self.just_opened = true;
     }
 }
+
+/// Draw the tiles of `prefab` as a grid of colored cells, describing the hovered one
+fn prefab_grid(ui: &mut egui::Ui, prefab: &PrefabDef, tile_db: &TileDb) {
+    const CELL: f32 = 6.0;
+    let size = egui::vec2(prefab.width() as f32, prefab.height() as f32) * CELL;
+    let (re, painter) = ui.allocate_painter(size, egui::Sense::hover());
+    let origin = re.rect.min;
+    let mut hovered = None;
+    for (y, row) in prefab.rows.iter().enumerate() {
+        for (x, ch) in row.chars().enumerate() {
+            let Some(key) = prefab.key(ch) else {
+                continue;
+            };
+            let rect = egui::Rect::from_min_size(
+                origin + egui::vec2(x as f32, y as f32) * CELL,
+                egui::vec2(CELL, CELL),
+            );
+            painter.rect_filled(rect, 0.0, key_color(key));
+            if re.hover_pos().is_some_and(|pos| rect.contains(pos)) {
+                hovered = Some((x, y, key));
+            }
+        }
+    }
+    if let Some((x, y, key)) = hovered {
+        let container = prefab
+            .containers
+            .iter()
+            .find(|c| usize::from(c.pos[0]) == x && usize::from(c.pos[1]) == y);
+        re.on_hover_text(format!(
+            "{x}, {y} '{}'\nbg: {}\nmid: {}{}",
+            key.ch,
+            key.bg.map_or("(keep)", |id| tile_name(tile_db, id)),
+            key.mid.map_or("(keep)", |id| tile_name(tile_db, id)),
+            container.map_or(String::new(), |c| format!(
+                "\n{} item stacks",
+                c.items.len()
+            )),
+        ));
+    }
+}
+
+/// The color of a legend key in the prefab preview
+fn key_color(key: &PrefabKey) -> egui::Color32 {
+    if let Some([r, g, b]) = key.color {
+        return egui::Color32::from_rgb(r, g, b);
+    }
+    match (key.mid, key.bg) {
+        (Some(mid), _) if !mid.empty() => id_color(mid.0),
+        (_, Some(bg)) if !bg.empty() => id_color(bg.0).gamma_multiply(0.4),
+        _ => egui::Color32::from_gray(20),
+    }
+}
+
+/// A color that tells tile ids apart
+fn id_color(id: u16) -> egui::Color32 {
+    let [r, g, b, _] = u32::from(id).wrapping_mul(0x9e37_79b9).to_le_bytes();
+    egui::Color32::from_rgb(r | 0x40, g | 0x40, b | 0x40)
+}
+
+fn tile_name<Layer: TileLayer>(tile_db: &TileDb, id: TileId<Layer>) -> &str
+where
+    TileDb: std::ops::Index<TileId<Layer>, Output = TileDef<Layer>>,
+{
+    if id.empty() {
+        "(empty)"
+    } else {
+        &tile_db[id].key
+    }
+}
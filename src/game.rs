@@ -1,36 +1,43 @@
 use {
-    self::{events::EventBuf, ui::UiState},
+    self::{combat::DamagePopup, events::EventBuf, ui::UiState},
     crate::{
         audio::AudioCtx,
         command::CmdVec,
+        data_pack::PackDbs,
         debug::{DebugState, DBG_OVR},
         graphics::ScreenRes,
         input::Input,
         inventory::Inventory,
         item::ItemDbExt,
+        itemdrop::{ItemdropBundle, PickupCooldown},
+        map::{TileColors, WorldMap},
         math::{wp_to_tp, WPosSc, WorldPos, TILE_SIZE},
-        player::PlayerBundle,
+        player::{Equipment, MovingEnt, PlayerBundle, PLAYER_HEIGHT},
         res::{Res, ResAudio},
-        save::Save,
+        save::{Save, WorldRules},
         tiles::TileDbExt,
         time::{daylight, tick_of_day},
-        world::{TilePos, World},
+        world::{GenVersion, TilePos, World},
     },
     egui_sfml::egui,
     fnv::FnvHashMap,
     mdv_data::{
         char::CharDb,
-        item::{ItemDb, ItemStack},
+        item::{ItemDb, ItemId, ItemQty, ItemStack},
+        palette::Palette,
         recipe::RecipeDb,
         tile::{LayerAccess, TileDb},
+        worldgen::{BiomeDef, WorldGenDb},
     },
     mdv_math::types::ScreenVec,
     rand::{thread_rng, Rng},
     std::path::{Path, PathBuf},
 };
 
+mod combat;
 mod events;
 pub mod rendering;
+mod scripts;
 mod systems;
 mod ui;
 
@@ -49,17 +56,22 @@ pub struct GameState {
     pub world: World,
     pub gravity: f32,
     pub current_biome: Biome,
-    pub prev_biome: Biome,
+    /// `None` until the first biome check, so the music of the starting biome gets played
+    pub prev_biome: Option<Biome>,
     pub ambient_light: u8,
     pub tile_db: TileDb,
     pub char_db: CharDb,
     pub recipe_db: RecipeDb,
     pub inventory: Inventory,
     pub itemdb: ItemDb,
+    /// Where new players appear. The player respawns here if there is no spawn anchor.
     pub spawn_point: WorldPos,
+    /// The bed (or other spawn anchor tile) the player chose to respawn at
+    pub spawn_anchor: Option<TilePos>,
     pub transient_tile_states: TransientTileStates,
     pub last_mine_attempt: u64,
     pub last_tile_place: u64,
+    pub last_attack: u64,
     pub ecw: hecs::World,
     pub ecb: hecs::CommandBuffer,
     pub player_en: hecs::Entity,
@@ -69,6 +81,13 @@ pub struct GameState {
     pub event_buf: EventBuf,
     // Respawn timer for player
     pub respawn_timer: u32,
+    /// Worn items of the dead player, given back on respawn
+    pub respawn_equipment: Equipment,
+    /// Where the last death of the player left a tombstone
+    pub death_tombstone: Option<WorldPos>,
+    pub rules: WorldRules,
+    /// The player died in hardcore mode, so there is no respawning
+    pub finished: bool,
     pub tile_interact_radius: u16,
     pub item_pickup_radius: u16,
     pub ui: UiState,
@@ -76,6 +95,8 @@ pub struct GameState {
     pub smart_cursor: bool,
     /// Tile pos to highlight (for smart cursor, etc.)
     pub highlight_tp: Option<TilePos>,
+    pub damage_popups: Vec<DamagePopup>,
+    pub map: WorldMap,
 }
 
 #[derive(Debug)]
@@ -86,55 +107,161 @@ pub struct TransientTileState {
     pub scale: f32,
 }
 
+/// The biome the camera is in
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
-pub enum Biome {
-    Surface,
-    Underground,
+pub struct Biome {
+    /// Index of the surface biome in [`WorldGenDb::biomes`].
+    ///
+    /// `None` if the world has no biomes, or if the underground variant of the
+    /// biome doesn't reach this deep.
+    pub surface: Option<usize>,
+    pub underground: bool,
+}
+
+/// Played where no biome decides the music
+const SURFACE_MUSIC: &str = "music/calm";
+const UNDERGROUND_MUSIC: &str = "music/underground";
+/// Drawn behind the surface where no biome decides the background
+const SURFACE_BACKGROUND: &str = "bg/sky";
+
+impl Biome {
+    pub const SURFACE: Self = Self {
+        surface: None,
+        underground: false,
+    };
+    fn def<'a>(&self, worldgen: &'a WorldGenDb) -> Option<&'a BiomeDef> {
+        self.surface.and_then(|idx| worldgen.biomes.get(idx))
+    }
+    /// Key of the music to play in this biome
+    pub fn music<'a>(&self, worldgen: &'a WorldGenDb) -> &'a str {
+        match (self.def(worldgen), self.underground) {
+            (Some(def), false) => &def.music,
+            (Some(def), true) => &def.underground_music,
+            (None, false) => SURFACE_MUSIC,
+            (None, true) => UNDERGROUND_MUSIC,
+        }
+    }
+    /// Key of the image drawn behind the world. `None` underground.
+    pub fn background<'a>(&self, worldgen: &'a WorldGenDb) -> Option<&'a str> {
+        if self.underground {
+            return None;
+        }
+        Some(
+            self.def(worldgen)
+                .map_or(SURFACE_BACKGROUND, |def| &def.background),
+        )
+    }
 }
 
 impl GameState {
-    pub(crate) fn new(world_name: String, path: PathBuf, res: &Res) -> GameState {
+    /// Load the world at `path`, or create it with the `gen_version` generator
+    pub(crate) fn new(
+        world_name: String,
+        path: PathBuf,
+        res: &Res,
+        gen_version: GenVersion,
+    ) -> GameState {
         let mut spawn_point = WorldPos::SURFACE_CENTER;
-        let mut tile_db = TileDb::load_or_default("data");
+        let PackDbs {
+            tile: mut tile_db,
+            item: mut itemdb,
+            recipe: recipe_db,
+            char: char_db,
+            worldgen,
+        } = PackDbs::load(&res.packs);
         tile_db.update_rects(&res.atlas.rects);
-        let mut itemdb = ItemDb::load_or_default("data");
         itemdb.update_rects(&res.atlas.rects);
         let mut inventory = Inventory::new_empty();
+        let mut placed = Vec::new();
+        let mut tombstones = Vec::new();
+        let mut rules = WorldRules::default();
+        let mut finished = false;
+        let mut spawn_anchor = None;
+        let mut waypoints = Vec::new();
         let mut world;
         let mut plr = PlayerBundle::new_at(spawn_point);
-        spawn_point.y -= WPosSc::try_from(plr.mov.mob.en.bb.y).unwrap();
-        plr.mov.mob.en.pos.y = i32::try_from(spawn_point.y).unwrap();
         match Save::load(&path) {
-            Ok(save) => {
+            Ok(mut save) => {
+                world = World::new(&world_name, path, save.world_seed, worldgen);
+                let palette = Palette::from_dbs(&itemdb, &tile_db);
+                if let Some(saved_palette) = &save.palette {
+                    let remap = saved_palette.remap_to(&palette);
+                    if !remap.is_identity() {
+                        log::info!("Item/tile databases changed since last save. Remapping ids.");
+                        save.inventory.remap_ids(&remap.items);
+                        save.player.equipment.remap_ids(&remap.items);
+                        save.placed
+                            .retain_mut(|placed| placed.remap_ids(&remap.items));
+                        for tomb in &mut save.tombstones {
+                            tomb.remap_ids(&remap.items);
+                        }
+                        world.remap_tiles(&remap);
+                        // Persist the new palette right away, so the remap isn't applied twice
+                        save.palette = Some(palette);
+                        if let Err(e) = save.save(&world.path) {
+                            log::error!("Failed to save remapped save: {e}");
+                        }
+                    }
+                }
+                // Worlds from before the generator was saved with them were made by the legacy one
+                world.load_gen_stamp(GenVersion::Legacy);
                 inventory = save.inventory;
-                world = World::new(&world_name, path, save.world_seed);
                 world.ticks = save.world_ticks;
                 plr.dat.update_from_save(&save.player);
                 plr.health = save.player.health;
+                plr.equipment = save.player.equipment;
+                plr.survival = save.player.survival;
+                plr.effects = save.player.effects;
+                placed = save.placed;
+                tombstones = save.tombstones;
+                rules = save.rules;
+                finished = save.finished;
+                spawn_anchor = save.spawn_anchor;
+                waypoints = save.waypoints;
             }
             Err(e) => {
                 log::error!("Failed to load save: {e}");
-                world = World::new(&world_name, path, thread_rng().gen());
+                world = World::new(&world_name, path, thread_rng().gen(), worldgen);
+                world.load_gen_stamp(gen_version);
             }
         }
+        // The surface is hilly, so stand on top of the ground at the center of the world
+        let surface = world.surface_at(wp_to_tp(spawn_point.x));
+        spawn_point.y =
+            surface * WPosSc::from(TILE_SIZE) - WPosSc::try_from(plr.mov.mob.en.bb.y).unwrap();
+        plr.mov.mob.en.pos.y = i32::try_from(spawn_point.y).unwrap();
         let mut ecw = hecs::World::new();
-        let player_en = ecw.spawn(plr);
+        let player_en = if finished {
+            log::info!("This world is finished. Not spawning a player.");
+            hecs::Entity::DANGLING
+        } else {
+            ecw.spawn(plr)
+        };
+        for placed in placed {
+            ecw.spawn((placed,));
+        }
+        for tomb in tombstones {
+            ecw.spawn((tomb,));
+        }
         log::info!("=== Spawned Player ===");
+        let map = WorldMap::new(TileColors::new(&tile_db, &res.packs), waypoints);
         Self {
             camera_offset: spawn_point,
             world,
             gravity: 0.55,
-            current_biome: Biome::Surface,
-            prev_biome: Biome::Surface,
+            current_biome: Biome::SURFACE,
+            prev_biome: None,
             ambient_light: 128,
             tile_db,
             inventory,
             itemdb,
             spawn_point,
+            spawn_anchor,
             transient_tile_states: Default::default(),
             last_mine_attempt: 0,
             last_tile_place: 0,
-            char_db: CharDb::load().unwrap(),
+            last_attack: 0,
+            char_db,
             ecw,
             ecb: hecs::CommandBuffer::default(),
             player_en,
@@ -143,12 +270,18 @@ impl GameState {
             pause_next_frame: false,
             event_buf: Default::default(),
             respawn_timer: 0,
+            respawn_equipment: Equipment::default(),
+            death_tombstone: None,
+            rules,
+            finished,
             tile_interact_radius: 113,
             item_pickup_radius: 80,
-            recipe_db: RecipeDb::load_or_default("data"),
+            recipe_db,
             ui: UiState::default(),
             smart_cursor: false,
             highlight_tp: None,
+            damage_popups: Vec::new(),
+            map,
         }
     }
 
@@ -168,18 +301,31 @@ impl GameState {
         scale: u8,
         egui_ctx: &egui::Context,
     ) {
-        self.ui.update_rects(&self.inventory, rt_size);
+        let container = self
+            .ui
+            .container
+            .and_then(|pos| self.world.container_at(pos));
+        self.ui.update_rects(&self.inventory, container, rt_size);
         self.ambient_light = daylight(tick_of_day(self.world.ticks));
         mdv_math::util::min_max_clamp(&mut self.ambient_light, 85, 200);
         if self.respawn_timer > 0 {
             self.respawn_timer -= 1;
-        } else if self.player_en == hecs::Entity::DANGLING {
-            self.player_en = self.ecw.spawn(PlayerBundle::new_at(self.spawn_point));
+        } else if self.player_en == hecs::Entity::DANGLING && !self.finished {
+            let mut plr = PlayerBundle::new_at(self.respawn_pos());
+            plr.equipment = std::mem::take(&mut self.respawn_equipment);
+            self.player_en = self.ecw.spawn(plr);
             self.controlled_en = self.player_en;
+            self.death_tombstone = None;
         }
-        let hud_msg = systems::ui_hud_input_system(self, input, scale, rt_size);
+        let hud_msg = systems::ui_hud_input_system(self, input, scale);
         if self.ui.menu.open {
-            systems::pause_menu::pause_menu_system(self, input, cmd, worlds_dir, au_ctx);
+            systems::pause_menu::pause_menu_system(
+                self, input, cmd, worlds_dir, au_ctx, &res.packs,
+            );
+            return;
+        }
+        if self.ui.map.open {
+            systems::map::map_screen_system(self, input, egui_ctx, rt_size, scale, cmd);
             return;
         }
         systems::craft_ui_system(self, egui_ctx, ScreenRes::from_sf_vec(res.atlas.tex.size()));
@@ -197,6 +343,10 @@ impl GameState {
             systems::move_control_system(self, input);
         }
         systems::move_system(self, rt_size.to_vec(), debug);
+        systems::map::explore_system(self);
+        systems::projectile_system(self);
+        combat::combat_tick_system(self);
+        systems::container_watch_system(self);
         if !hud_msg.cursor_occupied() {
             systems::interact_system(self, input, mouse_tpos, mouse_wpos);
             systems::item_use_system(self, input, mouse_tpos, au_res, au_ctx, mouse_wpos, debug);
@@ -205,19 +355,78 @@ impl GameState {
         systems::inventory_input_system(self, input);
         systems::item_drop_claim_system(self, au_ctx, au_res);
         systems::transient_blocks_system(self);
+        systems::survival_system(self);
         systems::health_system(self);
         self.world.ticks += 1;
-        let ev_buf = std::mem::take(&mut self.event_buf);
-        events::process_events(self, ev_buf, au_ctx, au_res);
+        events::process_events(self, au_ctx, au_res);
+    }
+
+    /// Where the player respawns.
+    ///
+    /// That's on their spawn anchor if it's still intact, otherwise the world spawn point.
+    pub(crate) fn respawn_pos(&mut self) -> WorldPos {
+        if let Some(anchor) = self.spawn_anchor {
+            let mid = self.world.tile_at_mut(anchor).mid;
+            if !mid.empty() && self.tile_db[mid].layer.spawn_anchor {
+                let bottom = anchor.to_world().y + WPosSc::from(TILE_SIZE);
+                return WorldPos {
+                    x: anchor.to_world().x,
+                    y: bottom.saturating_sub(PLAYER_HEIGHT.unsigned_abs()),
+                };
+            }
+            log::info!("Spawn anchor at {anchor:?} is gone. Falling back to world spawn.");
+            self.spawn_anchor = None;
+        }
+        self.spawn_point
     }
 
-    pub(crate) fn selected_item_is(&self, id: mdv_data::item::ItemId) -> bool {
+    pub(crate) fn selected_item_is(&self, id: ItemId) -> bool {
         self.selected_slot().map_or(false, |slot| slot.id == id)
     }
 
     pub(crate) fn selected_slot(&self) -> Option<&ItemStack> {
         self.inventory.slots.get(self.ui.selected_inv_slot)
     }
+
+    /// Add items to the inventory. Whatever doesn't fit gets dropped at the player's feet.
+    pub(crate) fn give_item(&mut self, id: ItemId, qty: ItemQty) {
+        let qty = self.inventory.add(id, qty, &self.itemdb);
+        self.drop_stack(ItemStack { id, qty, wear: 0 });
+    }
+
+    /// Drop a stack of items into the world at the player's position
+    pub(crate) fn drop_stack(&mut self, stack: ItemStack) {
+        if stack.qty == 0 {
+            return;
+        }
+        let Ok(mov) = self.ecw.query_one_mut::<&MovingEnt>(self.player_en) else {
+            log::warn!("No player to drop {} items at", stack.qty);
+            return;
+        };
+        let pos = mov.world_pos();
+        let en = self.ecw.spawn(ItemdropBundle::new_at(stack, pos));
+        self.ecw
+            .insert_one(
+                en,
+                PickupCooldown {
+                    tick_dropped: self.world.ticks,
+                    cooldown: 100,
+                },
+            )
+            .unwrap();
+    }
+
+    /// Hurt `en` like an attack without knockback would
+    pub(crate) fn hurt(&mut self, en: hecs::Entity, amount: f32) -> bool {
+        combat::hurt(self, en, amount, None)
+    }
+
+    /// Drop the contents of the container at `pos` (if any) into the world
+    pub(crate) fn spill_container_at(&mut self, pos: TilePos) {
+        if let Some(inv) = self.world.remove_container(pos) {
+            systems::spill_container(&mut self.ecw, inv, &pos);
+        }
+    }
 }
 
 pub fn for_each_tile_on_screen(
@@ -0,0 +1,147 @@
+use {
+    super::{events::Event, GameState},
+    crate::{
+        debug::{DbgOvr, DBG_OVR},
+        math::WorldPos,
+        player::{Equipment, Health, MoveExtra, MovingEnt},
+    },
+    sfml::graphics::Color,
+};
+
+/// How many ticks an entity can't be hurt by attacks after getting hit
+const IFRAMES: u16 = 30;
+/// How many ticks a knocked back entity loses control over its movement
+const STAGGER_TICKS: u8 = 12;
+/// How many ticks a damage number stays on screen
+pub const POPUP_TICKS: u16 = 60;
+
+/// Pushes the hit entity away from `from`
+pub struct Knockback {
+    pub from: WorldPos,
+    pub strength: f32,
+}
+
+/// A damage number floating above a hit entity
+pub struct DamagePopup {
+    pub pos: WorldPos,
+    pub amount: f32,
+    pub ticks_left: u16,
+}
+
+/// Hurt `en` with an attack.
+///
+/// Does nothing while the entity is in its invulnerability frames.
+/// Returns whether the attack landed.
+pub(super) fn hurt(
+    game: &mut GameState,
+    en: hecs::Entity,
+    amount: f32,
+    knockback: Option<Knockback>,
+) -> bool {
+    let Ok((health, mov, mov_extra, equipment)) = game.ecw.query_one_mut::<(
+        &mut Health,
+        Option<&mut MovingEnt>,
+        Option<&mut MoveExtra>,
+        Option<&Equipment>,
+    )>(en) else {
+        return false;
+    };
+    if health.iframes > 0 {
+        return false;
+    }
+    let stats = equipment
+        .map(|eq| eq.stats(&game.itemdb))
+        .unwrap_or_default();
+    let taken = health.damage(amount, &stats);
+    health.iframes = IFRAMES;
+    if let Some(kb) = knockback
+        && let Some(mov) = mov
+    {
+        let dir = if mov.world_pos().x < kb.from.x {
+            -1.0
+        } else {
+            1.0
+        };
+        mov.hspeed = dir * kb.strength;
+        mov.vspeed = -kb.strength / 2.0;
+        if let Some(extra) = mov_extra {
+            extra.stagger = STAGGER_TICKS;
+        }
+    }
+    game.event_buf.push(Event::Damaged {
+        en,
+        amount: taken,
+        gradual: false,
+    });
+    true
+}
+
+/// Hit every entity within `reach` pixels of `attacker`, in an `arc` (degrees) towards `aim`
+pub(super) fn melee_attack(
+    game: &mut GameState,
+    attacker: hecs::Entity,
+    aim: WorldPos,
+    damage: f32,
+    reach: u16,
+    arc: f32,
+    knockback: f32,
+) {
+    let Ok(src) = game.ecw.get::<&MovingEnt>(attacker).map(|mov| mov.center()) else {
+        return;
+    };
+    DBG_OVR.push(DbgOvr::WldCircle {
+        pos: src,
+        radius: reach,
+        c: Color::RED,
+    });
+    let (sx, sy) = src.to_signed();
+    let (ax, ay) = aim.to_signed();
+    let aim_angle = ((ay - sy) as f32).atan2((ax - sx) as f32);
+    let half_arc = arc.to_radians() / 2.0;
+    let mut targets = Vec::new();
+    for (en, (mov, _health)) in game.ecw.query::<(&MovingEnt, &Health)>().iter() {
+        if en == attacker {
+            continue;
+        }
+        let (tx, ty) = mov.center().to_signed();
+        let (dx, dy) = ((tx - sx) as f32, (ty - sy) as f32);
+        if dx.hypot(dy) > f32::from(reach) {
+            continue;
+        }
+        // Difference of the angles, wrapped to -PI..PI
+        let diff = (dy.atan2(dx) - aim_angle + std::f32::consts::PI)
+            .rem_euclid(std::f32::consts::TAU)
+            - std::f32::consts::PI;
+        if diff.abs() <= half_arc {
+            targets.push(en);
+        }
+    }
+    for en in targets {
+        hurt(
+            game,
+            en,
+            damage,
+            Some(Knockback {
+                from: src,
+                strength: knockback,
+            }),
+        );
+    }
+}
+
+/// Count down invulnerability frames and stagger, and age the damage popups
+pub(super) fn combat_tick_system(game: &mut GameState) {
+    for (_en, (health, mov_extra)) in game
+        .ecw
+        .query_mut::<(&mut Health, Option<&mut MoveExtra>)>()
+    {
+        health.iframes = health.iframes.saturating_sub(1);
+        if let Some(extra) = mov_extra {
+            extra.stagger = extra.stagger.saturating_sub(1);
+        }
+    }
+    game.damage_popups.retain_mut(|popup| {
+        popup.ticks_left = popup.ticks_left.saturating_sub(1);
+        popup.ticks_left > 0
+    });
+}
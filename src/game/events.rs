@@ -1,38 +1,87 @@
 use {
-    super::GameState,
-    crate::{audio::AudioCtx, player::Health, res::ResAudio},
+    super::{
+        combat::{DamagePopup, POPUP_TICKS},
+        GameState,
+    },
+    crate::{
+        audio::AudioCtx,
+        itemdrop::Projectile,
+        player::{Equipment, Health, MovingEnt},
+        res::ResAudio,
+    },
 };
 
 pub enum Event {
-    GroundHit { en: hecs::Entity, vspeed: f32 },
+    GroundHit {
+        en: hecs::Entity,
+        vspeed: f32,
+    },
+    /// An entity lost `amount` health
+    Damaged {
+        en: hecs::Entity,
+        amount: f32,
+        /// Damage over time (poison, starving, ...), which is dealt a little every tick
+        gradual: bool,
+    },
 }
 
 pub type EventBuf = Vec<Event>;
 
-pub fn process_events(
-    game: &mut GameState,
-    buf: EventBuf,
-    au_ctx: &mut AudioCtx,
-    au_res: &ResAudio,
-) {
-    for event in buf {
-        match event {
-            Event::GroundHit { en, vspeed } => on_ground_hit(game, en, vspeed, au_ctx, au_res),
+pub fn process_events(game: &mut GameState, au_ctx: &mut AudioCtx, au_res: &ResAudio) {
+    // Handlers can push more events, which get processed right away too
+    while !game.event_buf.is_empty() {
+        for event in std::mem::take(&mut game.event_buf) {
+            match event {
+                Event::GroundHit { en, vspeed } => on_ground_hit(game, en, vspeed),
+                Event::Damaged {
+                    en,
+                    amount,
+                    gradual,
+                } => on_damaged(game, en, amount, gradual, au_ctx, au_res),
+            }
+        }
+    }
+}
+
+fn on_ground_hit(game: &mut GameState, en: hecs::Entity, vspeed: f32) {
+    // Projectiles stop being dangerous once they land
+    let _ = game.ecw.remove_one::<Projectile>(en);
+    if vspeed > 18. {
+        if let Ok((health, equipment)) = game
+            .ecw
+            .query_one_mut::<(&mut Health, Option<&Equipment>)>(en)
+        {
+            let stats = equipment
+                .map(|eq| eq.stats(&game.itemdb))
+                .unwrap_or_default();
+            let amount = health.damage((vspeed - 18.) * 1.75, &stats);
+            game.event_buf.push(Event::Damaged {
+                en,
+                amount,
+                gradual: false,
+            });
         }
     }
 }
 
-fn on_ground_hit(
+fn on_damaged(
     game: &mut GameState,
     en: hecs::Entity,
-    vspeed: f32,
+    amount: f32,
+    gradual: bool,
     au_ctx: &mut AudioCtx,
     au_res: &ResAudio,
 ) {
-    if vspeed > 18. {
-        if let Ok(health) = game.ecw.query_one_mut::<&mut Health>(en) {
-            au_ctx.plr.play(au_res, "etc/ouch");
-            health.damage((vspeed - 18.) * 1.75);
-        }
+    // A sound and a number every tick would be too much for damage over time
+    if amount <= 0.0 || gradual {
+        return;
+    }
+    au_ctx.plr.play(au_res, "etc/ouch");
+    if let Ok(mov) = game.ecw.get::<&MovingEnt>(en) {
+        game.damage_popups.push(DamagePopup {
+            pos: mov.world_pos(),
+            amount,
+            ticks_left: POPUP_TICKS,
+        });
     }
 }
@@ -1,5 +1,6 @@
 pub mod debug;
 pub mod game;
+pub mod map;
 pub mod ui;
 
 use sfml::{
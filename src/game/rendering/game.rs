@@ -2,18 +2,19 @@ use {
     super::debug::draw_controlled_en_bb,
     crate::{
         debug::DebugState,
-        game::{for_each_tile_on_screen, Biome, GameState, TilestateKey},
+        game::{combat::POPUP_TICKS, for_each_tile_on_screen, GameState, TilestateKey},
         light::{self, LightEnumInfo, U16Vec},
         math::{IntRectExt, ScreenVecExt, WorldPos, TILE_SIZE},
-        player::{FacingDir, MoveExtra, MovingEnt, PlayerColors},
+        placed::{PlacedEnt, Tombstone},
+        player::{Equipment, FacingDir, MoveExtra, MovingEnt, PlayerColors},
         res::Res,
         sfml::{SpriteExt, WorldRectExt},
     },
-    mdv_data::{item::ItemId, tile::LayerAccess},
+    mdv_data::{item::ItemStack, tile::LayerAccess},
     mdv_math::types::ScreenVec,
     sfml::graphics::{
         Color, PrimitiveType, Rect, RectangleShape, RenderStates, RenderTarget, RenderTexture,
-        Shape, Sprite, Transform, Transformable, Vertex,
+        Shape, Sprite, Text, Transform, Transformable, Vertex,
     },
 };
 
@@ -24,15 +25,19 @@ pub(crate) fn draw_world(
     res: &Res,
 ) {
     verts.clear();
-    match game.current_biome {
-        Biome::Surface => {
-            let mut s = Sprite::with_texture(&res.forest_bg);
+    let bg = game
+        .current_biome
+        .background(&game.world.worldgen)
+        .and_then(|key| res.backgrounds.get(key));
+    match bg {
+        Some(tex) => {
+            let mut s = Sprite::with_texture(tex);
             s.fit_to_size(rt.size().as_other());
             let c = game.ambient_light;
             s.set_color(Color::rgb(c, c, c));
             rt.draw(&s);
         }
-        Biome::Underground => rt.clear(Color::rgb(0, 10, 30)),
+        None => rt.clear(Color::rgb(0, 10, 30)),
     }
     for_each_tile_on_screen(
         game.camera_offset,
@@ -139,16 +144,59 @@ pub(crate) fn draw_world(
 }
 pub fn draw_entities(game: &mut GameState, rt: &mut RenderTexture, res: &Res, debug: &DebugState) {
     let mut s = Sprite::with_texture(&res.atlas.tex);
+    let (co_x, co_y) = game.camera_offset.to_signed();
+    for (_en, placed) in game.ecw.query_mut::<&PlacedEnt>() {
+        let Some(rect) = res.atlas.rects.get(&placed.graphic) else {
+            log::error!("Missing rect for placed entity graphic {}", placed.graphic);
+            continue;
+        };
+        let (x, y) = placed.rect().topleft.to_signed();
+        s.set_position(((x - co_x) as f32, (y - co_y) as f32));
+        s.set_texture_rect(rect.to_sf());
+        rt.draw(&s);
+    }
+    if let Some(rect) = res.atlas.rects.get(Tombstone::GRAPHIC) {
+        s.set_texture_rect(rect.to_sf());
+        for (_en, tomb) in game.ecw.query_mut::<&Tombstone>() {
+            let (x, y) = tomb.rect().topleft.to_signed();
+            s.set_position(((x - co_x) as f32, (y - co_y) as f32));
+            rt.draw(&s);
+        }
+    }
     s.set_origin((16., 16.));
-    for (_en, (id, mov)) in game.ecw.query_mut::<(&ItemId, &MovingEnt)>() {
+    for (_en, (stack, mov)) in game.ecw.query_mut::<(&ItemStack, &MovingEnt)>() {
         let pos = mov.mob.en.pos;
-        let item_def = &game.itemdb.get(*id).unwrap();
-        let (co_x, co_y) = game.camera_offset.to_signed();
+        let item_def = &game.itemdb.get(stack.id).unwrap();
         s.set_position(((pos.x - co_x) as f32, (pos.y - co_y) as f32));
         s.set_texture_rect(item_def.tex_rect.to_sf());
         rt.draw(&s);
     }
     draw_player(game, rt, debug, res);
+    draw_damage_popups(game, rt, res);
+}
+
+/// Damage numbers rising above hit entities and fading away
+fn draw_damage_popups(game: &GameState, rt: &mut RenderTexture, res: &Res) {
+    let mut text = Text::new("", &res.sans_font, 16);
+    text.set_outline_thickness(1.0);
+    let (co_x, co_y) = game.camera_offset.to_signed();
+    for popup in &game.damage_popups {
+        let age = POPUP_TICKS - popup.ticks_left;
+        let (x, y) = popup.pos.to_signed();
+        text.set_position((
+            (x - co_x) as f32,
+            (y - co_y) as f32 - 16.0 - f32::from(age) / 2.0,
+        ));
+        #[expect(
+            clippy::cast_possible_truncation,
+            reason = "ticks_left is never above POPUP_TICKS"
+        )]
+        let alpha = (u32::from(popup.ticks_left) * 255 / u32::from(POPUP_TICKS)) as u8;
+        text.set_fill_color(Color::rgba(255, 80, 60, alpha));
+        text.set_outline_color(Color::rgba(0, 0, 0, alpha));
+        text.set_string(&format!("{:.0}", popup.amount.ceil()));
+        rt.draw(&text);
+    }
 }
 
 fn draw_player(game: &mut GameState, rt: &mut RenderTexture, debug: &DebugState, res: &Res) {
@@ -159,9 +207,11 @@ fn draw_player(game: &mut GameState, rt: &mut RenderTexture, debug: &DebugState,
 }
 
 fn draw_player_sprites(game: &mut GameState, rt: &mut RenderTexture, res: &Res) {
-    let Ok((mov, mov_extra, colors)) = game
-        .ecw
-        .query_one_mut::<(&MovingEnt, &MoveExtra, &PlayerColors)>(game.player_en)
+    let Ok((mov, mov_extra, colors, equipment)) =
+        game.ecw
+            .query_one_mut::<(&MovingEnt, &MoveExtra, &PlayerColors, Option<&Equipment>)>(
+                game.player_en,
+            )
     else {
         log::error!("Player query failed");
         return;
@@ -171,7 +221,12 @@ fn draw_player_sprites(game: &mut GameState, rt: &mut RenderTexture, res: &Res)
     let (co_x, co_y) = game.camera_offset.to_signed();
     let (base_x, base_y) = ((x - co_x) as f32, (y - co_y) as f32);
     let held_item_id = game.inventory.slots[game.ui.selected_inv_slot].id;
-    let held_item_graphic = game.itemdb.get(held_item_id).map(|def| &def.graphic_name);
+    // Equipment is drawn as worn, not as held
+    let held_item_graphic = game
+        .itemdb
+        .get(held_item_id)
+        .filter(|def| def.equip.is_none())
+        .map(|def| &def.graphic_name);
     let mut drawable_tool = false;
     #[rustfmt::skip]
     let [mut head_x, mut eye_x, mut hair_x, mut torso_x, mut legs_x, mut tool_x,
@@ -292,6 +347,31 @@ fn draw_player_sprites(game: &mut GameState, rt: &mut RenderTexture, res: &Res)
     s.set_position((base_x + shoes_x, base_y + shoes_y));
     s.set_color(colors.shoes);
     rt.draw(&s);
+    // Worn equipment, layered over the clothes
+    s.set_color(Color::WHITE);
+    for stack in equipment.iter().flat_map(|eq| &eq.slots) {
+        let Some(graphic) = game
+            .itemdb
+            .get(stack.id)
+            .and_then(|def| def.equip.as_ref())
+            .and_then(|equip| equip.worn_graphic.as_ref())
+        else {
+            continue;
+        };
+        let (Some(rect), Some(offs)) = (
+            res.atlas.rects.get(graphic),
+            game.char_db.graphic_offsets.get(graphic),
+        ) else {
+            continue;
+        };
+        let off = match mov_extra.facing_dir {
+            FacingDir::Left => &offs.left,
+            FacingDir::Right => &offs.right,
+        };
+        s.set_texture_rect(rect.to_sf());
+        s.set_position((base_x + f32::from(off.x), base_y + f32::from(off.y)));
+        rt.draw(&s);
+    }
     // Tool
     if let Some(graphic) = held_item_graphic
         && drawable_tool
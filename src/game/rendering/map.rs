@@ -0,0 +1,146 @@
+use {
+    crate::{
+        game::GameState,
+        map::{tile_to_map, WorldMap, CHUNK_MAP_PX},
+        res::Res,
+        sfml::RenderTargetExt,
+    },
+    sfml::{
+        graphics::{
+            CircleShape, Color, FloatRect, RectangleShape, RenderTarget, RenderTexture, Shape,
+            Sprite, Text, Transformable, View,
+        },
+        system::Vector2f,
+    },
+};
+
+/// Width and height of the minimap in the top right corner
+const MINIMAP_SIZE: f32 = 160.0;
+const MINIMAP_MARGIN: f32 = 8.0;
+const PLAYER_MARKER_COLOR: Color = Color::rgb(80, 255, 80);
+const WAYPOINT_MARKER_COLOR: Color = Color::rgb(255, 220, 60);
+
+pub fn draw_minimap(game: &GameState, rt: &mut RenderTexture) {
+    let Some(player_tile) = game.map.explored_from else {
+        return;
+    };
+    let rt_size: Vector2f = rt.size().as_other();
+    let frame = FloatRect::new(
+        rt_size.x - MINIMAP_SIZE - MINIMAP_MARGIN,
+        MINIMAP_MARGIN,
+        MINIMAP_SIZE,
+        MINIMAP_SIZE,
+    );
+    let mut rs = RectangleShape::from_rect(frame);
+    rs.set_fill_color(Color::rgba(0, 0, 0, 160));
+    rs.set_outline_color(Color::rgb(120, 120, 120));
+    rs.set_outline_thickness(1.0);
+    rt.draw(&rs);
+    // One screen pixel per map pixel, centered on the player
+    let center = tile_to_map(player_tile);
+    let mut view = View::from_rect(FloatRect::new(
+        center.x - MINIMAP_SIZE / 2.,
+        center.y - MINIMAP_SIZE / 2.,
+        MINIMAP_SIZE,
+        MINIMAP_SIZE,
+    ))
+    .unwrap();
+    view.set_viewport(FloatRect::new(
+        frame.left / rt_size.x,
+        frame.top / rt_size.y,
+        frame.width / rt_size.x,
+        frame.height / rt_size.y,
+    ));
+    rt.set_view(&view);
+    draw_chunks(&game.map, rt, view.center(), view.size());
+    let mut marker = CircleShape::new(2.0, 8);
+    marker.set_origin((2.0, 2.0));
+    marker.set_fill_color(WAYPOINT_MARKER_COLOR);
+    for wp in &game.map.waypoints {
+        marker.set_position(tile_to_map(wp.pos));
+        rt.draw(&marker);
+    }
+    marker.set_fill_color(PLAYER_MARKER_COLOR);
+    marker.set_position(center);
+    rt.draw(&marker);
+    reset_view(rt, rt_size);
+}
+
+pub fn draw_map_screen(game: &GameState, rt: &mut RenderTexture, res: &Res) {
+    let wnd = &game.ui.map;
+    let rt_size: Vector2f = rt.size().as_other();
+    rt.clear(Color::BLACK);
+    let view_size = rt_size / wnd.zoom;
+    let view = View::from_rect(FloatRect::new(
+        wnd.center.x - view_size.x / 2.,
+        wnd.center.y - view_size.y / 2.,
+        view_size.x,
+        view_size.y,
+    ))
+    .unwrap();
+    rt.set_view(&view);
+    draw_chunks(&game.map, rt, wnd.center, view_size);
+    reset_view(rt, rt_size);
+    // Markers and names stay the same size at every zoom level
+    let rt_res = rt.res();
+    let mut marker = CircleShape::new(4.0, 12);
+    marker.set_origin((4.0, 4.0));
+    marker.set_outline_color(Color::BLACK);
+    marker.set_outline_thickness(1.0);
+    let mut text = Text::new("", &res.sans_font, 14);
+    text.set_outline_thickness(2.0);
+    marker.set_fill_color(WAYPOINT_MARKER_COLOR);
+    for wp in &game.map.waypoints {
+        let pos = wnd.map_to_screen(tile_to_map(wp.pos), rt_res);
+        marker.set_position(pos);
+        rt.draw(&marker);
+        text.set_string(&wp.name);
+        text.set_position(pos + Vector2f::new(6.0, -9.0));
+        rt.draw(&text);
+    }
+    if let Some((pos, _)) = &wnd.new_waypoint {
+        marker.set_position(wnd.map_to_screen(tile_to_map(*pos), rt_res));
+        rt.draw(&marker);
+    }
+    if let Some(player_tile) = game.map.explored_from {
+        marker.set_fill_color(PLAYER_MARKER_COLOR);
+        marker.set_position(wnd.map_to_screen(tile_to_map(player_tile), rt_res));
+        rt.draw(&marker);
+    }
+    text.set_string("Drag or move to pan, scroll to zoom, right click to place a waypoint");
+    text.set_position((8.0, rt_size.y - 24.0));
+    rt.draw(&text);
+}
+
+/// Draw the images of the explored chunks inside of the area of the map pixels
+/// centered at `center`
+fn draw_chunks(map: &WorldMap, rt: &mut RenderTexture, center: Vector2f, size: Vector2f) {
+    let visible = FloatRect::new(
+        center.x - size.x / 2.,
+        center.y - size.y / 2.,
+        size.x,
+        size.y,
+    );
+    let chunk_px = f32::from(CHUNK_MAP_PX);
+    let mut spr = Sprite::new();
+    for (pos, tex) in map.chunks() {
+        let rect = FloatRect::new(
+            f32::from(pos.x) * chunk_px,
+            f32::from(pos.y) * chunk_px,
+            chunk_px,
+            chunk_px,
+        );
+        if rect.intersection(&visible).is_none() {
+            continue;
+        }
+        spr.set_texture(tex, true);
+        spr.set_position((rect.left, rect.top));
+        rt.draw(&spr);
+    }
+}
+
+/// Go back to drawing in screen pixels
+fn reset_view(rt: &mut RenderTexture, rt_size: Vector2f) {
+    let view = View::from_rect(FloatRect::new(0., 0., rt_size.x, rt_size.y)).unwrap();
+    rt.set_view(&view);
+}
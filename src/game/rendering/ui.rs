@@ -2,16 +2,20 @@ use {
     crate::{
         config::Config,
         debug::DebugState,
-        game::{ui::Inventory, GameState},
+        game::GameState,
         math::{IntRectExt, FPS_TARGET, TILE_SIZE},
-        player::{Health, MovingEnt},
+        player::{Equipment, Health, MovingEnt},
         res::Res,
+        save::DeathRule,
         sfml::{RectangleShapeExt, RenderTargetExt, ScreenRectSfExt, ScreenVecSfExt, SfVec2fExt},
+        status::{self, StatusEffects},
         stringfmt::LengthDisp,
+        survival::{Survival, VitalKind},
         time::ticks_hm,
     },
     gamedebug_core::imm_dbg,
-    mdv_math::types::ScreenVec,
+    mdv_data::item::{ItemDb, ItemDef, ItemStack},
+    mdv_math::types::{ScreenRect, ScreenVec},
     sfml::{
         graphics::{
             Color, Rect, RectangleShape, RenderTarget, RenderTexture, Shape, Sprite, Text,
@@ -30,6 +34,10 @@ pub fn draw_ui(
     mouse_pos: ScreenVec,
     scale: u8,
 ) {
+    if game.ui.map.open {
+        super::map::draw_map_screen(game, rt, res);
+        return;
+    }
     if game.ui.inv.open {
         draw_inventory(game, cfg, rt, res, mouse_pos, scale);
     }
@@ -63,25 +71,65 @@ pub fn draw_ui(
     let (hh, mm) = ticks_hm(game.world.ticks);
     text.set_string(&format!("Time: {hh:02}:{mm:02}"));
     rt.draw(&text);
+    draw_vitals(game, rt, &mut text);
+    draw_effects(game, rt, res, &mut text);
+    super::map::draw_minimap(game, rt);
     if game.player_en == hecs::Entity::DANGLING {
-        text.set_character_size(30);
-        text.set_position((200., 200.));
-        text.set_string("YOU ARE DEAD");
-        text.set_fill_color(Color::RED);
-        rt.draw(&text);
-        text.move_((0., 32.));
-        text.set_fill_color(Color::YELLOW);
-        text.set_string(&format!(
-            "Respawning in: {}",
-            game.respawn_timer / u32::from(FPS_TARGET)
-        ));
-        rt.draw(&text);
+        draw_death_screen(game, rt, &mut text);
     }
     if game.ui.menu.open {
         draw_menu(game, rt, res);
     }
 }
 
+fn draw_death_screen(game: &GameState, rt: &mut RenderTexture, text: &mut Text) {
+    let mut shade = RectangleShape::new();
+    shade.set_size(rt.size().as_other());
+    shade.set_fill_color(Color::rgba(0, 0, 0, 140));
+    rt.draw(&shade);
+    text.set_character_size(30);
+    text.set_position((200., 200.));
+    text.set_fill_color(Color::RED);
+    if game.finished {
+        text.set_string("GAME OVER");
+        rt.draw(&*text);
+        text.move_((0., 40.));
+        text.set_character_size(18);
+        text.set_fill_color(Color::WHITE);
+        text.set_string("You died in hardcore mode. This world is finished.");
+        rt.draw(&*text);
+        return;
+    }
+    text.set_string("YOU ARE DEAD");
+    rt.draw(&*text);
+    text.move_((0., 40.));
+    text.set_character_size(18);
+    text.set_fill_color(Color::WHITE);
+    let fate = match (game.rules.death, game.death_tombstone) {
+        (DeathRule::KeepInventory, _) => "You kept your items.".to_string(),
+        (_, Some(pos)) => format!(
+            "Your items were left in a tombstone at depth {}.",
+            LengthDisp(pos.depth() as f32)
+        ),
+        (_, None) => "You had nothing to lose.".to_string(),
+    };
+    text.set_string(&fate);
+    rt.draw(&*text);
+    text.move_((0., 32.));
+    text.set_character_size(24);
+    text.set_fill_color(Color::YELLOW);
+    let place = if game.spawn_anchor.is_some() {
+        " at your bed"
+    } else {
+        ""
+    };
+    text.set_string(&format!(
+        "Respawning{place} in: {}",
+        game.respawn_timer.div_ceil(u32::from(FPS_TARGET))
+    ));
+    rt.draw(&*text);
+}
+
 fn draw_hotbar(
     game: &GameState,
     text: &mut Text<'_>,
@@ -144,12 +192,89 @@ fn draw_hotbar(
                 text.set_string(&slot.qty.to_string());
                 rt.draw(&*text);
             }
+            draw_durability_bar(rt, Vector2f::from((x, y)), slot, item_def);
         } else {
             log::error!("Missing rect for item {}", item_def.name);
         }
     }
 }
 
+/// Bar at the bottom of an item slot showing how much durability is left
+fn draw_durability_bar(
+    rt: &mut RenderTexture,
+    slot_pos: Vector2f,
+    stack: &ItemStack,
+    item_def: &ItemDef,
+) {
+    if item_def.durability == 0 || stack.wear == 0 {
+        return;
+    }
+    let left = 1.0 - f32::from(stack.wear) / f32::from(item_def.durability);
+    let full_w = 28.0;
+    let mut rs =
+        RectangleShape::from_rect(Rect::new(slot_pos.x + 4., slot_pos.y + 30., full_w, 3.));
+    rs.set_fill_color(Color::rgba(0, 0, 0, 160));
+    rt.draw(&rs);
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "left is between 0 and 1"
+    )]
+    let (r, g) = (((1.0 - left) * 255.) as u8, (left * 255.) as u8);
+    rs.set_size((full_w * left, 3.));
+    rs.set_fill_color(Color::rgb(r, g, 0));
+    rt.draw(&rs);
+}
+
+/// Bars for food, stamina and breath of the controlled entity
+fn draw_vitals(game: &GameState, rt: &mut RenderTexture, text: &mut Text<'_>) {
+    let Ok(survival) = game.ecw.get::<&Survival>(game.controlled_en) else {
+        return;
+    };
+    text.set_character_size(14);
+    let full_w = 100.0;
+    let mut y = 76.0;
+    for kind in VitalKind::ALL {
+        let vital = survival.get(kind);
+        text.set_position((0., y));
+        text.set_string(kind.name());
+        rt.draw(&*text);
+        let mut rs = RectangleShape::from_rect(Rect::new(64., y + 4., full_w, 10.));
+        rs.set_fill_color(Color::rgba(0, 0, 0, 160));
+        rt.draw(&rs);
+        rs.set_size((full_w * vital.fraction(), 10.));
+        rs.set_fill_color(match kind {
+            VitalKind::Food => Color::rgb(200, 140, 60),
+            VitalKind::Stamina => Color::rgb(220, 220, 80),
+            VitalKind::Breath => Color::rgb(100, 170, 255),
+        });
+        rt.draw(&rs);
+        y += 18.0;
+    }
+}
+
+/// Icons of the status effects on the controlled entity, with the seconds left
+fn draw_effects(game: &GameState, rt: &mut RenderTexture, res: &Res, text: &mut Text<'_>) {
+    let Ok(effects) = game.ecw.get::<&StatusEffects>(game.controlled_en) else {
+        return;
+    };
+    let mut s = Sprite::with_texture(&res.atlas.tex);
+    text.set_character_size(12);
+    let y = 132.0;
+    let mut x = 0.0;
+    for eff in &effects.effects {
+        if let Some(rect) = res.atlas.rects.get(status::icon_graphic(eff.kind)) {
+            s.set_texture_rect(rect.to_sf());
+            s.set_position((x, y));
+            rt.draw(&s);
+        }
+        text.set_position((x, y + 16.));
+        text.set_string(&(eff.duration / u32::from(FPS_TARGET)).to_string());
+        rt.draw(&*text);
+        x += 24.0;
+    }
+}
+
 fn draw_menu(game: &GameState, rt: &mut RenderTexture, res: &Res) {
     let Some(list) = game.ui.menu.stack.last() else {
         log::warn!("Trying to draw empty menu");
@@ -185,11 +310,79 @@ fn draw_inventory(
     mouse_pos: ScreenVec,
     scale: u8,
 ) {
-    let rt_res = rt.res();
-    let rect = Inventory::screen_rect(rt_res, game.inventory.slots.len());
-    let sf_rect = rect.into_sf();
-    let mut rs = RectangleShape::from_rect(sf_rect);
-    let mut s = Sprite::with_texture(&res.atlas.tex);
+    imm_dbg!(game.inventory.slots.len());
+    draw_inventory_frame(rt, res, cfg, game.ui.inv_frame, "Inventory");
+    draw_slots(
+        rt,
+        res,
+        cfg,
+        &game.itemdb,
+        &game.inventory.slots,
+        &game.ui.inv_rects,
+        Some(game.ui.selected_inv_slot),
+    );
+    if let Ok(equipment) = game.ecw.get::<&Equipment>(game.player_en) {
+        draw_inventory_frame(rt, res, cfg, game.ui.equip_frame, "Gear");
+        draw_slots(
+            rt,
+            res,
+            cfg,
+            &game.itemdb,
+            &equipment.slots,
+            &game.ui.equip_rects,
+            None,
+        );
+    }
+    if let Some(frame) = game.ui.container_frame
+        && let Some(container) = game
+            .ui
+            .container
+            .and_then(|pos| game.world.container_at(pos))
+    {
+        draw_inventory_frame(rt, res, cfg, frame, "Storage");
+        draw_slots(
+            rt,
+            res,
+            cfg,
+            &game.itemdb,
+            &container.slots,
+            &game.ui.container_rects,
+            None,
+        );
+    }
+    if let Some(grabbed) = &game.inventory.grabbed {
+        let Some(item_def) = &game.itemdb.get(grabbed.id) else {
+            return;
+        };
+        let pos = mouse_pos.scaled(scale).to_sf_vec2f();
+        if let Some(rect) = res.atlas.rects.get(&item_def.graphic_name) {
+            let mut s = Sprite::with_texture(&res.atlas.tex);
+            let mut rect = rect.to_sf();
+            rect.width = rect.width.min(i32::from(TILE_SIZE));
+            rect.height = rect.height.min(i32::from(TILE_SIZE));
+            s.set_texture_rect(rect);
+            s.set_position(Vector2f::from(pos).scv_off(item_def.draw_off));
+            rt.draw(&s);
+            if item_def.stackable {
+                let mut text = slot_text(res);
+                text.set_position(Vector2f::from(pos).scv_off(ScreenVec { x: 2, y: 22 }));
+                text.set_string(&grabbed.qty.to_string());
+                rt.draw(&text);
+            }
+        } else {
+            log::error!("Missing rect for item {}", item_def.name);
+        }
+    }
+}
+
+fn draw_inventory_frame(
+    rt: &mut RenderTexture,
+    res: &Res,
+    cfg: &Config,
+    rect: ScreenRect,
+    title: &str,
+) {
+    let mut rs = RectangleShape::from_rect(rect.into_sf());
     rs.set_fill_color(Color::TRANSPARENT);
     rs.set_outline_thickness(3.0);
     rs.set_outline_color(cfg.ui.inv_frame_color.coffset(50).to_sf());
@@ -201,30 +394,44 @@ fn draw_inventory(
     rs.set_outline_color(cfg.ui.inv_frame_color.coffset(-50).to_sf());
     rs.set_fill_color(cfg.ui.inv_bg_color.to_sf());
     rt.draw(&rs);
-    let mut text = Text::new("Inventory", &res.sans_font, 16);
+    let mut text = Text::new(title, &res.sans_font, 16);
     text.set_outline_thickness(1.0);
     text.set_outline_color(Color::BLACK);
     text.set_position((f32::from(rect.x + 2), f32::from(rect.y)));
     rt.draw(&text);
-    text.set_character_size(14);
+}
+
+/// Text for drawing item quantities
+fn slot_text(res: &Res) -> Text<'_> {
+    let mut text = Text::new("", &res.sans_font, 14);
+    text.set_outline_thickness(1.0);
+    text.set_outline_color(Color::BLACK);
+    text
+}
+
+/// Draw the item slots of an inventory grid
+fn draw_slots(
+    rt: &mut RenderTexture,
+    res: &Res,
+    cfg: &Config,
+    itemdb: &ItemDb,
+    slots: &[ItemStack],
+    rects: &[ScreenRect],
+    selected: Option<usize>,
+) {
+    let mut rs = RectangleShape::new();
+    let mut s = Sprite::with_texture(&res.atlas.tex);
+    let mut text = slot_text(res);
     let inv_bg_color = cfg.ui.inv_bg_color.to_sf();
     let inv_frame_highlight = cfg.ui.inv_frame_highlight.to_sf();
     let inv_frame_color = cfg.ui.inv_frame_color.to_sf();
-    imm_dbg!(game.inventory.slots.len());
-    for ((i, slot), srect) in game
-        .inventory
-        .slots
-        .iter()
-        .enumerate()
-        .zip(&game.ui.inv_rects)
-    {
+    for ((i, slot), srect) in slots.iter().enumerate().zip(rects) {
         let pos = srect.sf_position();
         rs.set_screen_rect(srect.offset(1, 1));
         rs.set_fill_color(inv_bg_color);
-        if i == game.ui.selected_inv_slot {
+        if selected == Some(i) {
             s.set_color(inv_frame_highlight);
         } else {
-            rs.set_outline_thickness(0.0);
             s.set_color(inv_frame_color);
         }
         rt.draw(&rs);
@@ -232,7 +439,7 @@ fn draw_inventory(
         s.set_position(pos);
         rt.draw(&s);
         s.set_color(Color::WHITE);
-        let Some(item_def) = &game.itemdb.get(slot.id) else {
+        let Some(item_def) = &itemdb.get(slot.id) else {
             continue;
         };
         if let Some(rect) = res.atlas.rects.get(&item_def.graphic_name) {
@@ -247,27 +454,7 @@ fn draw_inventory(
                 text.set_string(&slot.qty.to_string());
                 rt.draw(&text);
             }
-        } else {
-            log::error!("Missing rect for item {}", item_def.name);
-        }
-    }
-    if let Some(grabbed) = &game.inventory.grabbed {
-        let Some(item_def) = &game.itemdb.get(grabbed.id) else {
-            return;
-        };
-        let pos = mouse_pos.scaled(scale).to_sf_vec2f();
-        if let Some(rect) = res.atlas.rects.get(&item_def.graphic_name) {
-            let mut rect = rect.to_sf();
-            rect.width = rect.width.min(i32::from(TILE_SIZE));
-            rect.height = rect.height.min(i32::from(TILE_SIZE));
-            s.set_texture_rect(rect);
-            s.set_position(Vector2f::from(pos).scv_off(item_def.draw_off));
-            rt.draw(&s);
-            if item_def.stackable {
-                text.set_position(Vector2f::from(pos).scv_off(ScreenVec { x: 2, y: 22 }));
-                text.set_string(&grabbed.qty.to_string());
-                rt.draw(&text);
-            }
+            draw_durability_bar(rt, Vector2f::from(pos), slot, item_def);
         } else {
             log::error!("Missing rect for item {}", item_def.name);
         }
@@ -0,0 +1,34 @@
+use {super::GameState, crate::player::MovingEnt};
+
+/// A script hook that items can run with [`UseAction::Script`].
+///
+/// Returns whether the script did anything, which wears down the item.
+///
+/// [`UseAction::Script`]: mdv_data::item::UseAction::Script
+type ScriptFn = fn(game: &mut GameState, user: hecs::Entity) -> bool;
+
+/// All script hooks, keyed by the name items refer to them with
+const SCRIPTS: &[(&str, ScriptFn)] = &[("recall", recall)];
+
+/// Run the script hook called `name` for `user`
+pub(super) fn run(game: &mut GameState, name: &str, user: hecs::Entity) -> bool {
+    match SCRIPTS.iter().find(|(key, _)| *key == name) {
+        Some((_, f)) => f(game, user),
+        None => {
+            log::error!("Unknown script hook '{name}'");
+            false
+        }
+    }
+}
+
+/// Teleport back to the spawn point (or bed)
+fn recall(game: &mut GameState, user: hecs::Entity) -> bool {
+    let pos = game.respawn_pos();
+    let Ok(mov) = game.ecw.query_one_mut::<&mut MovingEnt>(user) else {
+        return false;
+    };
+    mov.mob.en.pos = pos.to_s2dc();
+    mov.hspeed = 0.;
+    mov.vspeed = 0.;
+    true
+}
@@ -1,6 +1,10 @@
 use {
     self::pause_menu::open_menu,
-    super::{events::Event, Biome, GameState, TransientTileState, TransientTileStates},
+    super::{
+        combat::{self, Knockback},
+        events::Event,
+        scripts, Biome, GameState, TransientTileState, TransientTileStates,
+    },
     crate::{
         app::TileColEn,
         audio::AudioCtx,
@@ -9,23 +13,30 @@ use {
         egui_ext::EguiUiExt,
         graphics::ScreenRes,
         input::{Input, InputAction},
-        itemdrop::{ItemdropBundle, PickupCooldown},
-        math::{world_y_depth, WorldPos, WorldRect, TILE_SIZE},
-        player::{FacingDir, Health, MoveExtra, MovingEnt},
+        inventory::{self, Inventory, HOTBAR_SLOTS},
+        itemdrop::{ItemdropBundle, PickupCooldown, Projectile},
+        map::tile_to_map,
+        math::{world_y_depth, WPosSc, WorldPos, WorldRect, M_PER_PX, TILE_SIZE, WORLD_EXTENT_PX},
+        placed::{PlacedEnt, Tombstone},
+        player::{Equipment, FacingDir, Health, MoveExtra, MovingEnt},
         res::{Res, ResAudio},
+        save::DeathRule,
+        status::StatusEffects,
+        survival::{Conditions, Survival, VitalKind},
         world::{TilePos, World},
     },
     egui_sfml::egui,
     mdv_data::{
-        item::{ItemId, ItemStack, UseAction},
+        item::{EquipSlot, ItemId, ItemStack, StatMods, UseAction},
         tile::{LayerAccess, TileDb, TileDef, TileId, TileLayer},
     },
     mdv_math::{types::ScreenVec, util::step_towards},
     rand::{seq::SliceRandom, thread_rng, Rng},
-    sfml::{graphics::Color, window::Key},
+    sfml::graphics::Color,
     std::ops::{ControlFlow, Index},
 };
 
+pub mod map;
 pub mod pause_menu;
 
 enum DominantOffset {
@@ -55,10 +66,17 @@ pub(super) fn item_use_system(
     mouse_wpos: WorldPos,
     debug: &DebugState,
 ) {
-    let Ok(mov) = game.ecw.query_one_mut::<&mut MovingEnt>(game.controlled_en) else {
+    let Ok((mov, equipment, survival, health, effects)) = game.ecw.query_one_mut::<(
+        &mut MovingEnt,
+        Option<&Equipment>,
+        Option<&mut Survival>,
+        Option<&mut Health>,
+        Option<&mut StatusEffects>,
+    )>(game.controlled_en) else {
         log::warn!("No controlled entity");
         return;
     };
+    let stats = equipment.map_or_else(StatMods::default, |eq| eq.stats(&game.itemdb));
     // Without an active item, we don't even know how we want to do targeting
     let Some(active_slot) = game.inventory.slots.get_mut(game.ui.selected_inv_slot) else {
         log::error!("Selected slot {} out of bounds", game.ui.selected_inv_slot);
@@ -144,11 +162,172 @@ pub(super) fn item_use_system(
     } else {
         return;
     };
-    // Not all use actions might need a target tile position, but right now they do
+    // Actions that don't target a tile happen once per click
+    let pressed = if input.lmb_down {
+        input.lmb_pressed
+    } else {
+        input.rmb_pressed
+    };
+    let untargeted = match action {
+        UseAction::Consume {
+            food,
+            stamina,
+            breath,
+            health: heal,
+            effects: new_effects,
+        } => {
+            if pressed {
+                if let Some(survival) = survival {
+                    survival.get_mut(VitalKind::Food).change(*food);
+                    survival.get_mut(VitalKind::Stamina).change(*stamina);
+                    survival.get_mut(VitalKind::Breath).change(*breath);
+                }
+                if let Some(health) = health {
+                    health.heal(*heal);
+                }
+                if let Some(effects) = effects {
+                    for eff in new_effects {
+                        effects.apply(*eff);
+                    }
+                }
+                active_slot.qty -= 1;
+            }
+            true
+        }
+        UseAction::Throw {
+            speed,
+            damage,
+            knockback,
+        } => {
+            if pressed {
+                let en = game.ecw.spawn(ItemdropBundle::thrown(
+                    ItemStack {
+                        id: active_slot.id,
+                        qty: 1,
+                        wear: active_slot.wear,
+                    },
+                    player_pos,
+                    mouse_wpos,
+                    *speed,
+                ));
+                game.ecw
+                    .insert(
+                        en,
+                        (
+                            PickupCooldown {
+                                tick_dropped: game.world.ticks,
+                                cooldown: 100,
+                            },
+                            Projectile {
+                                damage: *damage,
+                                knockback: *knockback,
+                                thrower: game.controlled_en,
+                            },
+                        ),
+                    )
+                    .unwrap();
+                active_slot.qty -= 1;
+            }
+            true
+        }
+        UseAction::SpawnEntity {
+            graphic,
+            size,
+            climbable,
+        } => {
+            if pressed && (ptr_within_circle || debug.freecam) {
+                // Hang from the cursor, like a rope from a ledge
+                let half_w = WPosSc::try_from(size.x / 2).unwrap_or(0);
+                game.ecw.spawn((PlacedEnt {
+                    x: mouse_wpos.x.saturating_sub(half_w),
+                    y: mouse_wpos.y,
+                    size: *size,
+                    graphic: graphic.clone(),
+                    climbable: *climbable,
+                    item: active_slot.id,
+                },));
+                active_slot.qty -= 1;
+            }
+            true
+        }
+        UseAction::Script { name } => {
+            if pressed {
+                let name = name.clone();
+                let user = game.controlled_en;
+                if scripts::run(game, &name, user) {
+                    wear_selected_item(game);
+                }
+            }
+            return;
+        }
+        UseAction::Melee {
+            damage,
+            reach,
+            arc,
+            knockback,
+            cooldown,
+        } => {
+            if ticks - game.last_attack >= *cooldown {
+                game.last_attack = ticks;
+                let (damage, reach, arc, knockback) = (*damage, *reach, *arc, *knockback);
+                let attacker = game.controlled_en;
+                combat::melee_attack(game, attacker, mouse_wpos, damage, reach, arc, knockback);
+                wear_selected_item(game);
+            }
+            return;
+        }
+        UseAction::Shoot {
+            ammo,
+            speed,
+            damage,
+            knockback,
+            cooldown,
+        } => {
+            if ticks - game.last_attack >= *cooldown && game.inventory.count_item(*ammo) > 0 {
+                game.last_attack = ticks;
+                let projectile = Projectile {
+                    damage: *damage,
+                    knockback: *knockback,
+                    thrower: game.controlled_en,
+                };
+                let ammo_stack = ItemStack {
+                    id: *ammo,
+                    qty: 1,
+                    wear: 0,
+                };
+                let drop = ItemdropBundle::thrown(ammo_stack, player_pos, mouse_wpos, *speed);
+                game.inventory.remove(*ammo, 1);
+                let en = game.ecw.spawn(drop);
+                game.ecw
+                    .insert(
+                        en,
+                        (
+                            PickupCooldown {
+                                tick_dropped: ticks,
+                                cooldown: 100,
+                            },
+                            projectile,
+                        ),
+                    )
+                    .unwrap();
+                wear_selected_item(game);
+            }
+            return;
+        }
+        _ => false,
+    };
+    if untargeted {
+        if active_slot.qty == 0 {
+            active_slot.id = ItemId::EMPTY;
+            active_slot.wear = 0;
+        }
+        return;
+    }
+    // The remaining use actions all need a target tile position
     let Some(tpos_needed_always_fixme) = target_tpos else {
         return;
     };
-    do_use_action(
+    let used = do_use_action(
         action,
         &mut game.world,
         ticks,
@@ -162,13 +341,31 @@ pub(super) fn item_use_system(
         &mut game.transient_tile_states,
         &game.tile_db,
         mov,
+        &stats,
     );
+    // Removing a container tile shouldn't make its contents vanish
+    if let UseAction::RemoveTile {
+        layer: LayerAccess::Mid,
+    } = action
+        && let Some(inv) = game.world.remove_container(tpos_needed_always_fixme)
+    {
+        spill_container(&mut game.ecw, inv, &tpos_needed_always_fixme);
+    }
+    if used && itemdef.durability != 0 {
+        active_slot.wear += 1;
+        // The item broke
+        if active_slot.wear >= itemdef.durability {
+            active_slot.qty = 0;
+        }
+    }
     // Make sure that fully consumed stacks are cleared
     if active_slot.qty == 0 {
         active_slot.id = ItemId::EMPTY;
+        active_slot.wear = 0;
     }
 }
 
+/// Returns whether the item was used in a way that wears it down
 #[expect(clippy::too_many_arguments)]
 fn do_use_action(
     action: &UseAction,
@@ -184,7 +381,8 @@ fn do_use_action(
     transient_block_states: &mut TransientTileStates,
     tile_db: &TileDb,
     player_mov: &MovingEnt,
-) {
+    stats: &StatMods,
+) -> bool {
     match action {
         UseAction::PlaceBgTile { id } => {
             let mut can_place_this_here = true;
@@ -197,7 +395,7 @@ fn do_use_action(
             // Don't allow placing bg tiles in thin air. They need to be connected to some other bg tile.
             #[expect(clippy::collapsible_else_if, reason = "It's easier to read this way")]
             if is_bg_wall_here {
-                return;
+                return false;
             } else {
                 if ![above, below, left, right]
                     .into_iter()
@@ -211,9 +409,11 @@ fn do_use_action(
                     au_ctx.plr.play(au_res, snd);
                 }
                 t.bg = *id;
+                world.mark_changed(target_tpos);
                 active_slot.qty -= 1;
                 *last_tile_place = ticks;
             }
+            false
         }
         UseAction::PlaceMidTile { id } => {
             let mut can_place_this_here = true;
@@ -242,15 +442,17 @@ fn do_use_action(
             if can_place_this_here && ticks - *last_tile_place > tile_place_cooldown {
                 let tdef = &tile_db[*id];
                 if tdef.is_impassable() && player_mov.overlaps_tp(target_tpos) {
-                    return;
+                    return false;
                 }
                 if let Some(snd) = &tdef.hit_sound {
                     au_ctx.plr.play(au_res, snd);
                 }
                 t.mid = *id;
+                world.mark_changed(target_tpos);
                 active_slot.qty -= 1;
                 *last_tile_place = ticks;
             }
+            false
         }
         UseAction::RemoveTile { layer } => {
             let t = world.tile_at_mut(target_tpos);
@@ -258,23 +460,27 @@ fn do_use_action(
                 LayerAccess::Bg => t.bg = TileId::EMPTY,
                 LayerAccess::Mid => t.mid = TileId::EMPTY,
             }
+            world.mark_changed(target_tpos);
+            false
         }
-        UseAction::MineTile { power, delay } => {
+        UseAction::MineTile { power, delay, tier } => {
+            let (power, delay) = stats.mining(*power, *delay);
             let t = world.tile_at_mut(target_tpos);
             mine_tile(
                 &t.mid,
                 ticks,
-                delay,
+                &delay,
                 target_tpos,
-                power,
+                &power,
+                *tier,
                 au_ctx,
                 au_res,
                 last_mine_attempt,
                 transient_block_states,
                 tile_db,
-            );
+            )
         }
-        UseAction::MineBgTile { power, delay } => {
+        UseAction::MineBgTile { power, delay, tier } => {
             // Only allow digging walls if they have an empty neighbour.
             //
             // Being able to just dig any wall gives a "light source cheat" at the dirt level,
@@ -295,25 +501,35 @@ fn do_use_action(
             // Also only allow digging the backwall if the mid tile is empty.
             let empty_mid = t.mid.empty();
             if !has_empty_neighbour || !empty_mid {
-                return;
+                return false;
             }
+            let (power, delay) = stats.mining(*power, *delay);
             mine_tile(
                 &t.bg,
                 ticks,
-                delay,
+                &delay,
                 target_tpos,
-                power,
+                &power,
+                *tier,
                 au_ctx,
                 au_res,
                 last_mine_attempt,
                 transient_block_states,
                 tile_db,
-            );
+            )
         }
-        UseAction::Nothing => {}
+        // Handled by `item_use_system`, as they don't need a target tile
+        UseAction::Consume { .. }
+        | UseAction::Throw { .. }
+        | UseAction::SpawnEntity { .. }
+        | UseAction::Script { .. }
+        | UseAction::Melee { .. }
+        | UseAction::Shoot { .. }
+        | UseAction::Nothing => false,
     }
 }
 
+/// Returns whether the tile was hit
 #[expect(clippy::too_many_arguments)]
 fn mine_tile<L: TileLayer>(
     tid: &TileId<L>,
@@ -321,18 +537,23 @@ fn mine_tile<L: TileLayer>(
     delay: &u64,
     mouse_tpos: TilePos,
     power: &f32,
+    tier: u8,
     au_ctx: &mut AudioCtx,
     au_res: &ResAudio,
     last_mine_attempt: &mut u64,
     transient_block_states: &mut TransientTileStates,
     tile_db: &TileDb,
-) where
+) -> bool
+where
     TileDb: Index<TileId<L>, Output = TileDef<L>>,
 {
     if *tid == TileId::EMPTY || ticks - *last_mine_attempt < *delay {
-        return;
+        return false;
     }
     let tdef = &tile_db[*tid];
+    if tdef.unbreakable || tdef.min_tier > tier {
+        return false;
+    }
     let state = transient_block_states
         .entry(super::TilestateKey {
             pos: mouse_tpos,
@@ -354,6 +575,7 @@ fn mine_tile<L: TileLayer>(
         au_ctx.plr.play(au_res, hit_snd);
     }
     *last_mine_attempt = ticks;
+    true
 }
 
 pub(super) fn move_system(game: &mut GameState, rt_size: ScreenVec, debug: &DebugState) {
@@ -361,6 +583,8 @@ pub(super) fn move_system(game: &mut GameState, rt_size: ScreenVec, debug: &Debu
         .ecw
         .query_mut::<(&mut MovingEnt, Option<&mut MoveExtra>)>()
     {
+        // Before anything looks up the tiles around it
+        confine_to_world(mov);
         DBG_OVR.push(DbgOvr::WldRect {
             r: WorldRect {
                 topleft: WorldPos::from_en(&mov.mob.en),
@@ -441,6 +665,61 @@ pub(super) fn move_system(game: &mut GameState, rt_size: ScreenVec, debug: &Debu
     }
 }
 
+/// Keep `mov` inside of the world, in case it got past the bedrock, e.g. by teleporting.
+///
+/// Positions outside of the world don't map to valid chunks.
+fn confine_to_world(mov: &mut MovingEnt) {
+    let extent = i32::try_from(WORLD_EXTENT_PX).unwrap();
+    let en = &mut mov.mob.en;
+    let (max_x, max_y) = (extent - en.bb.x, extent - en.bb.y);
+    if !(0..=max_x).contains(&en.pos.x) {
+        en.pos.x = en.pos.x.clamp(0, max_x);
+        mov.hspeed = 0.;
+    }
+    if !(0..=max_y).contains(&en.pos.y) {
+        en.pos.y = en.pos.y.clamp(0, max_y);
+        mov.vspeed = 0.;
+    }
+}
+
+/// Wear down the selected item after a use that doesn't go through [`do_use_action`]
+fn wear_selected_item(game: &mut GameState) {
+    let slot = &mut game.inventory.slots[game.ui.selected_inv_slot];
+    let Some(def) = game.itemdb.get(slot.id) else {
+        return;
+    };
+    if def.durability == 0 {
+        return;
+    }
+    slot.wear += 1;
+    // The item broke
+    if slot.wear >= def.durability {
+        *slot = ItemStack::empty();
+    }
+}
+
+/// Hurt entities that get hit by projectiles
+pub(super) fn projectile_system(game: &mut GameState) {
+    let mut hits = Vec::new();
+    for (proj_en, (proj, proj_mov)) in game.ecw.query::<(&Projectile, &MovingEnt)>().iter() {
+        for (en, (mov, _health)) in game.ecw.query::<(&MovingEnt, &Health)>().iter() {
+            if en != proj.thrower && mov.mob.en.collides(&proj_mov.mob.en) {
+                let knockback = Knockback {
+                    from: proj_mov.world_pos(),
+                    strength: proj.knockback,
+                };
+                hits.push((proj_en, en, proj.damage, knockback));
+                break;
+            }
+        }
+    }
+    for (proj_en, en, damage, knockback) in hits {
+        combat::hurt(game, en, damage, Some(knockback));
+        // After hitting something, it's just an ordinary item drop
+        let _ = game.ecw.remove_one::<Projectile>(proj_en);
+    }
+}
+
 /// Calculate tile check pixel rectangle, for which tiles to check for collision
 fn calc_mov_wrect(mov: &MovingEnt) -> WorldRect {
     // Movement speeds are low enough that we don't have to fear truncation
@@ -520,48 +799,91 @@ fn calc_tile_ents(world: &mut World, tile_db: &TileDb, wrect: WorldRect) -> Vec<
 pub(super) fn biome_watch_system(game: &mut GameState, au_ctx: &AudioCtx, res: &Res) {
     let depth = world_y_depth(game.camera_offset.y);
     let depth_tiles = depth / i32::from(TILE_SIZE);
-    if depth_tiles > 70 {
-        game.current_biome = Biome::Underground;
-    } else {
-        game.current_biome = Biome::Surface;
-    }
-    if game.current_biome != game.prev_biome {
-        game.prev_biome = game.current_biome;
-        match game.current_biome {
-            Biome::Surface => {
-                au_ctx.play_music(&res.surf_music);
-            }
-            Biome::Underground => {
-                au_ctx.play_music(&res.und_music);
+    let worldgen = &game.world.worldgen;
+    // Underground variants only reach so deep below their surface biome
+    let surface = game
+        .world
+        .biome_at(game.camera_offset.tile_pos())
+        .filter(|&idx| {
+            worldgen
+                .biomes
+                .get(idx)
+                .is_some_and(|def| depth as f32 * M_PER_PX < def.depth)
+        });
+    game.current_biome = Biome {
+        surface,
+        underground: depth_tiles > 70,
+    };
+    if game.prev_biome != Some(game.current_biome) {
+        let music = game.current_biome.music(worldgen);
+        // Neighboring biomes can share music, which shouldn't restart
+        if game
+            .prev_biome
+            .is_none_or(|prev| prev.music(worldgen) != music)
+        {
+            match res.music.get(music) {
+                Some(data) => au_ctx.play_music(data),
+                None => log::warn!("Biome music '{music}' doesn't exist"),
             }
         }
+        game.prev_biome = Some(game.current_biome);
     }
 }
 
 /// Control the movements of the controlled entity (usually player character)
 pub(super) fn move_control_system(game: &mut GameState, input: &Input) {
-    let Ok((mov, mov_extra)) = game
+    let on_climbable = game
         .ecw
-        .query_one_mut::<(&mut MovingEnt, &mut MoveExtra)>(game.controlled_en)
-    else {
+        .get::<&MovingEnt>(game.controlled_en)
+        .is_ok_and(|mov| {
+            let rect = WorldRect::from_s2dc_en(&mov.mob.en);
+            game.ecw
+                .query::<&PlacedEnt>()
+                .iter()
+                .any(|(_en, placed)| placed.climbable && placed.rect().overlaps(&rect))
+        });
+    let Ok((mov, mov_extra, survival, effects)) = game.ecw.query_one_mut::<(
+        &mut MovingEnt,
+        &mut MoveExtra,
+        Option<&Survival>,
+        Option<&StatusEffects>,
+    )>(game.controlled_en) else {
         log::warn!("No controlled entity");
         return;
     };
-    let spd = if input.down_raw(Key::LShift) {
+    // Sprinting is only possible with stamina left
+    let can_sprint = survival.is_none_or(|surv| !surv.get(VitalKind::Stamina).is_depleted());
+    let sprint = input.down(InputAction::Sprint) && can_sprint;
+    let mut spd = if sprint {
         8.0
-    } else if input.down_raw(Key::LControl) {
+    } else if input.down(InputAction::FastMove) {
         128.0
     } else {
         3.0
     };
-    mov.hspeed = 0.;
-    if input.down(InputAction::Left) {
-        mov.hspeed = -spd;
-        mov_extra.facing_dir = FacingDir::Left;
+    if let Some(effects) = effects {
+        spd *= effects.speed_mul();
     }
-    if input.down(InputAction::Right) {
-        mov.hspeed = spd;
-        mov_extra.facing_dir = FacingDir::Right;
+    // Staggered entities get pushed around by the knockback instead
+    if mov_extra.stagger == 0 {
+        let dir = input.move_x();
+        mov.hspeed = spd * dir;
+        if dir < 0.0 {
+            mov_extra.facing_dir = FacingDir::Left;
+        } else if dir > 0.0 {
+            mov_extra.facing_dir = FacingDir::Right;
+        }
+    }
+    mov_extra.sprinting = sprint && mov.hspeed != 0.;
+    if on_climbable {
+        // Hold on to ropes and ladders instead of falling down
+        mov.vspeed = if input.down(InputAction::Up) {
+            -3.0
+        } else if input.down(InputAction::Down) {
+            3.0
+        } else {
+            0.0
+        };
     }
     if input.down(InputAction::Jump) && mov_extra.can_jump() {
         mov.vspeed = -10.0;
@@ -571,7 +893,7 @@ pub(super) fn move_control_system(game: &mut GameState, input: &Input) {
     if input.pressed(InputAction::ThrowItem) {
         if let Some(stack) = game.inventory.take_from_slot(game.ui.selected_inv_slot, 1) {
             let pos = mov.world_pos();
-            let en = game.ecw.spawn(ItemdropBundle::new_at(stack.id, pos));
+            let en = game.ecw.spawn(ItemdropBundle::new_at(stack, pos));
             game.ecw
                 .insert_one(
                     en,
@@ -586,9 +908,9 @@ pub(super) fn move_control_system(game: &mut GameState, input: &Input) {
 }
 
 pub(super) fn freecam_move_system(game: &mut GameState, input: &Input) {
-    let spd = if input.down_raw(Key::LShift) {
+    let spd = if input.down(InputAction::Sprint) {
         100
-    } else if input.down_raw(Key::LControl) {
+    } else if input.down(InputAction::FastMove) {
         1000
     } else {
         2
@@ -608,35 +930,17 @@ pub(super) fn freecam_move_system(game: &mut GameState, input: &Input) {
 }
 
 pub(super) fn inventory_input_system(game: &mut GameState, input: &Input) {
-    if input.pressed_raw(Key::Num1) {
-        game.ui.select_inv_slot(0);
-    }
-    if input.pressed_raw(Key::Num2) {
-        game.ui.select_inv_slot(1);
-    }
-    if input.pressed_raw(Key::Num3) {
-        game.ui.select_inv_slot(2);
-    }
-    if input.pressed_raw(Key::Num4) {
-        game.ui.select_inv_slot(3);
-    }
-    if input.pressed_raw(Key::Num5) {
-        game.ui.select_inv_slot(4);
-    }
-    if input.pressed_raw(Key::Num6) {
-        game.ui.select_inv_slot(5);
-    }
-    if input.pressed_raw(Key::Num7) {
-        game.ui.select_inv_slot(6);
-    }
-    if input.pressed_raw(Key::Num8) {
-        game.ui.select_inv_slot(7);
-    }
-    if input.pressed_raw(Key::Num9) {
-        game.ui.select_inv_slot(8);
+    for (n, slot) in (0..).zip(0..HOTBAR_SLOTS) {
+        if input.pressed(InputAction::Hotbar(n)) {
+            game.ui.select_inv_slot(slot);
+        }
     }
-    if input.pressed_raw(Key::Num0) {
-        game.ui.select_inv_slot(9);
+    let cur = game.ui.persistent_selected_inv_slot;
+    if input.pressed(InputAction::HotbarNext) {
+        game.ui.select_inv_slot((cur + 1) % HOTBAR_SLOTS);
+    } else if input.pressed(InputAction::HotbarPrev) {
+        game.ui
+            .select_inv_slot((cur + HOTBAR_SLOTS - 1) % HOTBAR_SLOTS);
     }
     if input.down(InputAction::SelectTorch) {
         if let Some(slot) = game.inventory.find_item_idx(data::item::TORCH) {
@@ -661,10 +965,18 @@ pub(super) fn transient_blocks_system(game: &mut GameState) {
                     tile.bg = TileId::EMPTY;
                 }
                 LayerAccess::Mid => {
-                    process_tile_item_drop(&game.tile_db, &mut game.ecw, tile.mid, &key.pos);
+                    let mid = tile.mid;
+                    process_tile_item_drop(&game.tile_db, &mut game.ecw, mid, &key.pos);
                     tile.mid = TileId::EMPTY;
+                    if let Some(inv) = game.world.remove_container(key.pos) {
+                        spill_container(&mut game.ecw, inv, &key.pos);
+                    }
+                    if !mid.empty() && game.tile_db[mid].layer.tree {
+                        fell_tree(&mut game.world, &game.tile_db, &mut game.ecw, key.pos);
+                    }
                 }
             }
+            game.world.mark_changed(key.pos);
             retain = false;
         }
         retain
@@ -689,9 +1001,9 @@ pub(super) fn item_drop_claim_system(
         radius: game.item_pickup_radius,
         c: Color::YELLOW,
     });
-    for (en, (id, mov, cd)) in game
+    for (en, (stack, mov, cd)) in game
         .ecw
-        .query::<(&ItemId, &mut MovingEnt, Option<&mut PickupCooldown>)>()
+        .query::<(&mut ItemStack, &mut MovingEnt, Option<&mut PickupCooldown>)>()
         .iter()
     {
         // Horizontal friction
@@ -699,20 +1011,25 @@ pub(super) fn item_drop_claim_system(
         if let Some(cd) = cd
             && game.world.ticks < cd.tick_dropped + cd.cooldown
         {
-            return;
+            continue;
         }
         // Player interaction
         // "Magnetism" behavior when player is close to an item drop
-        if game.inventory.item_can_be_added(*id, 1)
+        if game.inventory.item_can_be_added(stack.id, 1, &game.itemdb)
             && mov.within_radius_of_other(plr_mov, game.item_pickup_radius)
         {
             mov.move_towards_other(plr_mov, 4.0);
         }
-        #[expect(clippy::collapsible_if)]
         if plr_mov.mob.en.collides(&mov.mob.en) {
-            if game.inventory.add(*id, 1, &game.itemdb) {
+            // Take as much of the stack as fits, the rest stays on the ground
+            let left = game.inventory.add_stack(stack.clone(), &game.itemdb);
+            if left < stack.qty {
                 au_ctx.plr.play(au_res, "etc/pickup");
+            }
+            if left == 0 {
                 game.ecb.despawn(en);
+            } else {
+                stack.qty = left;
             }
         }
     }
@@ -738,7 +1055,11 @@ fn process_tile_item_drop<L: mdv_data::tile::TileLayer>(
     let amount = thread_rng().gen_range(drop.qty_range.clone());
     for _ in 0..amount {
         wld.spawn(ItemdropBundle::new_at(
-            drop.id,
+            ItemStack {
+                id: drop.id,
+                qty: 1,
+                wear: 0,
+            },
             WorldPos {
                 x: pos.x * u32::from(TILE_SIZE) + u32::from(TILE_SIZE) / 2,
                 y: pos.y * u32::from(TILE_SIZE) + u32::from(TILE_SIZE) / 2,
@@ -747,6 +1068,57 @@ fn process_tile_item_drop<L: mdv_data::tile::TileLayer>(
     }
 }
 
+/// Fell the rest of the tree above the chopped tile at `chopped`.
+///
+/// Trees are chopped from the top down: connected tree tiles at or above the chopped one
+/// come down and drop their items, the ones below it stay.
+fn fell_tree(world: &mut World, tile_db: &TileDb, wld: &mut hecs::World, chopped: TilePos) {
+    /// How far to the sides of the chopped tile a tree can reach
+    const REACH: u32 = 4;
+    let mut stack = vec![chopped];
+    while let Some(pos) = stack.pop() {
+        let neighbors = [
+            pos.x.checked_sub(1).map(|x| TilePos { x, y: pos.y }),
+            Some(TilePos {
+                x: pos.x + 1,
+                y: pos.y,
+            }),
+            pos.y.checked_sub(1).map(|y| TilePos { x: pos.x, y }),
+            Some(TilePos {
+                x: pos.x,
+                y: pos.y + 1,
+            }),
+        ];
+        for next in neighbors.into_iter().flatten() {
+            if next.y > chopped.y || next.x.abs_diff(chopped.x) > REACH {
+                continue;
+            }
+            let tile = world.tile_at_mut(next);
+            if tile.mid.empty() || !tile_db[tile.mid].layer.tree {
+                continue;
+            }
+            process_tile_item_drop(tile_db, wld, tile.mid, &next);
+            tile.mid = TileId::EMPTY;
+            world.mark_changed(next);
+            stack.push(next);
+        }
+    }
+}
+
+/// Drop the contents of a destroyed container into the world
+pub(super) fn spill_container(wld: &mut hecs::World, inv: Inventory, pos: &TilePos) {
+    let center = WorldPos {
+        x: pos.x * u32::from(TILE_SIZE) + u32::from(TILE_SIZE) / 2,
+        y: pos.y * u32::from(TILE_SIZE) + u32::from(TILE_SIZE) / 2,
+    };
+    for stack in inv.slots.into_iter().chain(inv.grabbed) {
+        if stack.id == ItemId::EMPTY {
+            continue;
+        }
+        wld.spawn(ItemdropBundle::new_at(stack, center));
+    }
+}
+
 pub enum UiHudInputSystemMsg {
     None,
     CursorOccupied,
@@ -764,26 +1136,45 @@ pub(crate) fn ui_hud_input_system(
     game: &mut GameState,
     input: &Input,
     scale: u8,
-    screen_res: ScreenRes,
 ) -> UiHudInputSystemMsg {
     let mut msg = UiHudInputSystemMsg::None;
-    if input.pressed_raw(Key::Escape) && !game.ui.menu.open {
-        open_menu(game);
+    if input.pressed(InputAction::Menu) && !game.ui.menu.open {
+        if game.ui.map.open {
+            game.ui.map.open = false;
+        } else {
+            open_menu(game);
+        }
+    }
+    if input.pressed(InputAction::Map) && !game.ui.menu.open {
+        game.ui.map.open ^= true;
+        let center = game
+            .map
+            .explored_from
+            .unwrap_or(game.camera_offset.tile_pos());
+        game.ui.map.center = tile_to_map(center);
+        game.ui.map.new_waypoint = None;
     }
-    if input.pressed_raw(Key::P) {
+    // The map covers the whole screen, and takes the input for itself
+    if game.ui.map.open {
+        return msg;
+    }
+    if input.pressed(InputAction::Pause) {
         game.paused ^= true;
         game.pause_next_frame = false;
     }
-    if input.pressed_raw(Key::Period) {
+    if input.pressed(InputAction::FrameAdvance) {
         game.paused = false;
         game.pause_next_frame = true;
     }
-    if input.pressed_raw(Key::R) {
+    if input.pressed(InputAction::ReverseGravity) {
         // Reverse gravity
         game.gravity = -game.gravity;
     }
     if input.pressed(InputAction::Inventory) {
         game.ui.inv.open ^= true;
+        if !game.ui.inv.open {
+            close_inventory(game);
+        }
     }
     if input.pressed(InputAction::Crafting) {
         game.ui.craft.open ^= true;
@@ -793,8 +1184,12 @@ pub(crate) fn ui_hud_input_system(
     }
     let mp = input.mouse_down_loc.scaled(scale);
     if game.ui.inv.open
-        && crate::game::ui::Inventory::screen_rect(screen_res, game.inventory.slots.len())
-            .contains_screen_pos(mp)
+        && (game.ui.inv_frame.contains_screen_pos(mp)
+            || game.ui.equip_frame.contains_screen_pos(mp)
+            || game
+                .ui
+                .container_frame
+                .is_some_and(|rect| rect.contains_screen_pos(mp)))
     {
         msg = UiHudInputSystemMsg::CursorOccupied;
     }
@@ -804,19 +1199,54 @@ pub(crate) fn ui_hud_input_system(
                 game.ui.selected_inv_slot = i;
             }
         }
-        if game.ui.inv.open {
-            for (i, rect) in game.ui.inv_rects.iter().enumerate() {
+    }
+    if game.ui.inv.open && (input.lmb_pressed || input.rmb_pressed) {
+        let shift = input.mods().shift;
+        let mut container = game
+            .ui
+            .container
+            .and_then(|pos| game.world.container_at_mut(pos, &game.tile_db));
+        for (i, rect) in game.ui.inv_rects.iter().enumerate() {
+            if !rect.contains_screen_pos(mp) {
+                continue;
+            }
+            if input.rmb_pressed {
+                game.inventory.right_click_slot(i, &game.itemdb);
+            } else if shift {
+                // Shift-click moves between the inventory and the open container, if any
+                match container.as_deref_mut() {
+                    Some(container) => game.inventory.transfer_slot(i, container, &game.itemdb),
+                    None => game.inventory.quick_move(i, &game.itemdb),
+                }
+            } else {
+                game.inventory.click_slot(i, &game.itemdb);
+            }
+        }
+        if input.lmb_pressed
+            && let Ok(equipment) = game.ecw.query_one_mut::<&mut Equipment>(game.player_en)
+        {
+            for (slot, rect) in EquipSlot::ALL.into_iter().zip(&game.ui.equip_rects) {
                 if rect.contains_screen_pos(mp) {
-                    match &mut game.inventory.grabbed {
-                        Some(grabbed) => {
-                            std::mem::swap(grabbed, &mut game.inventory.slots[i]);
-                        }
-                        None => {
-                            let item =
-                                std::mem::replace(&mut game.inventory.slots[i], ItemStack::empty());
-                            game.inventory.grabbed = Some(item);
-                        }
-                    }
+                    equipment.click_slot(slot, &mut game.inventory.grabbed, &game.itemdb);
+                }
+            }
+        }
+        if let Some(container) = container {
+            for (i, rect) in game.ui.container_rects.iter().enumerate() {
+                if !rect.contains_screen_pos(mp) {
+                    continue;
+                }
+                if shift && input.lmb_pressed {
+                    container.transfer_slot(i, &mut game.inventory, &game.itemdb);
+                    continue;
+                }
+                let Some(slot) = container.slots.get_mut(i) else {
+                    continue;
+                };
+                if input.rmb_pressed {
+                    inventory::right_click_stack(&mut game.inventory.grabbed, slot, &game.itemdb);
+                } else {
+                    inventory::click_stack(&mut game.inventory.grabbed, slot, &game.itemdb);
                 }
             }
         }
@@ -824,17 +1254,128 @@ pub(crate) fn ui_hud_input_system(
     msg
 }
 
+/// Close the inventory, along with any open container
+fn close_inventory(game: &mut GameState) {
+    game.ui.container = None;
+    // Put the grabbed stack back, dropping whatever doesn't fit
+    if let Some(stack) = game.inventory.grabbed.take() {
+        let (id, wear) = (stack.id, stack.wear);
+        let qty = game.inventory.add_stack(stack, &game.itemdb);
+        game.drop_stack(ItemStack { id, qty, wear });
+    }
+}
+
+/// Close the open container if the player went out of reach, or it no longer exists
+pub(super) fn container_watch_system(game: &mut GameState) {
+    let Some(pos) = game.ui.container else {
+        return;
+    };
+    let in_reach = game
+        .ecw
+        .query_one_mut::<&MovingEnt>(game.controlled_en)
+        .is_ok_and(|mov| {
+            // Some slack, as the container was in reach anywhere inside of its tile
+            let radius = game.tile_interact_radius + u16::from(TILE_SIZE);
+            pos.to_world()
+                .within_circle(WorldPos::from_en(&mov.mob.en), radius)
+        });
+    if !in_reach || game.world.container_at(pos).is_none() {
+        game.ui.container = None;
+    }
+}
+
+/// Drain the survival stats, and hurt entities that ran out of them
+pub(super) fn survival_system(game: &mut GameState) {
+    for (en, (mov, mov_extra, survival, health)) in
+        game.ecw
+            .query_mut::<(&MovingEnt, Option<&MoveExtra>, &mut Survival, &mut Health)>()
+    {
+        let cond = Conditions {
+            depth_m: mov.depth_disp().0 * M_PER_PX,
+            sprinting: mov_extra.is_some_and(|extra| extra.sprinting),
+        };
+        let damage = survival.tick(&cond);
+        if damage > 0.0 {
+            // Armor doesn't help against starving or suffocating
+            let amount = health.damage(damage, &StatMods::default());
+            game.event_buf.push(Event::Damaged {
+                en,
+                amount,
+                gradual: true,
+            });
+        }
+    }
+}
+
 pub(crate) fn health_system(game: &mut GameState) {
-    for (en, health) in game.ecw.query_mut::<&mut Health>() {
+    let mut death_site = None;
+    for (en, (health, mov, equipment, effects)) in game.ecw.query_mut::<(
+        &mut Health,
+        Option<&MovingEnt>,
+        Option<&mut Equipment>,
+        Option<&mut StatusEffects>,
+    )>() {
+        if let Some(effects) = effects {
+            let change = effects.tick();
+            if change > 0.0 {
+                health.heal(change);
+            } else if change < 0.0 {
+                // Poison and fire get through armor
+                let amount = health.damage(-change, &StatMods::default());
+                game.event_buf.push(Event::Damaged {
+                    en,
+                    amount,
+                    gradual: true,
+                });
+            }
+        }
         if health.current < 1. {
             if en == game.player_en {
                 game.player_en = hecs::Entity::DANGLING;
                 game.respawn_timer = 500;
+                // The respawned player gets to keep their worn items
+                if let Some(equipment) = equipment {
+                    game.respawn_equipment = std::mem::take(equipment);
+                }
+                death_site = mov.map(|mov| WorldPos {
+                    x: mov.center().x.saturating_sub(Tombstone::SIZE / 2),
+                    y: WPosSc::try_from(mov.feet_y())
+                        .unwrap_or(0)
+                        .saturating_sub(Tombstone::SIZE),
+                });
             }
             game.ecb.despawn(en);
         }
     }
     game.ecb.run_on(&mut game.ecw);
+    if let Some(pos) = death_site {
+        apply_death_rules(game, pos);
+    }
+}
+
+/// Apply the death rules of the world after the player died at `pos`
+fn apply_death_rules(game: &mut GameState, pos: WorldPos) {
+    if game.rules.hardcore {
+        log::info!("The player died in hardcore mode. This world is finished.");
+        game.finished = true;
+    }
+    let itemdb = &game.itemdb;
+    let items = match game.rules.death {
+        DeathRule::KeepInventory => return,
+        DeathRule::DropAll => game.inventory.take_where(|_| true),
+        DeathRule::DropCoins => game
+            .inventory
+            .take_where(|id| itemdb.get(id).is_some_and(|def| def.currency)),
+    };
+    if items.is_empty() {
+        return;
+    }
+    game.ecw.spawn((Tombstone {
+        x: pos.x,
+        y: pos.y,
+        items,
+    },));
+    game.death_tombstone = Some(pos);
 }
 
 pub(crate) fn craft_ui_system(
@@ -905,8 +1446,8 @@ pub(crate) fn craft_ui_system(
                         for inp_stack in &recipe.input {
                             game.inventory.remove(inp_stack.id, inp_stack.qty);
                         }
-                        game.inventory
-                            .add(recipe.output.id, recipe.output.qty, &game.itemdb);
+                        let (out_id, out_qty) = (recipe.output.id, recipe.output.qty);
+                        game.give_item(out_id, out_qty);
                     }
                 }
             });
@@ -927,12 +1468,66 @@ pub(crate) fn interact_system(
     let player_pos = WorldPos::from_en(&mov.mob.en);
     let ptr_within_circle = mouse_wpos.within_circle(player_pos, game.tile_interact_radius);
     if input.pressed(InputAction::Interact) && ptr_within_circle {
+        if game
+            .world
+            .container_at_mut(mouse_tpos, &game.tile_db)
+            .is_some()
+        {
+            game.ui.container = Some(mouse_tpos);
+            game.ui.inv.open = true;
+            return;
+        }
+        let mid = game.world.tile_at_mut(mouse_tpos).mid;
+        if !mid.empty() && game.tile_db[mid].layer.spawn_anchor {
+            log::info!("Spawn point set to {mouse_tpos:?}");
+            game.spawn_anchor = Some(mouse_tpos);
+            return;
+        }
+        // Pick placed objects back up
+        let mouse_rect = WorldRect {
+            topleft: mouse_wpos,
+            w: 1,
+            h: 1,
+        };
+        let placed = game
+            .ecw
+            .query_mut::<&PlacedEnt>()
+            .into_iter()
+            .find(|(_en, placed)| placed.rect().overlaps(&mouse_rect))
+            .map(|(en, placed)| (en, placed.item, placed.rect().topleft));
+        if let Some((en, item, pos)) = placed {
+            game.ecw.despawn(en).unwrap();
+            let stack = ItemStack {
+                id: item,
+                qty: 1,
+                wear: 0,
+            };
+            game.ecw.spawn(ItemdropBundle::new_at(stack, pos));
+            return;
+        }
+        // Reclaim the items left in a tombstone
+        let tomb = game
+            .ecw
+            .query_mut::<&mut Tombstone>()
+            .into_iter()
+            .find(|(_en, tomb)| tomb.rect().overlaps(&mouse_rect))
+            .map(|(en, tomb)| (en, std::mem::take(&mut tomb.items)));
+        if let Some((en, items)) = tomb {
+            game.ecw.despawn(en).unwrap();
+            for stack in items {
+                let (id, wear) = (stack.id, stack.wear);
+                let qty = game.inventory.add_stack(stack, &game.itemdb);
+                game.drop_stack(ItemStack { id, qty, wear });
+            }
+            return;
+        }
         let tile = game.world.tile_at_mut(mouse_tpos);
         if !tile.mid.empty() {
             // Uproot uprootable tiles
             if game.tile_db[tile.mid].uprootable {
                 process_tile_item_drop(&game.tile_db, &mut game.ecw, tile.mid, &mouse_tpos);
                 tile.mid = TileId::EMPTY;
+                game.world.mark_changed(mouse_tpos);
             }
         }
     }
@@ -0,0 +1,152 @@
+use {
+    crate::{
+        command::{Cmd, CmdVec, TpTarget},
+        game::{ui::MapWnd, GameState},
+        graphics::ScreenRes,
+        input::{Input, InputAction, RawInput},
+        map::{map_to_tile, tile_to_map, Waypoint},
+        math::WorldPos,
+        player::MovingEnt,
+        sfml::ScreenVecSfExt,
+    },
+    egui_sfml::egui,
+    mdv_math::types::ScreenVec,
+    sfml::system::Vector2f,
+};
+
+/// How far around the player tiles get explored
+const EXPLORE_RADIUS: u16 = 24;
+/// Screen pixels per frame the map pans with the movement keys
+const PAN_SPEED: f32 = 8.0;
+
+/// Explore the tiles around the player, and redraw the map images of the chunks where
+/// that revealed new tiles, or where tiles changed
+pub fn explore_system(game: &mut GameState) {
+    let Ok(mov) = game.ecw.query_one_mut::<&MovingEnt>(game.player_en) else {
+        return;
+    };
+    let center = WorldPos::from_en(&mov.mob.en).tile_pos();
+    let mut redraw = game.world.take_changed_chunks();
+    if game.map.explored_from != Some(center) {
+        game.map.explored_from = Some(center);
+        for pos in game.world.explore(center, EXPLORE_RADIUS) {
+            if !redraw.contains(&pos) {
+                redraw.push(pos);
+            }
+        }
+    }
+    for pos in redraw {
+        if let Some(chk) = game.world.loaded_chunk(pos) {
+            game.map.update_chunk(pos, chk);
+        }
+    }
+    // Explored chunks from earlier sessions, for the minimap
+    let (rx, ry) = center.to_chunk().region();
+    let regions = (ry.saturating_sub(1)..=ry.saturating_add(1))
+        .flat_map(|y| (rx.saturating_sub(1)..=rx.saturating_add(1)).map(move |x| (x, y)));
+    game.map.load_regions(&game.world, regions);
+}
+
+/// Pan and zoom the world map, and place, visit and delete waypoints
+pub fn map_screen_system(
+    game: &mut GameState,
+    input: &Input,
+    egui_ctx: &egui::Context,
+    rt_res: ScreenRes,
+    scale: u8,
+    cmd: &mut CmdVec,
+) {
+    let wnd = &mut game.ui.map;
+    let mp = input.mouse_down_loc.scaled(scale);
+    if input.lmb_down {
+        if let Some(from) = wnd.drag_from {
+            wnd.center -= (mp.to_sf_vec2f() - from.to_sf_vec2f()) / wnd.zoom;
+        }
+        wnd.drag_from = Some(mp);
+    } else {
+        wnd.drag_from = None;
+    }
+    let pan_y = if input.down(InputAction::Up) {
+        -1.0
+    } else if input.down(InputAction::Down) {
+        1.0
+    } else {
+        0.0
+    };
+    wnd.center += Vector2f::new(input.move_x(), pan_y) * PAN_SPEED / wnd.zoom;
+    for (raw, factor) in [(RawInput::WheelUp, 2.0), (RawInput::WheelDown, 0.5)] {
+        if input.pressed_input(raw) {
+            // Keep the map pixel under the cursor in place
+            let before = wnd.screen_to_map(mp, rt_res);
+            wnd.zoom = (wnd.zoom * factor).clamp(MapWnd::MIN_ZOOM, MapWnd::MAX_ZOOM);
+            wnd.center += before - wnd.screen_to_map(mp, rt_res);
+        }
+    }
+    if input.rmb_pressed && wnd.new_waypoint.is_none() {
+        let pos = map_to_tile(wnd.screen_to_map(mp, rt_res));
+        let name = format!("Waypoint {}", game.map.waypoints.len() + 1);
+        wnd.new_waypoint = Some((pos, name));
+    }
+    // Visible regions that may have explored chunks saved
+    let top_left = map_to_tile(wnd.screen_to_map(ScreenVec { x: 0, y: 0 }, rt_res));
+    let bottom_right = map_to_tile(wnd.screen_to_map(rt_res.to_vec(), rt_res));
+    let (x1, y1) = top_left.to_chunk().region();
+    let (x2, y2) = bottom_right.to_chunk().region();
+    game.map.load_regions(
+        &game.world,
+        (y1..=y2).flat_map(|y| (x1..=x2).map(move |x| (x, y))),
+    );
+    waypoint_windows(game, egui_ctx, cmd);
+}
+
+fn waypoint_windows(game: &mut GameState, egui_ctx: &egui::Context, cmd: &mut CmdVec) {
+    let wnd = &mut game.ui.map;
+    if let Some((pos, name)) = &mut wnd.new_waypoint {
+        let mut done = false;
+        egui::Window::new("New waypoint").show(egui_ctx, |ui| {
+            let re = ui.text_edit_singleline(name);
+            re.request_focus();
+            let valid = !name.trim().is_empty();
+            let enter = re.lost_focus() && ui.input(|inp| inp.key_pressed(egui::Key::Enter));
+            ui.horizontal(|ui| {
+                if ui.add_enabled(valid, egui::Button::new("Place")).clicked() || (valid && enter) {
+                    game.map.waypoints.push(Waypoint {
+                        name: name.trim().to_owned(),
+                        pos: *pos,
+                    });
+                    done = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    done = true;
+                }
+            });
+        });
+        if done {
+            wnd.new_waypoint = None;
+        }
+    }
+    let mut delete = None;
+    egui::Window::new("Waypoints").show(egui_ctx, |ui| {
+        if game.map.waypoints.is_empty() {
+            ui.label("Right click the map to place a waypoint");
+        }
+        for (i, wp) in game.map.waypoints.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(&wp.name);
+                if ui.button("Show").clicked() {
+                    wnd.center = tile_to_map(wp.pos);
+                }
+                if cfg!(debug_assertions) && ui.button("Teleport").clicked() {
+                    cmd.push(Cmd::Teleport(TpTarget::Waypoint(wp.name.clone())));
+                    wnd.open = false;
+                }
+                if ui.button("Delete").clicked() {
+                    delete = Some(i);
+                }
+            });
+        }
+    });
+    if let Some(i) = delete {
+        game.map.waypoints.remove(i);
+    }
+}
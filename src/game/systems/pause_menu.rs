@@ -2,13 +2,19 @@ use {
     crate::{
         audio::AudioCtx,
         command::{Cmd, CmdVec},
+        data_pack::DataPack,
         game::GameState,
-        input::{Input, InputAction},
-        save::world_dirs,
+        input::{
+            default_key_bindings, default_pad_bindings,
+            gamepad::{self, Dir},
+            Binding, Input, InputAction, Mods,
+        },
+        save::{world_dirs, DeathRule, WorldRules},
+        world::GenVersion,
     },
     rand::{thread_rng, Rng},
     sfml::{graphics::Color, window::Key},
-    std::path::Path,
+    std::{fmt::Write, path::Path},
 };
 
 pub type MenuStack = Vec<MenuList>;
@@ -20,6 +26,8 @@ pub struct MenuItem {
 
 enum MenuAction {
     NewRandom,
+    /// Create a random world with these rules
+    NewWorld(WorldRules),
     Load,
     LoadWorld(String),
     Settings,
@@ -27,9 +35,13 @@ enum MenuAction {
     Back,
     Input,
     Rebind(InputAction),
+    ResetBindings,
     MusicVolume,
     SfxVolume,
     DesktopFullscreen,
+    DataPacks,
+    /// Purely informational item, does nothing
+    Info,
 }
 
 pub fn pause_menu_system(
@@ -38,11 +50,30 @@ pub fn pause_menu_system(
     cmd: &mut CmdVec,
     worlds_dir: &Path,
     aud: &AudioCtx,
+    packs: &[DataPack],
 ) {
     if let Some(act) = game.ui.menu.action_to_rebind {
         game.ui.menu.sel_color = Color::RED;
-        if let Some(key) = input.just_pressed_raw {
-            input.key_bindings.insert(act, key);
+        // Modifier keys can be held for a combination with another input,
+        // so they only get bound on their own when released without one.
+        let binding = match (input.just_pressed_raw, input.just_released_raw) {
+            (Some(raw), _) if !raw.is_modifier() => Some(Binding {
+                input: raw,
+                mods: input.mods(),
+            }),
+            (None, Some(raw)) if raw.is_modifier() => Some(Binding {
+                input: raw,
+                mods: Mods::NONE,
+            }),
+            _ => None,
+        };
+        let pad_button = input.pad.just_pressed();
+        if let Some(button) = pad_button {
+            input.pad_bindings.insert(act, button);
+        } else if let Some(binding) = binding {
+            input.key_bindings.insert(act, binding);
+        }
+        if pad_button.is_some() || binding.is_some() {
             game.ui.menu.action_to_rebind = None;
             if let Some(items) = game.ui.menu.stack.last_mut() {
                 *items = build_keyconfig_menu(input);
@@ -51,16 +82,51 @@ pub fn pause_menu_system(
         return;
     }
     game.ui.menu.sel_color = Color::YELLOW;
-    let enter = input.pressed_raw(Key::Enter);
-    let left = input.pressed_raw(Key::Left);
-    let right = input.pressed_raw(Key::Right);
+    let pad_dir = input.pad.dir_pressed;
+    let enter = input.pressed_raw(Key::Enter) || input.pad.pressed(gamepad::BUTTON_CONFIRM);
+    let left = input.pressed_raw(Key::Left) || pad_dir == Some(Dir::Left);
+    let right = input.pressed_raw(Key::Right) || pad_dir == Some(Dir::Right);
+    let up = input.pressed_raw(Key::Up) || pad_dir == Some(Dir::Up);
+    let down = input.pressed_raw(Key::Down) || pad_dir == Some(Dir::Down);
+    let back = input.pressed_raw(Key::Escape) || input.pad.pressed(gamepad::BUTTON_BACK);
     if let Some(list) = game.ui.menu.stack.last_mut() {
         let current_menu_item = &mut list[game.ui.menu.cursor];
         match &mut current_menu_item.action {
             MenuAction::NewRandom => {
+                if enter {
+                    let mut list: MenuList = DeathRule::ALL
+                        .into_iter()
+                        .map(|death| MenuItem {
+                            text: death.name().into(),
+                            action: MenuAction::NewWorld(WorldRules {
+                                death,
+                                hardcore: false,
+                            }),
+                        })
+                        .collect();
+                    list.push(MenuItem {
+                        text: "Hardcore".into(),
+                        action: MenuAction::NewWorld(WorldRules {
+                            death: DeathRule::DropAll,
+                            hardcore: true,
+                        }),
+                    });
+                    list.push(MenuItem {
+                        text: "Back".into(),
+                        action: MenuAction::Back,
+                    });
+                    game.ui.menu.stack.push(list);
+                    game.ui.menu.cursor = 0;
+                }
+            }
+            MenuAction::NewWorld(rules) => {
                 if enter {
                     let n: u32 = thread_rng().gen();
-                    cmd.push(Cmd::LoadWorld(n.to_string()));
+                    cmd.push(Cmd::CreateWorld {
+                        name: n.to_string(),
+                        rules: *rules,
+                        gen_version: GenVersion::LATEST,
+                    });
                 }
             }
             MenuAction::Load => 'block: {
@@ -144,6 +210,13 @@ pub fn pause_menu_system(
                     game.ui.menu.action_to_rebind = Some(*act);
                 }
             }
+            MenuAction::ResetBindings => {
+                if enter {
+                    input.key_bindings = default_key_bindings();
+                    input.pad_bindings = default_pad_bindings();
+                    *list = build_keyconfig_menu(input);
+                }
+            }
             MenuAction::MusicVolume => {
                 if left {
                     cmd.push(Cmd::MusVolDec);
@@ -165,9 +238,27 @@ pub fn pause_menu_system(
                     cmd.push(Cmd::DesktopFullscreen);
                 }
             }
+            MenuAction::DataPacks => {
+                if enter {
+                    let mut items: Vec<MenuItem> = packs
+                        .iter()
+                        .map(|pack| MenuItem {
+                            text: format!("{} ({})", pack.name, pack.data_dir.display()),
+                            action: MenuAction::Info,
+                        })
+                        .collect();
+                    items.push(MenuItem {
+                        text: "Back".into(),
+                        action: MenuAction::Back,
+                    });
+                    game.ui.menu.stack.push(items);
+                    game.ui.menu.cursor = 0;
+                }
+            }
+            MenuAction::Info => {}
         }
     }
-    if input.pressed_raw(Key::Escape) && !game.ui.menu.first_frame {
+    if back && !game.ui.menu.first_frame {
         game.ui.menu.cursor = 0;
         game.ui.menu.stack.pop();
         dbg!(&game.ui.menu.stack.len());
@@ -176,14 +267,14 @@ pub fn pause_menu_system(
         }
     }
     #[expect(clippy::collapsible_if)]
-    if input.pressed_raw(Key::Up) {
+    if up {
         if game.ui.menu.cursor > 0 {
             game.ui.menu.cursor -= 1;
         }
     }
     if let Some(list) = game.ui.menu.stack.last() {
         #[expect(clippy::collapsible_if)]
-        if input.pressed_raw(Key::Down) {
+        if down {
             if game.ui.menu.cursor + 1 < list.len() {
                 game.ui.menu.cursor += 1;
             }
@@ -198,12 +289,23 @@ fn vol_text(pre: &str, mus_vol: f32) -> String {
 
 fn build_keyconfig_menu(input: &Input) -> Vec<MenuItem> {
     let mut items = Vec::new();
-    for (action, key) in &input.key_bindings {
+    for action in InputAction::ALL {
+        let mut text = match input.key_bindings.get(&action) {
+            Some(binding) => format!("{}: {binding}", action.name()),
+            None => format!("{}: <unbound>", action.name()),
+        };
+        if let Some(button) = input.pad_bindings.get(&action) {
+            write!(text, " / Pad {button}").unwrap();
+        }
         items.push(MenuItem {
-            text: format!("{}: {key:?}", action.name()),
-            action: MenuAction::Rebind(*action),
+            text,
+            action: MenuAction::Rebind(action),
         })
     }
+    items.push(MenuItem {
+        text: "Reset to defaults".into(),
+        action: MenuAction::ResetBindings,
+    });
     items.push(MenuItem {
         text: "Back".into(),
         action: MenuAction::Back,
@@ -225,6 +327,10 @@ pub fn open_menu(game: &mut GameState) {
             text: "Settings".into(),
             action: MenuAction::Settings,
         },
+        MenuItem {
+            text: "Data packs".into(),
+            action: MenuAction::DataPacks,
+        },
         MenuItem {
             text: "Quit".into(),
             action: MenuAction::Quit,
@@ -1,10 +1,13 @@
 use {
     super::systems::pause_menu::Menu,
-    crate::graphics::ScreenRes,
+    crate::{graphics::ScreenRes, sfml::ScreenVecSfExt, world::TilePos},
+    mdv_data::item::EquipSlot,
     mdv_math::{
-        types::{ScreenRect, ScreenSc},
+        types::{ScreenRect, ScreenSc, ScreenVec},
         util::center_offset,
     },
+    sfml::system::Vector2f,
+    smart_default::SmartDefault,
 };
 
 #[derive(Default)]
@@ -14,13 +17,27 @@ pub struct UiState {
     pub menu: Menu,
     pub inv: Inventory,
     pub craft: CraftWnd,
+    pub map: MapWnd,
     pub hotbar_rects: [ScreenRect; 10],
+    pub inv_frame: ScreenRect,
     pub inv_rects: Vec<ScreenRect>,
+    /// Equipment slots, to the left of the inventory
+    pub equip_frame: ScreenRect,
+    pub equip_rects: [ScreenRect; EquipSlot::ALL.len()],
+    /// Position of the container tile whose inventory is open next to the player's
+    pub container: Option<TilePos>,
+    pub container_frame: Option<ScreenRect>,
+    pub container_rects: Vec<ScreenRect>,
 }
 
 impl UiState {
     #[expect(clippy::cast_possible_wrap, reason = "Doesn't exceed i16::MAX")]
-    pub fn update_rects(&mut self, inv: &super::Inventory, rt_res: ScreenRes) {
+    pub fn update_rects(
+        &mut self,
+        inv: &super::Inventory,
+        container: Option<&super::Inventory>,
+        rt_res: ScreenRes,
+    ) {
         for i in 0..10u16 {
             let (x, y) = ((i * 44) + 8, (rt_res.h - 48));
             let rect = &mut self.hotbar_rects[i as usize];
@@ -29,22 +46,30 @@ impl UiState {
             rect.w = 36;
             rect.h = 36;
         }
-        let inv_rect = Inventory::screen_rect(rt_res, inv.slots.len());
-        let mut y_off = 24;
-        let mut x_off = 8;
-        self.inv_rects.clear();
-        for i in 0..inv.slots.len() {
-            self.inv_rects.push(ScreenRect {
-                x: inv_rect.x + x_off,
-                y: inv_rect.y + y_off,
+        let container_slots = container.map(|inv| inv.slots.len());
+        let (inv_frame, container_frame) =
+            Inventory::screen_rects(rt_res, inv.slots.len(), container_slots);
+        self.inv_frame = inv_frame;
+        slot_rects(&mut self.inv_rects, inv_frame, inv.slots.len());
+        self.equip_frame = ScreenRect {
+            x: inv_frame.x - CONTAINER_GAP - 52,
+            y: inv_frame.y,
+            w: 52,
+            h: 24,
+        };
+        for rect in &mut self.equip_rects {
+            *rect = ScreenRect {
+                x: self.equip_frame.x + 8,
+                y: self.equip_frame.y + self.equip_frame.h,
                 w: 36,
                 h: 36,
-            });
-            x_off += 42;
-            if (i + 1) % 10 == 0 {
-                x_off = 8;
-                y_off += 42;
-            }
+            };
+            self.equip_frame.h += 42;
+        }
+        self.container_frame = container_frame;
+        self.container_rects.clear();
+        if let (Some(frame), Some(n_slots)) = (container_frame, container_slots) {
+            slot_rects(&mut self.container_rects, frame, n_slots);
         }
     }
 
@@ -54,6 +79,26 @@ impl UiState {
     }
 }
 
+/// Lay out the rects of `n_slots` slots inside of the inventory frame `frame`
+fn slot_rects(rects: &mut Vec<ScreenRect>, frame: ScreenRect, n_slots: usize) {
+    let mut y_off = 24;
+    let mut x_off = 8;
+    rects.clear();
+    for i in 0..n_slots {
+        rects.push(ScreenRect {
+            x: frame.x + x_off,
+            y: frame.y + y_off,
+            w: 36,
+            h: 36,
+        });
+        x_off += 42;
+        if (i + 1) % usize::from(ITEMS_PER_ROW) == 0 {
+            x_off = 8;
+            y_off += 42;
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct Inventory {
     pub open: bool,
@@ -61,11 +106,30 @@ pub struct Inventory {
 
 const ITEMS_PER_ROW: u8 = 10;
 
+/// Horizontal space between the player inventory and an open container
+const CONTAINER_GAP: ScreenSc = 16;
+
 impl Inventory {
+    /// Screen rects of the player inventory, and of the open container next to it, if any
+    #[expect(clippy::cast_possible_wrap, reason = "resolution is always positive")]
+    pub fn screen_rects(
+        res: ScreenRes,
+        slots: usize,
+        container_slots: Option<usize>,
+    ) -> (ScreenRect, Option<ScreenRect>) {
+        let mut inv = Self::screen_rect(res, slots);
+        let Some(container_slots) = container_slots else {
+            return (inv, None);
+        };
+        let mut container = Self::screen_rect(res, container_slots);
+        inv.x = center_offset(inv.w + CONTAINER_GAP + container.w, res.w as i16);
+        container.x = inv.x + inv.w + CONTAINER_GAP;
+        (inv, Some(container))
+    }
     #[expect(clippy::cast_possible_wrap, reason = "resolution is always positive")]
-    pub fn screen_rect(res: ScreenRes, slots: usize) -> ScreenRect {
+    fn screen_rect(res: ScreenRes, slots: usize) -> ScreenRect {
         let items_per_row = ScreenSc::from(ITEMS_PER_ROW);
-        let rows = ScreenSc::try_from(slots).unwrap() / items_per_row;
+        let rows = ScreenSc::try_from(slots.div_ceil(usize::from(ITEMS_PER_ROW))).unwrap();
         let w = (items_per_row * 40) + 34;
         let h = (rows * 40) + 34;
         let x = center_offset(w, res.w as i16);
@@ -79,3 +143,35 @@ pub struct CraftWnd {
     pub open: bool,
     pub selected_recipe: Option<usize>,
 }
+
+/// The fullscreen world map
+#[derive(SmartDefault)]
+pub struct MapWnd {
+    pub open: bool,
+    /// Map pixel shown at the center of the screen
+    pub center: Vector2f,
+    /// Screen pixels per map pixel
+    #[default(1.0)]
+    pub zoom: f32,
+    /// Where the mouse was last frame while dragging the map around
+    pub drag_from: Option<ScreenVec>,
+    /// Position and name of the waypoint being placed
+    pub new_waypoint: Option<(TilePos, String)>,
+}
+
+impl MapWnd {
+    pub const MIN_ZOOM: f32 = 0.25;
+    pub const MAX_ZOOM: f32 = 8.0;
+    /// The map pixel at the screen position `pos`
+    pub fn screen_to_map(&self, pos: ScreenVec, rt_res: ScreenRes) -> Vector2f {
+        self.center + (pos.to_sf_vec2f() - half_res(rt_res)) / self.zoom
+    }
+    /// The screen position of the map pixel `pos`
+    pub fn map_to_screen(&self, pos: Vector2f, rt_res: ScreenRes) -> Vector2f {
+        (pos - self.center) * self.zoom + half_res(rt_res)
+    }
+}
+
+fn half_res(res: ScreenRes) -> Vector2f {
+    Vector2f::new(f32::from(res.w) / 2., f32::from(res.h) / 2.)
+}
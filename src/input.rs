@@ -1,66 +1,257 @@
 use {
-    crate::math::ScreenVecExt,
+    self::gamepad::{Gamepad, JoystickSource},
+    crate::{inventory::HOTBAR_SLOTS, math::ScreenVecExt},
     fnv::{FnvHashMap, FnvHashSet},
-    mdv_math::types::ScreenVec,
+    mdv_math::types::{ScreenSc, ScreenVec},
+    serde::{Deserialize, Serialize},
     sfml::window::{mouse, Event, Key},
     smart_default::SmartDefault,
+    std::fmt,
 };
 
+pub mod gamepad;
+
+/// Pixels per frame the virtual cursor moves at full right stick deflection
+const PAD_CURSOR_SPEED: f32 = 12.0;
+
 #[derive(SmartDefault)]
 pub struct Input {
-    down: FnvHashSet<Key>,
-    pressed: FnvHashSet<Key>,
+    down: FnvHashSet<RawInput>,
+    pressed: FnvHashSet<RawInput>,
+    released: FnvHashSet<RawInput>,
+    /// Modifiers that were part of a chord since they were last pressed
+    chorded: Mods,
     #[default(default_key_bindings())]
-    pub key_bindings: FnvHashMap<InputAction, Key>,
+    pub key_bindings: FnvHashMap<InputAction, Binding>,
     pub lmb_down: bool,
     pub lmb_pressed: bool,
     pub rmb_down: bool,
+    pub rmb_pressed: bool,
     pub mouse_down_loc: ScreenVec,
     pub mid_pressed: bool,
-    pub just_pressed_raw: Option<Key>,
+    pub just_pressed_raw: Option<RawInput>,
+    pub just_released_raw: Option<RawInput>,
+    pub pad: Gamepad,
+    /// Gamepad button bound to each action, in addition to the key binding
+    #[default(default_pad_bindings())]
+    pub pad_bindings: FnvHashMap<InputAction, u32>,
+}
+
+/// A physical input that actions can be bound to
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum RawInput {
+    Key(#[serde(with = "key_serde")] Key),
+    Mouse(MouseButton),
+    WheelUp,
+    WheelDown,
+}
+
+impl RawInput {
+    /// Whether this is one of the modifier keys that [`Mods`] tracks
+    pub fn is_modifier(self) -> bool {
+        self.modifier() != Mods::NONE
+    }
+    /// The modifier this input provides when held
+    fn modifier(self) -> Mods {
+        let Self::Key(key) = self else {
+            return Mods::NONE;
+        };
+        Mods {
+            ctrl: matches!(key, Key::LControl | Key::RControl),
+            shift: matches!(key, Key::LShift | Key::RShift),
+            alt: matches!(key, Key::LAlt | Key::RAlt),
+        }
+    }
+}
+
+impl fmt::Display for RawInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Key(key) => write!(f, "{key:?}"),
+            Self::Mouse(button) => write!(f, "Mouse {button:?}"),
+            Self::WheelUp => f.write_str("Wheel up"),
+            Self::WheelDown => f.write_str("Wheel down"),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    X1,
+    X2,
+}
+
+impl MouseButton {
+    fn from_sf(button: mouse::Button) -> Self {
+        match button {
+            mouse::Button::Left => Self::Left,
+            mouse::Button::Right => Self::Right,
+            mouse::Button::Middle => Self::Middle,
+            mouse::Button::XButton1 => Self::X1,
+            mouse::Button::XButton2 => Self::X2,
+        }
+    }
+}
+
+/// Modifier keys held down together with an input
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Mods {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Mods {
+    pub const NONE: Self = Self {
+        ctrl: false,
+        shift: false,
+        alt: false,
+    };
+    /// Whether all the modifiers of `required` are held in `self`
+    fn satisfies(self, required: Self) -> bool {
+        (self.ctrl || !required.ctrl)
+            && (self.shift || !required.shift)
+            && (self.alt || !required.alt)
+    }
+    /// Whether `self` and `other` have any modifier in common
+    fn overlaps(self, other: Self) -> bool {
+        (self.ctrl && other.ctrl) || (self.shift && other.shift) || (self.alt && other.alt)
+    }
+    fn union(self, other: Self) -> Self {
+        Self {
+            ctrl: self.ctrl || other.ctrl,
+            shift: self.shift || other.shift,
+            alt: self.alt || other.alt,
+        }
+    }
+    fn without(self, other: Self) -> Self {
+        Self {
+            ctrl: self.ctrl && !other.ctrl,
+            shift: self.shift && !other.shift,
+            alt: self.alt && !other.alt,
+        }
+    }
+}
+
+/// An input, and the modifiers that have to be held for it to trigger an action
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Binding {
+    pub input: RawInput,
+    #[serde(default)]
+    pub mods: Mods,
+}
+
+impl Binding {
+    const fn key(key: Key) -> Self {
+        Self {
+            input: RawInput::Key(key),
+            mods: Mods::NONE,
+        }
+    }
+    const fn raw(input: RawInput) -> Self {
+        Self {
+            input,
+            mods: Mods::NONE,
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.mods.ctrl {
+            f.write_str("Ctrl+")?;
+        }
+        if self.mods.shift {
+            f.write_str("Shift+")?;
+        }
+        if self.mods.alt {
+            f.write_str("Alt+")?;
+        }
+        self.input.fmt(f)
+    }
+}
+
+pub fn default_key_bindings() -> FnvHashMap<InputAction, Binding> {
+    let mut bindings = FnvHashMap::from_iter([
+        (InputAction::Down, Binding::key(Key::S)),
+        (InputAction::Left, Binding::key(Key::A)),
+        (InputAction::Up, Binding::key(Key::W)),
+        (InputAction::Right, Binding::key(Key::D)),
+        (InputAction::Jump, Binding::key(Key::Space)),
+        (InputAction::ThrowItem, Binding::key(Key::Q)),
+        (InputAction::Interact, Binding::key(Key::E)),
+        (InputAction::SelectTorch, Binding::key(Key::LShift)),
+        (InputAction::Inventory, Binding::key(Key::I)),
+        (InputAction::Crafting, Binding::key(Key::C)),
+        (InputAction::Map, Binding::key(Key::M)),
+        (InputAction::SmartCursorToggle, Binding::key(Key::LControl)),
+        (InputAction::Sprint, Binding::key(Key::LShift)),
+        (InputAction::FastMove, Binding::key(Key::LControl)),
+        (InputAction::Menu, Binding::key(Key::Escape)),
+        (InputAction::Pause, Binding::key(Key::P)),
+        (InputAction::FrameAdvance, Binding::key(Key::Period)),
+        (InputAction::ReverseGravity, Binding::key(Key::R)),
+        (InputAction::HotbarNext, Binding::raw(RawInput::WheelDown)),
+        (InputAction::HotbarPrev, Binding::raw(RawInput::WheelUp)),
+    ]);
+    let number_keys = [
+        Key::Num1,
+        Key::Num2,
+        Key::Num3,
+        Key::Num4,
+        Key::Num5,
+        Key::Num6,
+        Key::Num7,
+        Key::Num8,
+        Key::Num9,
+        Key::Num0,
+    ];
+    for (slot, key) in (0..).zip(number_keys) {
+        bindings.insert(InputAction::Hotbar(slot), Binding::key(key));
+    }
+    bindings
 }
 
-fn default_key_bindings() -> FnvHashMap<InputAction, Key> {
+/// Button numbers follow SFML's Xbox controller layout
+pub fn default_pad_bindings() -> FnvHashMap<InputAction, u32> {
     FnvHashMap::from_iter([
-        (InputAction::Down, Key::S),
-        (InputAction::Left, Key::A),
-        (InputAction::Up, Key::W),
-        (InputAction::Right, Key::D),
-        (InputAction::Jump, Key::Space),
-        (InputAction::ThrowItem, Key::Q),
-        (InputAction::Interact, Key::E),
-        (InputAction::SelectTorch, Key::LShift),
-        (InputAction::Inventory, Key::I),
-        (InputAction::Crafting, Key::C),
-        (InputAction::SmartCursorToggle, Key::LControl),
+        (InputAction::Jump, 0),
+        (InputAction::ThrowItem, 1),
+        (InputAction::Interact, 2),
+        (InputAction::Inventory, 3),
+        (InputAction::HotbarPrev, 4),
+        (InputAction::HotbarNext, 5),
+        (InputAction::Crafting, 6),
+        (InputAction::Menu, 7),
+        (InputAction::Sprint, 9),
     ])
 }
 
 impl Input {
     pub fn update_from_event(&mut self, ev: &Event, egui_kbd: bool, egui_ptr: bool) {
         match ev {
-            &Event::KeyPressed { code, .. } => {
-                self.pressed.insert(code);
-                self.down.insert(code);
-                self.just_pressed_raw = Some(code);
-            }
-            Event::KeyReleased { code, .. } => {
-                self.down.remove(code);
-            }
+            &Event::KeyPressed { code, .. } => self.press(RawInput::Key(code)),
+            &Event::KeyReleased { code, .. } => self.release(RawInput::Key(code)),
             &Event::MouseButtonPressed { button, x, y } => {
                 self.mouse_down_loc = ScreenVec::from_reso_i32(x, y);
+                self.press(RawInput::Mouse(MouseButton::from_sf(button)));
                 if button == mouse::Button::Left {
                     self.lmb_down = true;
                     self.lmb_pressed = true;
                 }
                 if button == mouse::Button::Right {
                     self.rmb_down = true;
+                    self.rmb_pressed = true;
                 }
                 if button == mouse::Button::Middle {
                     self.mid_pressed = true;
                 }
             }
             &Event::MouseButtonReleased { button, .. } => {
+                self.release(RawInput::Mouse(MouseButton::from_sf(button)));
                 if button == mouse::Button::Left {
                     self.lmb_down = false;
                 }
@@ -69,51 +260,230 @@ impl Input {
                 }
             }
             &Event::MouseMoved { x, y } => self.mouse_down_loc = ScreenVec::from_reso_i32(x, y),
+            &Event::MouseWheelScrolled {
+                wheel: mouse::Wheel::VerticalWheel,
+                delta,
+                ..
+            } => {
+                let raw = if delta > 0.0 {
+                    RawInput::WheelUp
+                } else {
+                    RawInput::WheelDown
+                };
+                // The wheel has no held state, it only ever gets "pressed"
+                self.pressed.insert(raw);
+                self.just_pressed_raw = Some(raw);
+            }
             _ => {}
         }
         if egui_kbd {
-            self.pressed.clear();
-            self.down.clear();
+            self.pressed.retain(|raw| !matches!(raw, RawInput::Key(_)));
+            self.down.retain(|raw| !matches!(raw, RawInput::Key(_)));
+            self.released.retain(|raw| !matches!(raw, RawInput::Key(_)));
         }
         if egui_ptr {
             self.lmb_down = false;
             self.rmb_down = false;
             self.mid_pressed = false;
+            self.pressed.retain(|raw| matches!(raw, RawInput::Key(_)));
+            self.down.retain(|raw| matches!(raw, RawInput::Key(_)));
+            self.released.retain(|raw| matches!(raw, RawInput::Key(_)));
         }
     }
+    fn press(&mut self, raw: RawInput) {
+        self.pressed.insert(raw);
+        self.down.insert(raw);
+        self.just_pressed_raw = Some(raw);
+        // Remember which modifiers got used for a chord, so their own bindings don't fire
+        self.chorded = self.chorded.without(raw.modifier());
+        let held = self.mods();
+        for binding in self.key_bindings.values() {
+            if binding.input == raw && held.satisfies(binding.mods) {
+                self.chorded = self.chorded.union(binding.mods);
+            }
+        }
+    }
+    fn release(&mut self, raw: RawInput) {
+        self.down.remove(&raw);
+        self.released.insert(raw);
+        self.just_released_raw = Some(raw);
+    }
     /// Pressed event should be cleared every frame
     pub fn clear_pressed(&mut self) {
         self.mid_pressed = false;
         self.pressed.clear();
+        self.released.clear();
         self.just_pressed_raw = None;
+        self.just_released_raw = None;
         self.lmb_pressed = false;
+        self.rmb_pressed = false;
     }
     pub fn down_raw(&self, key: Key) -> bool {
-        self.down.contains(&key)
+        self.down.contains(&RawInput::Key(key))
     }
     pub fn pressed_raw(&self, key: Key) -> bool {
-        self.pressed.contains(&key)
+        self.pressed.contains(&RawInput::Key(key))
     }
-    pub fn down(&self, action: InputAction) -> bool {
-        match self.key_bindings.get(&action) {
-            Some(key) => self.down_raw(*key),
-            None => {
-                log::warn!("Missing key bind for {}", action.name());
-                false
-            }
+    /// Whether `raw` was pressed this frame, regardless of bindings
+    pub fn pressed_input(&self, raw: RawInput) -> bool {
+        self.pressed.contains(&raw)
+    }
+    /// The modifier keys currently held down
+    pub fn mods(&self) -> Mods {
+        Mods {
+            ctrl: self.down_raw(Key::LControl) || self.down_raw(Key::RControl),
+            shift: self.down_raw(Key::LShift) || self.down_raw(Key::RShift),
+            alt: self.down_raw(Key::LAlt) || self.down_raw(Key::RAlt),
+        }
+    }
+    fn binding(&self, action: InputAction) -> Option<&Binding> {
+        let binding = self.key_bindings.get(&action);
+        if binding.is_none() {
+            log::warn!("Missing key bind for {}", action.name());
         }
+        binding
+    }
+    /// Whether the modifiers held right now select `binding`.
+    ///
+    /// If another binding on the same input needs more of the held modifiers, that one wins.
+    /// A binding on a modifier key itself yields to any chord using that modifier.
+    fn binding_active(&self, binding: &Binding) -> bool {
+        let held = self.mods();
+        if !held.satisfies(binding.mods) {
+            return false;
+        }
+        let own_mod = binding.input.modifier();
+        !self.key_bindings.values().any(|other| {
+            let more_specific = other.input == binding.input
+                && other.mods != binding.mods
+                && other.mods.satisfies(binding.mods)
+                && held.satisfies(other.mods);
+            let chord_on_own_mod = own_mod.overlaps(other.mods)
+                && self.down.contains(&other.input)
+                && held.satisfies(other.mods);
+            more_specific || chord_on_own_mod
+        })
+    }
+    /// Whether any binding uses the modifier `key` as part of a chord
+    fn is_chord_modifier(&self, key: RawInput) -> bool {
+        let modifier = key.modifier();
+        self.key_bindings
+            .values()
+            .any(|binding| binding.mods.overlaps(modifier))
+    }
+    /// Whether `action` is held down through its keyboard/mouse binding
+    fn key_down(&self, action: InputAction) -> bool {
+        self.binding(action).is_some_and(|binding| {
+            self.down.contains(&binding.input) && self.binding_active(binding)
+        })
+    }
+    /// Whether `action` got triggered this frame through its keyboard/mouse binding.
+    ///
+    /// Modifier keys that also start chords trigger when released instead, unless a chord
+    /// was used while they were held.
+    fn key_pressed(&self, action: InputAction) -> bool {
+        self.binding(action).is_some_and(|binding| {
+            if self.is_chord_modifier(binding.input) {
+                self.released.contains(&binding.input)
+                    && !self.chorded.overlaps(binding.input.modifier())
+                    && self
+                        .mods()
+                        .without(binding.input.modifier())
+                        .satisfies(binding.mods)
+            } else {
+                self.pressed.contains(&binding.input) && self.binding_active(binding)
+            }
+        })
+    }
+    /// Whether `action` is held down on the gamepad
+    fn pad_down(&self, action: InputAction) -> bool {
+        let dir = match action {
+            InputAction::Left => Some(gamepad::Dir::Left),
+            InputAction::Right => Some(gamepad::Dir::Right),
+            InputAction::Up => Some(gamepad::Dir::Up),
+            InputAction::Down => Some(gamepad::Dir::Down),
+            _ => None,
+        };
+        (dir.is_some() && self.pad.dir == dir)
+            || self
+                .pad_bindings
+                .get(&action)
+                .is_some_and(|&button| self.pad.down(button))
+    }
+    pub fn down(&self, action: InputAction) -> bool {
+        self.key_down(action) || self.pad_down(action)
     }
     pub fn pressed(&self, action: InputAction) -> bool {
-        self.pressed_raw(self.key_bindings[&action])
+        self.key_pressed(action)
+            || self
+                .pad_bindings
+                .get(&action)
+                .is_some_and(|&button| self.pad.pressed(button))
+    }
+    /// Horizontal movement intent in -1..1.
+    ///
+    /// Keys always move at full speed, the gamepad stick allows slower movement.
+    pub fn move_x(&self) -> f32 {
+        if self.key_down(InputAction::Right) {
+            1.0
+        } else if self.key_down(InputAction::Left) {
+            -1.0
+        } else {
+            self.pad.move_stick[0]
+        }
+    }
+    /// Read the gamepad state, and move the cursor with its right stick.
+    ///
+    /// Returns the new cursor position if the gamepad moved it.
+    pub fn poll_gamepad(
+        &mut self,
+        src: &impl JoystickSource,
+        window_size: ScreenVec,
+    ) -> Option<ScreenVec> {
+        let [was_lt, was_rt] = self.pad.triggers;
+        self.pad.poll(src);
+        // The triggers act as the mouse buttons, for using items
+        let [lt, rt] = self.pad.triggers;
+        if rt != was_rt {
+            self.lmb_down = rt;
+            self.lmb_pressed |= rt;
+        }
+        if lt != was_lt {
+            self.rmb_down = lt;
+            self.rmb_pressed |= lt;
+        }
+        let [x, y] = self.pad.aim_stick;
+        if x == 0.0 && y == 0.0 {
+            return None;
+        }
+        #[expect(
+            clippy::cast_possible_truncation,
+            reason = "The cursor moves a few pixels per frame"
+        )]
+        let (dx, dy) = (
+            (x * PAD_CURSOR_SPEED) as ScreenSc,
+            (y * PAD_CURSOR_SPEED) as ScreenSc,
+        );
+        let loc = &mut self.mouse_down_loc;
+        loc.x = loc
+            .x
+            .saturating_add(dx)
+            .clamp(0, (window_size.x - 1).max(0));
+        loc.y = loc
+            .y
+            .saturating_add(dy)
+            .clamp(0, (window_size.y - 1).max(0));
+        Some(*loc)
     }
     /// Clear all keyboard state
     pub(crate) fn clear_all_kbd(&mut self) {
         self.down.clear();
         self.pressed.clear();
+        self.released.clear();
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum InputAction {
     Left,
     Right,
@@ -125,10 +495,57 @@ pub enum InputAction {
     SelectTorch,
     Inventory,
     Crafting,
+    /// Open the world map
+    Map,
     SmartCursorToggle,
+    Sprint,
+    /// Very fast movement, for debugging
+    FastMove,
+    Menu,
+    Pause,
+    /// Advance a single frame while paused
+    FrameAdvance,
+    ReverseGravity,
+    /// Select a hotbar slot (0-9)
+    Hotbar(u8),
+    HotbarNext,
+    HotbarPrev,
 }
 
 impl InputAction {
+    /// Every action, in the order they are listed in the key config menu
+    pub const ALL: [Self; 30] = [
+        Self::Left,
+        Self::Right,
+        Self::Up,
+        Self::Down,
+        Self::Jump,
+        Self::Sprint,
+        Self::Interact,
+        Self::ThrowItem,
+        Self::SelectTorch,
+        Self::Inventory,
+        Self::Crafting,
+        Self::Map,
+        Self::SmartCursorToggle,
+        Self::Hotbar(0),
+        Self::Hotbar(1),
+        Self::Hotbar(2),
+        Self::Hotbar(3),
+        Self::Hotbar(4),
+        Self::Hotbar(5),
+        Self::Hotbar(6),
+        Self::Hotbar(7),
+        Self::Hotbar(8),
+        Self::Hotbar(9),
+        Self::HotbarNext,
+        Self::HotbarPrev,
+        Self::Menu,
+        Self::Pause,
+        Self::FrameAdvance,
+        Self::ReverseGravity,
+        Self::FastMove,
+    ];
     pub fn name(&self) -> &'static str {
         match self {
             InputAction::Left => "Left",
@@ -141,7 +558,224 @@ impl InputAction {
             InputAction::SelectTorch => "Select torch",
             InputAction::Inventory => "Inventory",
             InputAction::Crafting => "Crafting",
+            InputAction::Map => "Map",
             InputAction::SmartCursorToggle => "Smart cursor toggle",
+            InputAction::Sprint => "Sprint",
+            InputAction::FastMove => "Fast move (debug)",
+            InputAction::Menu => "Menu",
+            InputAction::Pause => "Pause",
+            InputAction::FrameAdvance => "Advance frame",
+            InputAction::ReverseGravity => "Reverse gravity (debug)",
+            InputAction::Hotbar(slot) => HOTBAR_NAMES
+                .get(usize::from(*slot))
+                .copied()
+                .unwrap_or("Hotbar ?"),
+            InputAction::HotbarNext => "Next hotbar slot",
+            InputAction::HotbarPrev => "Previous hotbar slot",
+        }
+    }
+}
+
+const HOTBAR_NAMES: [&str; HOTBAR_SLOTS] = [
+    "Hotbar 1",
+    "Hotbar 2",
+    "Hotbar 3",
+    "Hotbar 4",
+    "Hotbar 5",
+    "Hotbar 6",
+    "Hotbar 7",
+    "Hotbar 8",
+    "Hotbar 9",
+    "Hotbar 10",
+];
+
+/// Keys are saved by name, so the config stays readable
+mod key_serde {
+    use {
+        serde::{Deserialize, Deserializer, Serializer},
+        sfml::window::Key,
+    };
+
+    /// The match in `name` has to cover every key, so a key left out of the list doesn't compile
+    macro_rules! keys {
+        ($($key:ident,)*) => {
+            /// The keys that can be loaded back from a config
+            pub(super) const KEYS: &[Key] = &[$(Key::$key),*];
+            fn name(key: Key) -> &'static str {
+                match key {
+                    $(Key::$key => stringify!($key),)*
+                }
+            }
+        };
+    }
+
+    keys! {
+        Unknown,
+        A,
+        B,
+        C,
+        D,
+        E,
+        F,
+        G,
+        H,
+        I,
+        J,
+        K,
+        L,
+        M,
+        N,
+        O,
+        P,
+        Q,
+        R,
+        S,
+        T,
+        U,
+        V,
+        W,
+        X,
+        Y,
+        Z,
+        Num0,
+        Num1,
+        Num2,
+        Num3,
+        Num4,
+        Num5,
+        Num6,
+        Num7,
+        Num8,
+        Num9,
+        Escape,
+        LControl,
+        LShift,
+        LAlt,
+        LSystem,
+        RControl,
+        RShift,
+        RAlt,
+        RSystem,
+        Menu,
+        LBracket,
+        RBracket,
+        Semicolon,
+        Comma,
+        Period,
+        Apostrophe,
+        Slash,
+        Backslash,
+        Grave,
+        Equal,
+        Hyphen,
+        Space,
+        Enter,
+        Backspace,
+        Tab,
+        PageUp,
+        PageDown,
+        End,
+        Home,
+        Insert,
+        Delete,
+        Add,
+        Subtract,
+        Multiply,
+        Divide,
+        Left,
+        Right,
+        Up,
+        Down,
+        Numpad0,
+        Numpad1,
+        Numpad2,
+        Numpad3,
+        Numpad4,
+        Numpad5,
+        Numpad6,
+        Numpad7,
+        Numpad8,
+        Numpad9,
+        F1,
+        F2,
+        F3,
+        F4,
+        F5,
+        F6,
+        F7,
+        F8,
+        F9,
+        F10,
+        F11,
+        F12,
+        F13,
+        F14,
+        F15,
+        Pause,
+    }
+
+    pub fn serialize<S: Serializer>(key: &Key, ser: S) -> Result<S::Ok, S::Error> {
+        ser.serialize_str(name(*key))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<Key, D::Error> {
+        let name = String::deserialize(de)?;
+        match KEYS.iter().find(|key| self::name(**key) == name) {
+            Some(key) => Ok(*key),
+            None => {
+                log::warn!("Unknown key '{name}' in key bindings");
+                Ok(Key::Unknown)
+            }
         }
     }
 }
+
+#[test]
+fn test_chord_wins_over_plain_bindings() {
+    let mut input = Input::default();
+    let ctrl = Mods {
+        ctrl: true,
+        ..Mods::NONE
+    };
+    input.key_bindings.insert(
+        InputAction::Pause,
+        Binding {
+            input: RawInput::Key(Key::Num1),
+            mods: ctrl,
+        },
+    );
+    input.press(RawInput::Key(Key::LControl));
+    input.press(RawInput::Key(Key::Num1));
+    assert!(input.pressed(InputAction::Pause));
+    assert!(!input.pressed(InputAction::Hotbar(0)));
+    assert!(!input.down(InputAction::FastMove));
+    assert!(!input.pressed(InputAction::SmartCursorToggle));
+    input.clear_pressed();
+    input.release(RawInput::Key(Key::Num1));
+    input.release(RawInput::Key(Key::LControl));
+    // Ctrl was used for a chord, so it doesn't toggle the smart cursor
+    assert!(!input.pressed(InputAction::SmartCursorToggle));
+    input.clear_pressed();
+    // Held on its own, it does
+    input.press(RawInput::Key(Key::LControl));
+    assert!(input.down(InputAction::FastMove));
+    input.clear_pressed();
+    input.release(RawInput::Key(Key::LControl));
+    assert!(input.pressed(InputAction::SmartCursorToggle));
+    // Plain bindings still work with unrelated modifiers held
+    input.press(RawInput::Key(Key::LShift));
+    input.press(RawInput::Key(Key::D));
+    assert!(input.down(InputAction::Right) && input.down(InputAction::Sprint));
+}
+
+#[test]
+fn test_all_keys_roundtrip() {
+    for &key in key_serde::KEYS {
+        let ser = ron::to_string(&RawInput::Key(key)).unwrap();
+        assert_eq!(
+            ron::from_str::<RawInput>(&ser).unwrap(),
+            RawInput::Key(key),
+            "{ser}"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use {fnv::FnvHashSet, sfml::window::joystick::Axis};
+
+/// Stick deflection (0-1) below which a stick counts as centered
+pub const DEADZONE: f32 = 0.25;
+/// Stick deflection (0-1) above which a stick counts as pushed in a direction
+const DIGITAL_THRESHOLD: f32 = 0.5;
+/// How many joysticks and buttons SFML supports
+const MAX_JOYSTICKS: u32 = 8;
+const MAX_BUTTONS: u32 = 32;
+
+/// Confirms menu items (A on an Xbox layout)
+pub const BUTTON_CONFIRM: u32 = 0;
+/// Goes back in menus (B on an Xbox layout)
+pub const BUTTON_BACK: u32 = 1;
+
+/// Where gamepad state is read from, so tests can feed in a fake gamepad
+pub trait JoystickSource {
+    fn is_connected(&self, id: u32) -> bool;
+    fn is_button_pressed(&self, id: u32, button: u32) -> bool;
+    /// Axis position in the -100..100 range, like SFML reports it
+    fn axis_position(&self, id: u32, axis: Axis) -> f32;
+}
+
+/// The real joysticks, read through SFML
+pub struct SfmlJoysticks;
+
+impl JoystickSource for SfmlJoysticks {
+    fn is_connected(&self, id: u32) -> bool {
+        sfml::window::joystick::is_connected(id)
+    }
+    fn is_button_pressed(&self, id: u32, button: u32) -> bool {
+        sfml::window::joystick::is_button_pressed(id, button)
+    }
+    fn axis_position(&self, id: u32, axis: Axis) -> f32 {
+        sfml::window::joystick::axis_position(id, axis)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Dir {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// State of the first connected gamepad
+#[derive(Default)]
+pub struct Gamepad {
+    /// Id of the joystick in use, if one is connected
+    pub id: Option<u32>,
+    down: FnvHashSet<u32>,
+    pressed: FnvHashSet<u32>,
+    /// Left stick (or d-pad), with the deadzone applied. Both axes are in -1..1.
+    pub move_stick: [f32; 2],
+    /// Right stick, with the deadzone applied. Drives the virtual cursor.
+    pub aim_stick: [f32; 2],
+    /// Left and right trigger
+    pub triggers: [bool; 2],
+    /// The direction the move stick or d-pad points to, if it's pushed far enough
+    pub dir: Option<Dir>,
+    /// Set on the frame `dir` changed to a new direction
+    pub dir_pressed: Option<Dir>,
+}
+
+impl Gamepad {
+    /// Read the new state from `src`. Should be called once every frame.
+    pub fn poll(&mut self, src: &impl JoystickSource) {
+        let Some(id) = (0..MAX_JOYSTICKS).find(|&id| src.is_connected(id)) else {
+            if self.id.take().is_some() {
+                log::info!("Gamepad disconnected");
+            }
+            *self = Self::default();
+            return;
+        };
+        if self.id != Some(id) {
+            log::info!("Using gamepad {id}");
+            self.id = Some(id);
+        }
+        self.pressed.clear();
+        for button in 0..MAX_BUTTONS {
+            if src.is_button_pressed(id, button) {
+                if self.down.insert(button) {
+                    self.pressed.insert(button);
+                }
+            } else {
+                self.down.remove(&button);
+            }
+        }
+        let axis = |axis| src.axis_position(id, axis) / 100.0;
+        self.move_stick = deadzone([axis(Axis::X), axis(Axis::Y)]);
+        // The d-pad overrides the stick
+        let pov = [axis(Axis::PovX), axis(Axis::PovY)];
+        for (stick, pov) in self.move_stick.iter_mut().zip(pov) {
+            if pov.abs() > DIGITAL_THRESHOLD {
+                *stick = pov.signum();
+            }
+        }
+        self.aim_stick = deadzone([axis(Axis::U), axis(Axis::V)]);
+        // Triggers rest at -1 on some platforms and at 0 on others
+        self.triggers = [
+            axis(Axis::Z) > DIGITAL_THRESHOLD,
+            axis(Axis::R) > DIGITAL_THRESHOLD,
+        ];
+        let dir = digital_dir(self.move_stick);
+        self.dir_pressed = if dir == self.dir { None } else { dir };
+        self.dir = dir;
+    }
+    pub fn down(&self, button: u32) -> bool {
+        self.down.contains(&button)
+    }
+    pub fn pressed(&self, button: u32) -> bool {
+        self.pressed.contains(&button)
+    }
+    /// A button that was pressed this frame, if any
+    pub fn just_pressed(&self) -> Option<u32> {
+        self.pressed.iter().min().copied()
+    }
+}
+
+/// Ignore small deflections, and rescale the rest to start from 0
+fn deadzone(stick: [f32; 2]) -> [f32; 2] {
+    let len = stick[0].hypot(stick[1]);
+    if len < DEADZONE {
+        return [0.0; 2];
+    }
+    let scale = ((len - DEADZONE) / (1.0 - DEADZONE)).min(1.0) / len;
+    stick.map(|v| v * scale)
+}
+
+fn digital_dir([x, y]: [f32; 2]) -> Option<Dir> {
+    if x.abs().max(y.abs()) < DIGITAL_THRESHOLD {
+        None
+    } else if x.abs() > y.abs() {
+        Some(if x < 0.0 { Dir::Left } else { Dir::Right })
+    } else {
+        Some(if y < 0.0 { Dir::Up } else { Dir::Down })
+    }
+}
+
+#[cfg(test)]
+#[derive(Default)]
+struct FakeJoystick {
+    buttons: FnvHashSet<u32>,
+    axes: Vec<(Axis, f32)>,
+}
+
+#[cfg(test)]
+impl JoystickSource for FakeJoystick {
+    fn is_connected(&self, id: u32) -> bool {
+        id == 1
+    }
+    fn is_button_pressed(&self, _id: u32, button: u32) -> bool {
+        self.buttons.contains(&button)
+    }
+    fn axis_position(&self, _id: u32, axis: Axis) -> f32 {
+        self.axes
+            .iter()
+            .find(|(ax, _)| *ax == axis)
+            .map_or(0.0, |(_, pos)| *pos)
+    }
+}
+
+#[test]
+fn test_poll_fake_gamepad() {
+    let mut pad = Gamepad::default();
+    let mut fake = FakeJoystick {
+        axes: vec![(Axis::X, -10.0), (Axis::U, 100.0)],
+        ..Default::default()
+    };
+    fake.buttons.insert(BUTTON_CONFIRM);
+    pad.poll(&fake);
+    assert_eq!(pad.id, Some(1));
+    assert!(pad.pressed(BUTTON_CONFIRM));
+    // Within the deadzone
+    assert_eq!(pad.move_stick, [0.0, 0.0]);
+    assert_eq!(pad.aim_stick, [1.0, 0.0]);
+    assert_eq!(pad.dir, None);
+    fake.axes[0].1 = -80.0;
+    pad.poll(&fake);
+    // Still held, but not newly pressed
+    assert!(pad.down(BUTTON_CONFIRM) && !pad.pressed(BUTTON_CONFIRM));
+    assert!(pad.move_stick[0] < -0.5 && pad.move_stick[0] > -1.0);
+    assert_eq!(pad.dir_pressed, Some(Dir::Left));
+    pad.poll(&fake);
+    assert_eq!(pad.dir, Some(Dir::Left));
+    assert_eq!(pad.dir_pressed, None);
+}
@@ -1,9 +1,16 @@
 use {
-    mdv_data::item::{ItemDb, ItemId, ItemStack},
+    mdv_data::{
+        item::{ItemDb, ItemDef, ItemId, ItemQty, ItemStack},
+        palette::IdRemap,
+    },
     serde::{Deserialize, Serialize},
+    std::ops::Range,
 };
 
-#[derive(Debug, Serialize, Deserialize)]
+/// The first row of the inventory is the hotbar
+pub const HOTBAR_SLOTS: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Inventory {
     pub slots: Vec<ItemStack>,
     pub grabbed: Option<ItemStack>,
@@ -11,67 +18,130 @@ pub struct Inventory {
 impl Inventory {
     /// A new inventory filled with empty slots
     pub(crate) fn new_empty() -> Self {
-        let mut slots = Vec::new();
-        for _ in 0..20 {
-            slots.push(ItemStack {
-                id: ItemId::EMPTY,
-                qty: 0,
-            });
-        }
+        Self::with_slots(20)
+    }
+    /// A new inventory with `n` empty slots
+    pub(crate) fn with_slots(n: usize) -> Self {
         Self {
-            slots,
+            slots: vec![ItemStack::empty(); n],
             grabbed: None,
         }
     }
-    /// Returns false if the item can't be added (full inv)
-    pub fn add(&mut self, id: ItemId, qty: u16, itemdb: &ItemDb) -> bool {
-        let stackable = itemdb.get(id).map_or(false, |en| en.stackable);
-        if !stackable {
-            // Just try to find an empty slot
-            for slot in &mut self.slots {
-                if slot.id == ItemId::EMPTY {
-                    slot.id = id;
-                    // Quantity doesn't matter for non-stackable items
-                    slot.qty = 1;
-                    return true;
-                }
-            }
-        }
+    /// Add items, filling up existing stacks first.
+    ///
+    /// Returns how many items didn't fit.
+    pub fn add(&mut self, id: ItemId, qty: ItemQty, itemdb: &ItemDb) -> ItemQty {
+        self.add_stack(ItemStack { id, qty, wear: 0 }, itemdb)
+    }
+
+    /// Like [`Self::add`], but keeps the wear of the stack
+    pub fn add_stack(&mut self, stack: ItemStack, itemdb: &ItemDb) -> ItemQty {
+        self.add_to_range(0..self.slots.len(), stack, itemdb)
+    }
+
+    fn add_to_range(&mut self, range: Range<usize>, stack: ItemStack, itemdb: &ItemDb) -> ItemQty {
+        let limit = stack_limit(itemdb, stack.id);
+        let mut qty = stack.qty;
         // First, try to merge with existing slots
-        for slot in &mut self.slots {
-            if slot.id == id {
-                slot.qty += qty;
-                return true;
+        for slot in &mut self.slots[range.clone()] {
+            if qty == 0 {
+                return 0;
+            }
+            if slot.id == stack.id && slot.qty < limit {
+                let n = qty.min(limit - slot.qty);
+                slot.qty += n;
+                qty -= n;
             }
         }
-        // Next, try to find an empty slot
-        for slot in &mut self.slots {
+        // Next, try to find empty slots
+        for slot in &mut self.slots[range] {
+            if qty == 0 {
+                return 0;
+            }
             if slot.id == ItemId::EMPTY {
-                slot.id = id;
-                slot.qty = qty;
-                return true;
+                let n = qty.min(limit);
+                *slot = ItemStack {
+                    id: stack.id,
+                    qty: n,
+                    wear: stack.wear,
+                };
+                qty -= n;
             }
         }
-        false
+        qty
     }
 
-    /// Returns whether an item could be added into the inventory
-    ///
-    /// TODO: Add quantity handling
-    pub fn item_can_be_added(&self, id: ItemId, _qty: u16) -> bool {
-        // First, try to merge with existing slots
+    /// Returns whether `qty` amount of item `id` could be added into the inventory
+    pub fn item_can_be_added(&self, id: ItemId, qty: ItemQty, itemdb: &ItemDb) -> bool {
+        let limit = stack_limit(itemdb, id);
+        let mut room: u64 = 0;
         for slot in &self.slots {
             if slot.id == id {
-                return true;
+                room += u64::from(limit.saturating_sub(slot.qty));
+            } else if slot.id == ItemId::EMPTY {
+                room += u64::from(limit);
             }
         }
-        // Next, try to find an empty slot
-        for slot in &self.slots {
-            if slot.id == ItemId::EMPTY {
-                return true;
-            }
+        room >= u64::from(qty)
+    }
+
+    /// Left click on a slot. See [`click_stack`].
+    pub(crate) fn click_slot(&mut self, idx: usize, itemdb: &ItemDb) {
+        if let Some(slot) = self.slots.get_mut(idx) {
+            click_stack(&mut self.grabbed, slot, itemdb);
+        }
+    }
+
+    /// Right click on a slot. See [`right_click_stack`].
+    pub(crate) fn right_click_slot(&mut self, idx: usize, itemdb: &ItemDb) {
+        if let Some(slot) = self.slots.get_mut(idx) {
+            right_click_stack(&mut self.grabbed, slot, itemdb);
+        }
+    }
+
+    /// Move the stack in slot `idx` between the hotbar and the rest of the inventory
+    pub(crate) fn quick_move(&mut self, idx: usize, itemdb: &ItemDb) {
+        let Some(slot) = self.slots.get_mut(idx) else {
+            return;
+        };
+        if slot.id == ItemId::EMPTY {
+            return;
+        }
+        let stack = std::mem::replace(slot, ItemStack::empty());
+        let (id, wear) = (stack.id, stack.wear);
+        let target = if idx < HOTBAR_SLOTS {
+            HOTBAR_SLOTS..self.slots.len()
+        } else {
+            0..HOTBAR_SLOTS
+        };
+        let left = self.add_to_range(target, stack, itemdb);
+        if left != 0 {
+            self.slots[idx] = ItemStack {
+                id,
+                qty: left,
+                wear,
+            };
+        }
+    }
+
+    /// Move as much as possible of the stack in slot `idx` into `other`
+    pub(crate) fn transfer_slot(&mut self, idx: usize, other: &mut Self, itemdb: &ItemDb) {
+        let Some(slot) = self.slots.get_mut(idx) else {
+            return;
+        };
+        if slot.id == ItemId::EMPTY {
+            return;
+        }
+        let stack = std::mem::replace(slot, ItemStack::empty());
+        let (id, wear) = (stack.id, stack.wear);
+        let left = other.add_stack(stack, itemdb);
+        if left != 0 {
+            *slot = ItemStack {
+                id,
+                qty: left,
+                wear,
+            };
         }
-        false
     }
 
     pub(crate) fn take_from_slot(&mut self, idx: usize, qty: u16) -> Option<ItemStack> {
@@ -84,10 +154,12 @@ impl Inventory {
                         Some(result) => {
                             slot.qty = result;
                             let id = slot.id;
+                            let wear = slot.wear;
                             if slot.qty == 0 {
                                 slot.id = ItemId::EMPTY;
+                                slot.wear = 0;
                             }
-                            Some(ItemStack { id, qty })
+                            Some(ItemStack { id, qty, wear })
                         }
                         None => None,
                     }
@@ -121,6 +193,7 @@ impl Inventory {
             // Make any slots that reached a qty of 0 empty
             if slot.qty == 0 {
                 slot.id = ItemId::EMPTY;
+                slot.wear = 0;
             }
             if qty == 0 {
                 break;
@@ -128,6 +201,20 @@ impl Inventory {
         }
     }
 
+    /// Take out every stack (including the grabbed one) whose item matches `pred`
+    pub(crate) fn take_where(&mut self, mut pred: impl FnMut(ItemId) -> bool) -> Vec<ItemStack> {
+        let mut taken = Vec::new();
+        for slot in &mut self.slots {
+            if slot.id != ItemId::EMPTY && pred(slot.id) {
+                taken.push(std::mem::replace(slot, ItemStack::empty()));
+            }
+        }
+        if let Some(stack) = self.grabbed.take_if(|stack| pred(stack.id)) {
+            taken.push(stack);
+        }
+        taken
+    }
+
     pub(crate) fn find_item_idx(&self, id: ItemId) -> Option<usize> {
         for (i, slot) in self.slots.iter().enumerate() {
             if slot.id == id && slot.qty > 0 {
@@ -136,4 +223,142 @@ impl Inventory {
         }
         None
     }
+
+    /// Remap the item ids of all stacks after the item database changed
+    pub(crate) fn remap_ids(&mut self, remap: &IdRemap) {
+        for stack in self.slots.iter_mut().chain(self.grabbed.as_mut()) {
+            stack.id = ItemId(remap.get(stack.id.0));
+            if stack.id == ItemId::EMPTY {
+                stack.qty = 0;
+            }
+        }
+    }
+}
+
+/// Left click on `slot` with the cursor holding `grabbed`.
+///
+/// Picks up the stack, or puts down the grabbed stack. Identical stacks get merged,
+/// different ones swapped.
+pub(crate) fn click_stack(grabbed: &mut Option<ItemStack>, slot: &mut ItemStack, itemdb: &ItemDb) {
+    match grabbed {
+        None => {
+            if slot.id != ItemId::EMPTY {
+                *grabbed = Some(std::mem::replace(slot, ItemStack::empty()));
+            }
+        }
+        Some(held) => {
+            let limit = stack_limit(itemdb, slot.id);
+            if held.id == slot.id && slot.qty < limit {
+                let n = held.qty.min(limit - slot.qty);
+                slot.qty += n;
+                held.qty -= n;
+            } else {
+                std::mem::swap(held, slot);
+            }
+            if held.qty == 0 || held.id == ItemId::EMPTY {
+                *grabbed = None;
+            }
+        }
+    }
+}
+
+/// Right click on `slot` with the cursor holding `grabbed`.
+///
+/// Takes half of the stack, or puts down a single item of the grabbed stack.
+pub(crate) fn right_click_stack(
+    grabbed: &mut Option<ItemStack>,
+    slot: &mut ItemStack,
+    itemdb: &ItemDb,
+) {
+    match grabbed {
+        None => {
+            if slot.id == ItemId::EMPTY {
+                return;
+            }
+            let half = slot.qty.div_ceil(2);
+            slot.qty -= half;
+            let taken = ItemStack {
+                id: slot.id,
+                qty: half,
+                wear: slot.wear,
+            };
+            if slot.qty == 0 {
+                *slot = ItemStack::empty();
+            }
+            *grabbed = Some(taken);
+        }
+        Some(held) => {
+            if slot.id == ItemId::EMPTY {
+                *slot = ItemStack {
+                    id: held.id,
+                    qty: 1,
+                    wear: held.wear,
+                };
+            } else if slot.id == held.id && slot.qty < stack_limit(itemdb, slot.id) {
+                slot.qty += 1;
+            } else {
+                return;
+            }
+            held.qty -= 1;
+            if held.qty == 0 {
+                *grabbed = None;
+            }
+        }
+    }
+}
+
+/// How many of item `id` fit into a single slot
+fn stack_limit(itemdb: &ItemDb, id: ItemId) -> ItemQty {
+    itemdb.get(id).map_or(1, ItemDef::stack_limit)
+}
+
+#[test]
+fn test_add_respects_max_stack() {
+    use mdv_data::item::UseAction;
+    let itemdb = ItemDb {
+        db: vec![ItemDef {
+            key: "dirt_block".into(),
+            name: "Dirt Block".into(),
+            graphic_name: String::new(),
+            tex_rect: Default::default(),
+            draw_off: Default::default(),
+            use1: UseAction::Nothing,
+            use2: UseAction::Nothing,
+            stackable: true,
+            max_stack: 10,
+            durability: 0,
+            equip: None,
+            currency: false,
+        }],
+    };
+    let mut inv = Inventory::new_empty();
+    assert_eq!(inv.add(ItemId(1), 195, &itemdb), 0);
+    assert!(!inv.item_can_be_added(ItemId(1), 6, &itemdb));
+    assert_eq!(inv.add(ItemId(1), 10, &itemdb), 5);
+    assert!(inv.slots.iter().all(|slot| slot.qty == 10));
+}
+
+#[test]
+fn test_take_where() {
+    let mut inv = Inventory::new_empty();
+    inv.slots[0] = ItemStack {
+        id: ItemId(1),
+        qty: 5,
+        wear: 0,
+    };
+    inv.slots[3] = ItemStack {
+        id: ItemId(2),
+        qty: 7,
+        wear: 0,
+    };
+    inv.grabbed = Some(ItemStack {
+        id: ItemId(2),
+        qty: 1,
+        wear: 0,
+    });
+    let taken = inv.take_where(|id| id == ItemId(2));
+    assert_eq!(taken.iter().map(|stack| stack.qty).sum::<u16>(), 8);
+    assert_eq!(inv.count_item(ItemId(2)), 0);
+    assert_eq!(inv.count_item(ItemId(1)), 5);
+    assert!(inv.grabbed.is_none());
 }
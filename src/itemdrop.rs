@@ -1,22 +1,22 @@
 use {
     crate::{math::WorldPos, player::MovingEnt},
-    mdv_data::item::ItemId,
+    mdv_data::item::ItemStack,
     rand::{seq::SliceRandom, thread_rng, Rng},
     s2dc::Vec2,
 };
 
 #[derive(hecs::Bundle)]
 pub struct ItemdropBundle {
-    pub id: ItemId,
+    pub stack: ItemStack,
     pub mov: MovingEnt,
 }
 
 impl ItemdropBundle {
-    pub fn new_at(id: ItemId, pos: WorldPos) -> Self {
+    pub fn new_at(stack: ItemStack, pos: WorldPos) -> Self {
         let mut rng = thread_rng();
         let spd = rng.gen_range(0.5..2.4);
         Self {
-            id,
+            stack,
             mov: MovingEnt {
                 hspeed: *[-spd, spd].choose(&mut rng).unwrap(),
                 vspeed: 0.0,
@@ -24,9 +24,31 @@ impl ItemdropBundle {
             },
         }
     }
+    /// A drop flying from `pos` towards `target`
+    pub fn thrown(stack: ItemStack, pos: WorldPos, target: WorldPos, speed: f32) -> Self {
+        let dx = target.x as f32 - pos.x as f32;
+        let dy = target.y as f32 - pos.y as f32;
+        let len = dx.hypot(dy).max(1.0);
+        Self {
+            stack,
+            mov: MovingEnt {
+                hspeed: dx / len * speed,
+                vspeed: dy / len * speed,
+                mob: s2dc::MobileEntity::from_pos_and_bb(pos.to_s2dc(), Vec2 { x: 4, y: 4 }),
+            },
+        }
+    }
 }
 
 pub struct PickupCooldown {
     pub tick_dropped: u64,
     pub cooldown: u64,
 }
+
+/// A thrown item that hurts what it hits, until it lands
+pub struct Projectile {
+    pub damage: f32,
+    pub knockback: f32,
+    /// Doesn't get hurt by its own projectile
+    pub thrower: hecs::Entity,
+}
@@ -4,7 +4,8 @@ use {
         game::GameState,
         graphics::ScreenRes,
         math::{WPosSc, TILE_SIZE},
-        player::MovingEnt,
+        player::{Equipment, MovingEnt},
+        status::StatusEffects,
         world::{TPosSc, TilePos},
     },
     fnv::FnvHashSet,
@@ -140,14 +141,28 @@ pub(crate) fn enumerate_light_sources(
     let mut x = 0;
     let mut y = 0;
     let torch_selected = game.selected_item_is(data::item::TORCH);
-    let held_torch_pos = if torch_selected {
-        match game.ecw.query_one_mut::<&MovingEnt>(game.controlled_en) {
-            Ok(en) => en.tile_pos().checked_sub(tp),
-            Err(_) => None,
-        }
-    } else {
-        None
-    };
+    // The player lights up their surroundings by holding a torch, wearing a light source,
+    // or being on fire
+    let (held_torch_pos, held_light) =
+        match game
+            .ecw
+            .query_one_mut::<(&MovingEnt, Option<&Equipment>, Option<&StatusEffects>)>(
+                game.controlled_en,
+            ) {
+            Ok((en, equipment, effects)) => {
+                let mut light = equipment.map_or(0, |eq| eq.stats(&game.itemdb).light);
+                light = light.max(effects.map_or(0, StatusEffects::light));
+                if torch_selected {
+                    light = 255;
+                }
+                if light > 0 {
+                    (en.tile_pos().checked_sub(tp), light)
+                } else {
+                    (None, 0)
+                }
+            }
+            Err(_) => (None, 0),
+        };
     loop {
         let held_torch_here = match held_torch_pos {
             Some(torch_pos) => x == torch_pos.x && y == torch_pos.y,
@@ -166,16 +181,16 @@ pub(crate) fn enumerate_light_sources(
             255
         };
         if held_torch_here {
-            intensity = 255;
+            intensity = intensity.max(held_light);
         }
-        let ls = t.mid == data::tile::mid::TILES_TORCH || empty;
+        let ls = t.mid == data::tile::mid::TORCH || empty;
         if ls || held_torch_here {
             light_state.light_sources.push_back(LightSrc {
                 map_idx: i,
                 intensity,
             });
         }
-        let lb = t.mid == data::tile::mid::TILES_DIRT || t.mid == data::tile::mid::TILES_STONE;
+        let lb = t.mid == data::tile::mid::DIRT || t.mid == data::tile::mid::STONE;
         if lb {
             light_state.light_blockers.insert(i);
         }
@@ -12,9 +12,11 @@ use {
     app::App,
     clap::Parser,
     config::Config,
+    data_pack::DataPack,
     directories::ProjectDirs,
     gamedebug_core::IMMEDIATE,
     res::{Res, ResAudio},
+    res_locator::ResLocator,
     sfml::show_fatal_error_window,
     std::backtrace::Backtrace,
 };
@@ -25,6 +27,7 @@ mod cmdline;
 mod command;
 mod config;
 mod data;
+mod data_pack;
 mod debug;
 mod egui_ext;
 mod game;
@@ -34,19 +37,28 @@ mod inventory;
 mod item;
 mod itemdrop;
 mod light;
+mod map;
 mod math;
+mod placed;
 mod player;
+mod pregen;
 mod res;
+mod res_locator;
 mod save;
 mod sfml;
+mod status;
 mod stringfmt;
+mod survival;
 mod texture_atlas;
 mod tiles;
 mod time;
 mod world;
 
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct CliArgs {
+    #[command(subcommand)]
+    command: Option<CliCmd>,
     world_name: Option<String>,
     #[arg(long = "rand")]
     rand_world: bool,
@@ -55,13 +67,19 @@ pub struct CliArgs {
     debug: bool,
 }
 
-fn try_main() -> anyhow::Result<()> {
+#[derive(clap::Subcommand)]
+enum CliCmd {
+    /// Generate an area of a world ahead of time, and optionally save a map of it
+    Pregen(pregen::PregenArgs),
+}
+
+fn try_main(cli_args: CliArgs) -> anyhow::Result<()> {
     IMMEDIATE.set_enabled(true);
-    let cli_args = CliArgs::parse();
     let project_dirs = ProjectDirs::from("", "", "mantle-diver").unwrap();
     let cfg = Config::load(project_dirs.config_dir())?;
-    let mut res = Res::load(&cfg.res_folder_path)?;
-    let aud = ResAudio::load(&cfg.res_folder_path)?;
+    let locator = ResLocator::new(&project_dirs)?;
+    let mut res = Res::load(DataPack::list(&cfg, &locator))?;
+    let aud = ResAudio::load(&res.packs)?;
     let mus_vol = cfg.music_vol;
     let sfx_vol = cfg.sfx_vol;
     let mut app = App::new(cli_args, &res, cfg, project_dirs)?;
@@ -72,6 +90,18 @@ fn try_main() -> anyhow::Result<()> {
 }
 
 fn main() {
+    let cli_args = CliArgs::parse();
+    if let Some(CliCmd::Pregen(args)) = cli_args.command {
+        // There's no window to show errors in, so everything goes to the terminal
+        env_logger::builder()
+            .filter_level(log::LevelFilter::Warn)
+            .init();
+        if let Err(e) = pregen::run(args) {
+            eprintln!("{e:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
     std::panic::set_hook(Box::new(|panic_info| {
         let payload = panic_info.payload();
         let msg = if let Some(s) = payload.downcast_ref::<&str>() {
@@ -102,7 +132,7 @@ fn main() {
     env_logger::builder()
         .filter_level(log::LevelFilter::Info)
         .init();
-    if let Err(e) = try_main() {
+    if let Err(e) = try_main(cli_args) {
         show_fatal_error_window("Fatal error", e.to_string());
     }
 }
@@ -0,0 +1,259 @@
+use {
+    crate::{
+        data_pack::{res_subdirs, DataPack},
+        math::WORLD_EXTENT,
+        world::{Chunk, ChunkPos, TPosSc, Tile, TilePos, World, CHUNK_EXTENT},
+    },
+    fnv::{FnvHashMap, FnvHashSet},
+    mdv_data::tile::{TileDb, TileDef, TileLayer},
+    serde::{Deserialize, Serialize},
+    sfml::{
+        cpp::FBox,
+        graphics::{Image, IntRect, Texture},
+        system::Vector2f,
+    },
+};
+
+/// RGBA color of a map pixel nothing is drawn on
+pub const EMPTY_COLOR: [u8; 4] = [0; 4];
+/// Color of explored tiles with nothing in them
+const AIR_COLOR: [u8; 4] = [28, 26, 40, 255];
+/// Width and height in tiles of a pixel of the world map
+pub const MAP_TILES_PER_PX: u16 = 4;
+/// Width and height in pixels of the world map image of a chunk
+pub const CHUNK_MAP_PX: u16 = CHUNK_EXTENT / MAP_TILES_PER_PX;
+/// How many regions with explored chunks get read from disk in a frame,
+/// so walking around doesn't stutter
+const REGIONS_PER_FRAME: usize = 1;
+
+/// A named place marked on the world map
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Waypoint {
+    pub name: String,
+    pub pos: TilePos,
+}
+
+/// The parts of the world the player has seen, one image per chunk
+pub struct WorldMap {
+    colors: TileColors,
+    /// Map images of the explored chunks found so far
+    chunks: FnvHashMap<ChunkPos, FBox<Texture>>,
+    /// Regions whose saved chunks were already looked at for explored tiles
+    loaded_regions: FnvHashSet<(u8, u8)>,
+    pub waypoints: Vec<Waypoint>,
+    /// The tile the player last explored around
+    pub explored_from: Option<TilePos>,
+}
+
+impl WorldMap {
+    pub fn new(colors: TileColors, waypoints: Vec<Waypoint>) -> Self {
+        Self {
+            colors,
+            chunks: FnvHashMap::default(),
+            loaded_regions: FnvHashSet::default(),
+            waypoints,
+            explored_from: None,
+        }
+    }
+    /// Use new tile colors, so the map has to be drawn again from scratch
+    pub fn set_colors(&mut self, colors: TileColors) {
+        self.colors = colors;
+        self.chunks.clear();
+        self.loaded_regions.clear();
+    }
+    /// The map images of the explored chunks found so far
+    pub fn chunks(&self) -> impl Iterator<Item = (ChunkPos, &Texture)> {
+        self.chunks.iter().map(|(&pos, tex)| (pos, &**tex))
+    }
+    /// Draw the map image of the chunk at `pos` again from its tiles
+    pub fn update_chunk(&mut self, pos: ChunkPos, chk: &Chunk) {
+        if chk.explored().is_empty() {
+            self.chunks.remove(&pos);
+            return;
+        }
+        let px = u32::from(CHUNK_MAP_PX);
+        let tex = Image::create_from_pixels(px, px, &chunk_pixels(chk, &self.colors))
+            .and_then(|img| Texture::from_image(&img, IntRect::default()));
+        match tex {
+            Ok(tex) => {
+                self.chunks.insert(pos, tex);
+            }
+            Err(e) => log::error!("Failed to create map image of chunk {pos:?}: {e}"),
+        }
+    }
+    /// Find the explored chunks saved in `regions` that weren't looked at yet.
+    ///
+    /// Stops after [`REGIONS_PER_FRAME`] regions with explored chunks, the rest are
+    /// found by later calls.
+    pub fn load_regions(&mut self, world: &World, regions: impl IntoIterator<Item = (u8, u8)>) {
+        let mut budget = REGIONS_PER_FRAME;
+        for region in regions {
+            if budget == 0 {
+                return;
+            }
+            if !self.loaded_regions.insert(region) {
+                continue;
+            }
+            let chunks = world.saved_explored_chunks(region);
+            if chunks.is_empty() {
+                continue;
+            }
+            budget -= 1;
+            for (pos, chk) in chunks {
+                // Chunks drawn from the loaded world are newer than what's saved
+                if !self.chunks.contains_key(&pos) {
+                    self.update_chunk(pos, &chk);
+                }
+            }
+        }
+    }
+}
+
+/// Position of the tile `pos` on the world map, in map pixels
+pub fn tile_to_map(pos: TilePos) -> Vector2f {
+    let scale = f32::from(MAP_TILES_PER_PX);
+    Vector2f::new(pos.x as f32 / scale, pos.y as f32 / scale)
+}
+
+/// The tile at the map pixel position `map`, staying inside of the world
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "Float to int casts saturate, and the result is clamped to the world"
+)]
+pub fn map_to_tile(map: Vector2f) -> TilePos {
+    let scale = f32::from(MAP_TILES_PER_PX);
+    let tile = |c: f32| ((c * scale) as TPosSc).min(WORLD_EXTENT - 1);
+    TilePos {
+        x: tile(map.x),
+        y: tile(map.y),
+    }
+}
+
+/// RGBA pixels of the map image of `chk`.
+///
+/// Each pixel is the average color of the explored tiles it covers,
+/// and transparent if none of them are explored.
+fn chunk_pixels(chk: &Chunk, colors: &TileColors) -> Vec<u8> {
+    let (ext, scale) = (usize::from(CHUNK_EXTENT), usize::from(MAP_TILES_PER_PX));
+    let px = usize::from(CHUNK_MAP_PX);
+    let mut pixels = Vec::with_capacity(px * px * 4);
+    for py in 0..px {
+        for px_x in 0..px {
+            let mut sum = [0u32; 3];
+            let mut n = 0;
+            for y in py * scale..(py + 1) * scale {
+                for idx in y * ext + px_x * scale..y * ext + (px_x + 1) * scale {
+                    if !chk.explored().get(idx) {
+                        continue;
+                    }
+                    let mut color = colors.tile(chk.tiles()[idx]);
+                    if color == EMPTY_COLOR {
+                        color = AIR_COLOR;
+                    }
+                    for (sum, c) in sum.iter_mut().zip(color) {
+                        *sum += u32::from(c);
+                    }
+                    n += 1;
+                }
+            }
+            if n == 0 {
+                pixels.extend(EMPTY_COLOR);
+            } else {
+                pixels.extend(sum.map(|sum| u8::try_from(sum / n).unwrap()));
+                pixels.push(255);
+            }
+        }
+    }
+    pixels
+}
+
+/// Representative colors of the tiles, for drawing maps
+pub struct TileColors {
+    /// Indexed by tile id. The last one is for ids that aren't in the database.
+    bg: Vec<[u8; 3]>,
+    mid: Vec<[u8; 3]>,
+}
+
+impl TileColors {
+    /// Take the map colors of the tiles, or the average colors of their graphics
+    pub fn new(tile_db: &TileDb, packs: &[DataPack]) -> Self {
+        Self {
+            bg: layer_colors(&tile_db.bg, &tile_db.unknown_bg, packs),
+            mid: layer_colors(&tile_db.mid, &tile_db.unknown_mid, packs),
+        }
+    }
+    /// RGBA color of `tile` on a map. Background walls are darker than the tiles in front.
+    pub fn tile(&self, tile: Tile) -> [u8; 4] {
+        let get = |colors: &[[u8; 3]], id: u16| colors[usize::from(id).min(colors.len() - 1)];
+        if !tile.mid.empty() {
+            let [r, g, b] = get(&self.mid, tile.mid.0);
+            [r, g, b, 255]
+        } else if !tile.bg.empty() {
+            let [r, g, b] = get(&self.bg, tile.bg.0);
+            [r / 2, g / 2, b / 2, 255]
+        } else {
+            EMPTY_COLOR
+        }
+    }
+}
+
+fn layer_colors<Layer: TileLayer>(
+    defs: &[TileDef<Layer>],
+    unknown: &TileDef<Layer>,
+    packs: &[DataPack],
+) -> Vec<[u8; 3]> {
+    // Id 0 is the empty tile, which doesn't get drawn
+    std::iter::once([0; 3])
+        .chain(
+            defs.iter()
+                .chain([unknown])
+                .map(|def| tile_color(def, packs)),
+        )
+        .collect()
+}
+
+fn tile_color<Layer: TileLayer>(def: &TileDef<Layer>, packs: &[DataPack]) -> [u8; 3] {
+    if let Some(color) = def.map_color {
+        return color;
+    }
+    // Later packs override graphics of earlier packs with the same name
+    let image = res_subdirs(packs, "graphics")
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .map(|dir| dir.join(format!("{}.png", def.graphic_name)))
+        .find(|path| path.exists())
+        .and_then(|path| Image::from_file(path.to_str()?).ok());
+    match image {
+        Some(image) => average_color(image.pixel_data()),
+        None => [255, 0, 255],
+    }
+}
+
+/// Average color of the RGBA `pixels` that aren't fully transparent
+fn average_color(pixels: &[u8]) -> [u8; 3] {
+    let mut sum = [0u64; 3];
+    let mut n = 0;
+    for px in pixels.chunks_exact(4).filter(|px| px[3] != 0) {
+        for (sum, &c) in sum.iter_mut().zip(px) {
+            *sum += u64::from(c);
+        }
+        n += 1;
+    }
+    sum.map(|sum| u8::try_from(sum / n.max(1)).unwrap())
+}
+
+#[test]
+fn test_map_coords() {
+    let pos = TilePos { x: 1000, y: 42_000 };
+    assert_eq!(tile_to_map(pos), Vector2f::new(250.0, 10_500.0));
+    assert_eq!(map_to_tile(tile_to_map(pos)), pos);
+    assert_eq!(
+        map_to_tile(Vector2f::new(-5.0, 1e9)),
+        TilePos {
+            x: 0,
+            y: WORLD_EXTENT - 1
+        }
+    );
+}
@@ -0,0 +1,70 @@
+use {
+    crate::math::{WPosSc, WorldPos, WorldRect},
+    mdv_data::{
+        item::{ItemId, ItemStack},
+        palette::IdRemap,
+    },
+    mdv_math::types::ScreenVec,
+    serde::{Deserialize, Serialize},
+};
+
+/// An object placed into the world that isn't bound to the tile grid, like a rope
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlacedEnt {
+    pub x: WPosSc,
+    pub y: WPosSc,
+    pub size: ScreenVec,
+    pub graphic: String,
+    pub climbable: bool,
+    /// The item it was placed with, given back when it's picked up
+    pub item: ItemId,
+}
+
+impl PlacedEnt {
+    pub fn rect(&self) -> WorldRect {
+        WorldRect {
+            topleft: WorldPos {
+                x: self.x,
+                y: self.y,
+            },
+            w: WPosSc::try_from(self.size.x).unwrap_or(0),
+            h: WPosSc::try_from(self.size.y).unwrap_or(0),
+        }
+    }
+    /// Returns `false` if the item it was placed with no longer exists
+    pub(crate) fn remap_ids(&mut self, remap: &IdRemap) -> bool {
+        self.item = ItemId(remap.get(self.item.0));
+        self.item != ItemId::EMPTY
+    }
+}
+
+/// Holds the items the player dropped when they died
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Tombstone {
+    pub x: WPosSc,
+    pub y: WPosSc,
+    pub items: Vec<ItemStack>,
+}
+
+impl Tombstone {
+    pub const GRAPHIC: &str = "ents/tombstone";
+    /// Width and height of the graphic
+    pub const SIZE: WPosSc = 32;
+    pub fn rect(&self) -> WorldRect {
+        WorldRect {
+            topleft: WorldPos {
+                x: self.x,
+                y: self.y,
+            },
+            w: Self::SIZE,
+            h: Self::SIZE,
+        }
+    }
+    /// Remap the item ids after the item database changed, dropping items that no longer exist
+    pub(crate) fn remap_ids(&mut self, remap: &IdRemap) {
+        for stack in &mut self.items {
+            stack.id = ItemId(remap.get(stack.id.0));
+        }
+        self.items.retain(|stack| stack.id != ItemId::EMPTY);
+    }
+}
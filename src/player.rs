@@ -1,10 +1,16 @@
 use {
     crate::{
         math::{WPosSc, WorldPos, WorldRect},
+        status::StatusEffects,
         stringfmt::LengthDisp,
+        survival::Survival,
         world::TilePos,
     },
     extension_traits::extension,
+    mdv_data::{
+        item::{EquipSlot, ItemDb, ItemId, ItemStack, StatMods},
+        palette::IdRemap,
+    },
     mdv_math::util::{move_towards_hspeed_vspeed, point_within_circle},
     s2dc::{vec2, MobileEntity},
     serde::{Deserialize, Serialize},
@@ -32,13 +38,82 @@ pub struct Health {
     pub max: f32,
     /// If true, entity cannot be hurt
     pub invulnerable: bool,
+    /// Ticks left until attacks can hurt the entity again
+    #[serde(skip)]
+    pub iframes: u16,
 }
 impl Health {
-    pub(crate) fn damage(&mut self, amount: f32) {
+    /// Take `amount` damage, reduced by the defense of `stats`.
+    ///
+    /// Returns the damage actually taken.
+    pub(crate) fn damage(&mut self, amount: f32, stats: &StatMods) -> f32 {
         if self.invulnerable {
+            return 0.0;
+        }
+        let taken = (amount - stats.defense).max(0.0);
+        self.current -= taken;
+        taken
+    }
+    pub(crate) fn heal(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+}
+
+/// Items worn by an entity, one for each [`EquipSlot`]
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Equipment {
+    pub slots: [ItemStack; EquipSlot::ALL.len()],
+}
+
+impl Default for Equipment {
+    fn default() -> Self {
+        Self {
+            slots: EquipSlot::ALL.map(|_| ItemStack::empty()),
+        }
+    }
+}
+
+impl Equipment {
+    pub fn get(&self, slot: EquipSlot) -> &ItemStack {
+        &self.slots[slot as usize]
+    }
+    /// The stat modifiers of all the worn items combined
+    pub fn stats(&self, itemdb: &ItemDb) -> StatMods {
+        let mut stats = StatMods::default();
+        for stack in &self.slots {
+            if let Some(equip) = itemdb.get(stack.id).and_then(|def| def.equip.as_ref()) {
+                stats.add(&equip.mods);
+            }
+        }
+        stats
+    }
+    /// Left click on an equipment slot while holding `grabbed`.
+    ///
+    /// Only items that can be worn in `slot` can be put there.
+    pub(crate) fn click_slot(
+        &mut self,
+        slot: EquipSlot,
+        grabbed: &mut Option<ItemStack>,
+        itemdb: &ItemDb,
+    ) {
+        if let Some(held) = grabbed
+            && !itemdb
+                .get(held.id)
+                .and_then(|def| def.equip.as_ref())
+                .is_some_and(|equip| equip.slot == slot)
+        {
             return;
         }
-        self.current -= amount;
+        crate::inventory::click_stack(grabbed, &mut self.slots[slot as usize], itemdb);
+    }
+    /// Remap the item ids of all worn items after the item database changed
+    pub(crate) fn remap_ids(&mut self, remap: &IdRemap) {
+        for stack in &mut self.slots {
+            stack.id = ItemId(remap.get(stack.id.0));
+            if stack.id == ItemId::EMPTY {
+                *stack = ItemStack::empty();
+            }
+        }
     }
 }
 
@@ -47,6 +122,10 @@ pub struct MoveExtra {
     /// true if the player wants to jump down from a platform
     pub down_intent: bool,
     pub facing_dir: FacingDir,
+    /// Moving at sprinting speed, which costs stamina
+    pub sprinting: bool,
+    /// Ticks left of being knocked back, without control over horizontal movement
+    pub stagger: u8,
 }
 
 impl Default for MoveExtra {
@@ -55,6 +134,8 @@ impl Default for MoveExtra {
             jumps_left: Default::default(),
             down_intent: Default::default(),
             facing_dir: FacingDir::Right,
+            sprinting: false,
+            stagger: 0,
         }
     }
 }
@@ -109,6 +190,14 @@ impl MovingEnt {
             y: self.mob.en.pos.y as WPosSc,
         }
     }
+    /// Middle of the bounding box
+    pub(crate) fn center(&self) -> WorldPos {
+        let rect = self.world_rect();
+        WorldPos {
+            x: rect.topleft.x + rect.w / 2,
+            y: rect.topleft.y + rect.h / 2,
+        }
+    }
     pub(crate) fn tile_pos(&self) -> TilePos {
         self.world_pos().tile_pos()
     }
@@ -139,25 +228,35 @@ impl s2dc::Entity {
     }
 }
 
+/// Height of the player's bounding box
+pub const PLAYER_HEIGHT: i32 = 46;
+
 #[derive(hecs::Bundle)]
 pub struct PlayerBundle {
     pub mov: MovingEnt,
     pub mov_extra: MoveExtra,
     pub dat: PlayerColors,
     pub health: Health,
+    pub equipment: Equipment,
+    pub survival: Survival,
+    pub effects: StatusEffects,
 }
 
 impl PlayerBundle {
     pub fn new_at(pos: WorldPos) -> Self {
         Self {
-            mov: MovingEnt::new(pos, vec2(20, 46)),
+            mov: MovingEnt::new(pos, vec2(20, PLAYER_HEIGHT)),
             mov_extra: MoveExtra::default(),
             dat: PlayerColors::default(),
             health: Health {
                 current: 100.,
                 max: 100.,
                 invulnerable: false,
+                iframes: 0,
             },
+            equipment: Equipment::default(),
+            survival: Survival::default(),
+            effects: StatusEffects::default(),
         }
     }
 }
@@ -0,0 +1,259 @@
+use {
+    crate::{
+        config::Config,
+        data_pack::{DataPack, PackDbs},
+        map::{TileColors, EMPTY_COLOR},
+        math::WORLD_EXTENT,
+        res_locator::ResLocator,
+        save::Save,
+        world::{Chunk, ChunkPos, GenVersion, World, CHUNK_EXTENT, REGION_CHUNK_EXTENT},
+    },
+    anyhow::{bail, Context},
+    directories::ProjectDirs,
+    mdv_data::{palette::Palette, validate::validate_worldgen},
+    sfml::graphics::Image,
+    std::{
+        num::NonZeroUsize,
+        path::PathBuf,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+    },
+};
+
+/// Width and height of a region in tiles
+const REGION_TILES: u32 = REGION_CHUNK_EXTENT as u32 * CHUNK_EXTENT as u32;
+/// Width and height of the world in regions
+const WORLD_REGIONS: u32 = WORLD_EXTENT.div_ceil(REGION_TILES);
+/// Most pixels a map can have. Images larger than 1 GiB overflow the PNG encoder of SFML.
+const MAX_MAP_PIXELS: u64 = 1 << 28;
+
+/// Generate and save the chunks of an area of a world ahead of time, without opening a window
+#[derive(clap::Args)]
+pub struct PregenArgs {
+    /// Name of the world. It has to be created in the game first, and not be open in it.
+    #[arg(long)]
+    world: String,
+    /// Regions to generate, as `x,y,w,h` in regions of 1024x1024 tiles.
+    ///
+    /// The center of the world is in region column 122, and the surface in region row 41.
+    #[arg(long, value_parser = parse_rect)]
+    rect: RegionRect,
+    /// Generate the chunks that already exist again, replacing everything built there
+    #[arg(long)]
+    regen: bool,
+    /// Switch the world to the latest generator and the current world generation data first,
+    /// like the "Upgrade" button of the world manager
+    #[arg(long)]
+    upgrade: bool,
+    /// Save a map of the area to this PNG file
+    #[arg(long)]
+    map: Option<PathBuf>,
+    /// Width and height in tiles of a pixel of the map
+    #[arg(long, default_value_t = 1)]
+    map_scale: u32,
+    /// How many regions to generate at the same time. The number of CPUs by default.
+    #[arg(long)]
+    threads: Option<NonZeroUsize>,
+}
+
+#[derive(Clone, Copy)]
+struct RegionRect {
+    x: u8,
+    y: u8,
+    w: u8,
+    h: u8,
+}
+
+fn parse_rect(s: &str) -> Result<RegionRect, String> {
+    let nums = s
+        .split(',')
+        .map(|n| n.trim().parse::<u8>().map_err(|e| format!("'{n}': {e}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    let &[x, y, w, h] = &nums[..] else {
+        return Err("Expected x,y,w,h".into());
+    };
+    if w == 0 || h == 0 {
+        return Err("The rect is empty".into());
+    }
+    let fits = |start: u8, len: u8| u32::from(start) + u32::from(len) <= WORLD_REGIONS;
+    if !fits(x, w) || !fits(y, h) {
+        return Err(format!(
+            "The rect reaches out of the world, which is {WORLD_REGIONS}x{WORLD_REGIONS} regions"
+        ));
+    }
+    Ok(RegionRect { x, y, w, h })
+}
+
+impl RegionRect {
+    fn regions(self) -> impl Iterator<Item = (u8, u8)> {
+        let xs = self.x..self.x + self.w;
+        (self.y..self.y + self.h).flat_map(move |y| xs.clone().map(move |x| (x, y)))
+    }
+}
+
+pub fn run(args: PregenArgs) -> anyhow::Result<()> {
+    let project_dirs = ProjectDirs::from("", "", "mantle-diver").unwrap();
+    let cfg = Config::load(project_dirs.config_dir())?;
+    let locator = ResLocator::new(&project_dirs)?;
+    let packs = DataPack::list(&cfg, &locator);
+    let path = project_dirs.data_dir().join("worlds").join(&args.world);
+    let save = Save::load(&path).with_context(|| {
+        format!(
+            "Failed to load world '{}'. Worlds have to be created in the game first.",
+            args.world
+        )
+    })?;
+    let PackDbs {
+        tile: tile_db,
+        item: item_db,
+        worldgen,
+        ..
+    } = PackDbs::load(&packs);
+    if let Some(saved_palette) = &save.palette
+        && !saved_palette
+            .remap_to(&Palette::from_dbs(&item_db, &tile_db))
+            .is_identity()
+    {
+        bail!("The tiles changed since the world was last played. Open it in the game first.");
+    }
+    let mut world = World::new(&args.world, path, save.world_seed, worldgen.clone());
+    world.load_gen_stamp(GenVersion::Legacy);
+    if args.upgrade {
+        let problems = validate_worldgen(&worldgen, &tile_db);
+        if !problems.is_empty() {
+            bail!("Invalid world generation data:\n{}", problems.join("\n"));
+        }
+        world.upgrade_gen(worldgen);
+    }
+    eprintln!("Generating with the {} generator", world.gen_version.name());
+    let map = args
+        .map
+        .as_ref()
+        .map(|_| MapImage::new(args.rect, args.map_scale))
+        .transpose()?;
+    let colors = map.as_ref().map(|_| TileColors::new(&tile_db, &packs));
+    let regions: Vec<_> = args.rect.regions().collect();
+    let threads = args
+        .threads
+        .or_else(|| std::thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get);
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let map = Mutex::new(map);
+    std::thread::scope(|s| {
+        for _ in 0..threads.min(regions.len()) {
+            s.spawn(|| {
+                while let Some(&region) = regions.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let chunks = world.pregen_region(region, args.regen);
+                    if let (Some(map), Some(colors)) = (&mut *map.lock().unwrap(), &colors) {
+                        for (pos, chk) in &chunks {
+                            map.draw_chunk(*pos, chk, colors);
+                        }
+                    }
+                    let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                    eprintln!("Region {region:?} done ({done}/{})", regions.len());
+                }
+            });
+        }
+    });
+    if let (Some(map), Some(path)) = (map.into_inner().unwrap(), &args.map) {
+        map.save(path)?;
+        eprintln!("Map saved to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Map of the pregenerated area, one pixel per `scale`x`scale` tiles
+struct MapImage {
+    /// Top left of the area in tiles
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    scale: u32,
+    /// RGBA, row by row
+    pixels: Vec<u8>,
+}
+
+impl MapImage {
+    fn new(rect: RegionRect, scale: u32) -> anyhow::Result<Self> {
+        let scale = scale.max(1);
+        let width = (u32::from(rect.w) * REGION_TILES).div_ceil(scale);
+        let height = (u32::from(rect.h) * REGION_TILES).div_ceil(scale);
+        let n_pixels = u64::from(width) * u64::from(height);
+        if n_pixels > MAX_MAP_PIXELS {
+            bail!(
+                "A {width}x{height} map is too big to save. \
+                 Use a larger --map-scale, or pregenerate a smaller area."
+            );
+        }
+        Ok(Self {
+            x: u32::from(rect.x) * REGION_TILES,
+            y: u32::from(rect.y) * REGION_TILES,
+            width,
+            height,
+            scale,
+            pixels: EMPTY_COLOR.repeat(usize::try_from(n_pixels)?),
+        })
+    }
+    /// Draw the top left tile of every `scale`x`scale` block that falls into the chunk
+    fn draw_chunk(&mut self, pos: ChunkPos, chk: &Chunk, colors: &TileColors) {
+        let ext = u32::from(CHUNK_EXTENT);
+        let (chk_x, chk_y) = (u32::from(pos.x) * ext, u32::from(pos.y) * ext);
+        for (y, row) in (chk_y..).zip(chk.tiles().chunks_exact(usize::from(CHUNK_EXTENT))) {
+            let rel_y = y - self.y;
+            if rel_y % self.scale != 0 {
+                continue;
+            }
+            for (x, &tile) in (chk_x..).zip(row) {
+                let rel_x = x - self.x;
+                if rel_x % self.scale != 0 {
+                    continue;
+                }
+                let idx = u64::from(rel_y / self.scale) * u64::from(self.width)
+                    + u64::from(rel_x / self.scale);
+                let idx = usize::try_from(idx * 4).unwrap();
+                self.pixels[idx..idx + 4].copy_from_slice(&colors.tile(tile));
+            }
+        }
+    }
+    fn save(&self, path: &std::path::Path) -> anyhow::Result<()> {
+        let image = Image::create_from_pixels(self.width, self.height, &self.pixels)
+            .context("Failed to create map image")?;
+        let path_str = path.to_str().context("Map path isn't valid UTF-8")?;
+        image
+            .save_to_file(path_str)
+            .with_context(|| format!("Failed to save map to {}", path.display()))
+    }
+}
+
+#[test]
+fn test_parse_rect() {
+    let rect = parse_rect("120, 40,4,2").unwrap();
+    assert_eq!(
+        rect.regions().collect::<Vec<_>>(),
+        [
+            (120, 40),
+            (121, 40),
+            (122, 40),
+            (123, 40),
+            (120, 41),
+            (121, 41),
+            (122, 41),
+            (123, 41)
+        ]
+    );
+    assert!(parse_rect("1,2,3").is_err());
+    assert!(parse_rect("1,2,0,3").is_err());
+    assert!(parse_rect("240,0,10,1").is_err());
+}
+
+#[test]
+fn test_map_size_limit() {
+    let rect = parse_rect("100,20,64,64").unwrap();
+    assert!(MapImage::new(rect, 1).is_err());
+    let map = MapImage::new(parse_rect("100,20,2,1").unwrap(), 4).unwrap();
+    assert_eq!((map.width, map.height), (512, 256));
+}
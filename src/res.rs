@@ -1,5 +1,8 @@
 use {
-    crate::texture_atlas::AtlasBundle,
+    crate::{
+        data_pack::{res_subdirs, DataPack},
+        texture_atlas::AtlasBundle,
+    },
     egui_sfml::egui::epaint::ahash::HashMap,
     sfml::{
         cpp::FBox,
@@ -12,10 +15,13 @@ pub type AuBuf = Cursor<Vec<u8>>;
 
 pub struct Res {
     pub atlas: AtlasBundle,
-    pub surf_music: AuBuf,
-    pub und_music: AuBuf,
+    /// Music of all packs, by key (e.g. `music/calm`)
+    pub music: HashMap<String, AuBuf>,
     pub sans_font: FBox<Font>,
-    pub forest_bg: FBox<Texture>,
+    /// Images drawn behind the surface, by key (e.g. `bg/sky`)
+    pub backgrounds: HashMap<String, FBox<Texture>>,
+    /// The data packs the resources were loaded from, base game first
+    pub packs: Vec<DataPack>,
 }
 
 pub struct ResAudio {
@@ -28,25 +34,42 @@ pub fn load_sound<P: AsRef<Path>>(path: P) -> anyhow::Result<AuBuf> {
 }
 
 impl Res {
-    pub fn load(res_path: &str) -> anyhow::Result<Self> {
+    pub fn load(packs: Vec<DataPack>) -> anyhow::Result<Self> {
+        let res_path = packs[0].res_dir.display().to_string();
+        let mut music = HashMap::default();
+        for dir in res_subdirs(&packs, "music") {
+            walk_res_dir(&dir, |path| {
+                music.insert(path_key(path), load_sound(path).unwrap());
+            });
+        }
+        let mut backgrounds = HashMap::default();
+        for dir in res_subdirs(&packs, "bg") {
+            walk_res_dir(&dir, |path| {
+                let tex = Texture::from_file(path.to_str().unwrap()).unwrap();
+                backgrounds.insert(path_key(path), tex);
+            });
+        }
         Ok(Self {
-            atlas: AtlasBundle::new(res_path)?,
-            surf_music: load_sound(format!("{res_path}/music/calm.ogg"))?,
-            und_music: load_sound(format!("{res_path}/music/underground.mp3"))?,
+            atlas: AtlasBundle::new(&packs)?,
+            music,
             sans_font: Font::from_file(&format!("{res_path}/fonts/ShareTechMono-Regular.ttf"))
                 .unwrap(),
-            forest_bg: Texture::from_file(&format!("{res_path}/bg/sky.png")).unwrap(),
+            backgrounds,
+            packs,
         })
     }
 }
 
 impl ResAudio {
-    pub fn load(res_path: &str) -> anyhow::Result<Self> {
+    /// Later packs override sounds of earlier packs with the same key
+    pub fn load(packs: &[DataPack]) -> anyhow::Result<Self> {
         let mut map = HashMap::default();
-        walk_res_dir(&format!("{res_path}/sfx"), |path| {
-            let snd = load_sound(path.to_str().unwrap()).unwrap();
-            map.insert(path_key(path), snd);
-        });
+        for dir in res_subdirs(packs, "sfx") {
+            walk_res_dir(&dir, |path| {
+                let snd = load_sound(path.to_str().unwrap()).unwrap();
+                map.insert(path_key(path), snd);
+            });
+        }
         Ok(Self { sounds: map })
     }
 }
@@ -72,7 +95,7 @@ fn test_path_key() {
     );
 }
 
-pub fn walk_res_dir(base: &str, mut f: impl FnMut(&Path)) {
+pub fn walk_res_dir(base: impl AsRef<Path>, mut f: impl FnMut(&Path)) {
     for en in walkdir::WalkDir::new(base) {
         let en = en.unwrap();
         if en.file_type().is_file() {
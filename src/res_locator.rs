@@ -0,0 +1,79 @@
+use {
+    directories::ProjectDirs,
+    std::path::{Path, PathBuf},
+};
+
+/// Environment variable that overrides where the shipped game data is looked for
+pub const DATA_DIR_ENV: &str = "MANTLE_DIVER_DATA_DIR";
+
+/// Finds the game's files independently of the working directory.
+///
+/// Shipped data (`data/` and the resource folder) is treated as read-only.
+/// The in-game editors write their copies to the user data directory instead,
+/// and those copies take precedence over the shipped data when loading.
+pub struct ResLocator {
+    /// Directory containing the shipped `data/` and resource folder
+    pub shipped_dir: PathBuf,
+    /// Per-user data directory. Worlds and data packs live here.
+    pub user_dir: PathBuf,
+}
+
+impl ResLocator {
+    pub fn new(project_dirs: &ProjectDirs) -> anyhow::Result<Self> {
+        let candidates = shipped_dir_candidates(project_dirs);
+        let Some(shipped_dir) = candidates.iter().find(|dir| is_shipped_dir(dir)) else {
+            let searched: Vec<String> = candidates
+                .iter()
+                .map(|dir| dir.display().to_string())
+                .collect();
+            anyhow::bail!(
+                "Couldn't find the game data. Searched:\n{}\n\n\
+                 Set {DATA_DIR_ENV} to the directory containing `data`.",
+                searched.join("\n")
+            );
+        };
+        log::info!("Using game data from {}", shipped_dir.display());
+        Ok(Self {
+            shipped_dir: shipped_dir.clone(),
+            user_dir: project_dirs.data_dir().to_owned(),
+        })
+    }
+    pub fn shipped_data_dir(&self) -> PathBuf {
+        self.shipped_dir.join("data")
+    }
+    /// Where the in-game editors save their copies of the databases
+    pub fn user_data_dir(&self) -> PathBuf {
+        self.user_dir.join("edited")
+    }
+}
+
+/// Candidate directories for the shipped data, in order of preference
+fn shipped_dir_candidates(project_dirs: &ProjectDirs) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(dir) = std::env::var_os(DATA_DIR_ENV) {
+        dirs.push(dir.into());
+    }
+    if let Ok(exe) = std::env::current_exe()
+        && let Some(dir) = exe.parent()
+    {
+        dirs.push(dir.to_owned());
+    }
+    // Running from the source tree with `cargo run`
+    #[cfg(debug_assertions)]
+    dirs.push(env!("CARGO_MANIFEST_DIR").into());
+    if let Ok(dir) = std::env::current_dir() {
+        dirs.push(dir);
+    }
+    dirs.push(project_dirs.data_dir().to_owned());
+    let xdg_data_dirs = std::env::var_os("XDG_DATA_DIRS")
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+    for dir in std::env::split_paths(&xdg_data_dirs) {
+        dirs.push(dir.join("mantle-diver"));
+    }
+    dirs
+}
+
+fn is_shipped_dir(dir: &Path) -> bool {
+    dir.join("data").join("tiles.ron").is_file()
+}
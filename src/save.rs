@@ -1,5 +1,14 @@
 use {
-    crate::{inventory::Inventory, player::Health},
+    crate::{
+        inventory::Inventory,
+        map::Waypoint,
+        placed::{PlacedEnt, Tombstone},
+        player::{Equipment, Health, PlayerColors},
+        status::StatusEffects,
+        survival::Survival,
+        world::TilePos,
+    },
+    mdv_data::palette::Palette,
     serde::{Deserialize, Serialize},
     std::path::{Path, PathBuf},
 };
@@ -11,6 +20,58 @@ pub struct Save {
     pub world_seed: i32,
     pub player: PlayerSav,
     pub world_ticks: u64,
+    /// The item and tile keys the ids in this world were saved with.
+    ///
+    /// Older saves don't have one. Their ids are assumed to match the current databases.
+    #[serde(default)]
+    pub palette: Option<Palette>,
+    /// Objects placed into the world that aren't tiles
+    #[serde(default)]
+    pub placed: Vec<PlacedEnt>,
+    #[serde(default)]
+    pub rules: WorldRules,
+    /// A hardcore world whose player died. It can be looked at, but not played anymore.
+    #[serde(default)]
+    pub finished: bool,
+    /// Items left behind by the player at the places they died
+    #[serde(default)]
+    pub tombstones: Vec<Tombstone>,
+    /// The bed (or other spawn anchor tile) the player respawns at
+    #[serde(default)]
+    pub spawn_anchor: Option<TilePos>,
+    /// Places the player marked on the world map
+    #[serde(default)]
+    pub waypoints: Vec<Waypoint>,
+}
+
+/// What happens to the inventory when the player dies
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeathRule {
+    #[default]
+    KeepInventory,
+    /// Every item goes into a tombstone at the death site
+    DropAll,
+    /// Only currency items go into a tombstone
+    DropCoins,
+}
+
+impl DeathRule {
+    pub const ALL: [Self; 3] = [Self::KeepInventory, Self::DropAll, Self::DropCoins];
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::KeepInventory => "Keep inventory",
+            Self::DropAll => "Drop all items",
+            Self::DropCoins => "Drop coins",
+        }
+    }
+}
+
+/// Rules of a world, chosen when it's created
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug)]
+pub struct WorldRules {
+    pub death: DeathRule,
+    /// The world is finished when the player dies
+    pub hardcore: bool,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -22,6 +83,43 @@ pub struct PlayerSav {
     pub pants_color: Rgb,
     pub shoes_color: Rgb,
     pub health: Health,
+    #[serde(default)]
+    pub equipment: Equipment,
+    #[serde(default)]
+    pub survival: Survival,
+    #[serde(default)]
+    pub effects: StatusEffects,
+}
+
+impl PlayerSav {
+    /// Stand-in for the player of a finished world, who doesn't exist anymore
+    pub(crate) fn finished() -> Self {
+        let mut sav = Self::respawning(Equipment::default());
+        sav.health.current = 0.;
+        sav
+    }
+
+    /// The player that a dead player respawns as, wearing `equipment`
+    pub(crate) fn respawning(equipment: Equipment) -> Self {
+        let colors = PlayerColors::default();
+        Self {
+            skin_color: Rgb::from_sf(colors.skin),
+            eye_color: Rgb::from_sf(colors.eye),
+            hair_color: Rgb::from_sf(colors.hair),
+            shirt_color: Rgb::from_sf(colors.shirt),
+            pants_color: Rgb::from_sf(colors.pants),
+            shoes_color: Rgb::from_sf(colors.shoes),
+            health: Health {
+                current: 100.,
+                max: 100.,
+                invulnerable: false,
+                iframes: 0,
+            },
+            equipment,
+            survival: Survival::default(),
+            effects: StatusEffects::default(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
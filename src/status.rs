@@ -0,0 +1,130 @@
+use {
+    crate::math::FPS_TARGET,
+    mdv_data::item::{EffectKind, StatusEffect},
+    serde::{Deserialize, Serialize},
+};
+
+/// What happens when an effect is applied to an entity that already has it
+enum Stacking {
+    /// Keep the stronger strength and the longer duration
+    Refresh,
+    /// Strengths add up, and the longer duration is kept
+    Intensify,
+}
+
+fn stacking(kind: EffectKind) -> Stacking {
+    match kind {
+        EffectKind::Poison => Stacking::Intensify,
+        EffectKind::Burning | EffectKind::Regeneration | EffectKind::Speed => Stacking::Refresh,
+    }
+}
+
+/// Name of the graphic shown in the HUD for `kind`
+pub fn icon_graphic(kind: EffectKind) -> &'static str {
+    match kind {
+        EffectKind::Poison => "ui/effects/poison",
+        EffectKind::Burning => "ui/effects/burning",
+        EffectKind::Regeneration => "ui/effects/regeneration",
+        EffectKind::Speed => "ui/effects/speed",
+    }
+}
+
+/// Light emitted by a burning entity
+const BURNING_LIGHT: u8 = 200;
+
+/// The timed status effects an entity is under.
+///
+/// Each kind of effect is present at most once. `duration` counts the ticks left.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    pub fn apply(&mut self, new: StatusEffect) {
+        let Some(old) = self.effects.iter_mut().find(|eff| eff.kind == new.kind) else {
+            self.effects.push(new);
+            return;
+        };
+        match stacking(new.kind) {
+            Stacking::Refresh => old.strength = old.strength.max(new.strength),
+            Stacking::Intensify => old.strength += new.strength,
+        }
+        old.duration = old.duration.max(new.duration);
+    }
+    /// Count down the effects and drop the expired ones.
+    ///
+    /// Returns how much health the entity gains this tick. Negative means damage.
+    pub fn tick(&mut self) -> f32 {
+        let mut health = 0.0;
+        for eff in &mut self.effects {
+            let per_tick = eff.strength / f32::from(FPS_TARGET);
+            match eff.kind {
+                EffectKind::Poison | EffectKind::Burning => health -= per_tick,
+                EffectKind::Regeneration => health += per_tick,
+                EffectKind::Speed => {}
+            }
+            eff.duration = eff.duration.saturating_sub(1);
+        }
+        self.effects.retain(|eff| eff.duration > 0);
+        health
+    }
+    /// Multiplier of the movement speed
+    pub fn speed_mul(&self) -> f32 {
+        let extra: f32 = self
+            .effects
+            .iter()
+            .filter(|eff| eff.kind == EffectKind::Speed)
+            .map(|eff| eff.strength)
+            .sum();
+        (1.0 + extra).max(0.1)
+    }
+    /// Light emitted by the entity because of its effects (0-255)
+    pub fn light(&self) -> u8 {
+        if self
+            .effects
+            .iter()
+            .any(|eff| eff.kind == EffectKind::Burning)
+        {
+            BURNING_LIGHT
+        } else {
+            0
+        }
+    }
+}
+
+#[test]
+fn test_stacking() {
+    let mut effects = StatusEffects::default();
+    let poison = StatusEffect {
+        kind: EffectKind::Poison,
+        strength: 2.0,
+        duration: 100,
+    };
+    let speed = StatusEffect {
+        kind: EffectKind::Speed,
+        strength: 0.5,
+        duration: 100,
+    };
+    effects.apply(poison);
+    effects.apply(StatusEffect {
+        duration: 50,
+        ..poison
+    });
+    effects.apply(speed);
+    effects.apply(StatusEffect {
+        strength: 0.2,
+        duration: 300,
+        ..speed
+    });
+    assert_eq!(effects.effects.len(), 2);
+    assert_eq!(effects.effects[0].strength, 4.0);
+    assert_eq!(effects.effects[0].duration, 100);
+    assert_eq!(effects.effects[1].strength, 0.5);
+    assert_eq!(effects.effects[1].duration, 300);
+    assert!(effects.tick() < 0.0);
+    for _ in 0..300 {
+        effects.tick();
+    }
+    assert!(effects.effects.is_empty());
+}
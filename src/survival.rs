@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+
+/// A resource that drains over time, like food or breath
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Vital {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Vital {
+    const fn full(max: f32) -> Self {
+        Self { current: max, max }
+    }
+    pub fn is_depleted(&self) -> bool {
+        self.current <= 0.0
+    }
+    /// How full this vital is, between 0 and 1
+    pub fn fraction(&self) -> f32 {
+        if self.max <= 0.0 {
+            return 0.0;
+        }
+        (self.current / self.max).clamp(0.0, 1.0)
+    }
+    /// Add `amount` (or remove, if negative), staying between 0 and `max`
+    pub fn change(&mut self, amount: f32) {
+        self.current = (self.current + amount).clamp(0.0, self.max);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VitalKind {
+    Food,
+    Stamina,
+    Breath,
+}
+
+impl VitalKind {
+    pub const ALL: [Self; 3] = [Self::Food, Self::Stamina, Self::Breath];
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Food => "Food",
+            Self::Stamina => "Stamina",
+            Self::Breath => "Breath",
+        }
+    }
+}
+
+/// What an entity is doing, which decides how its vitals drain
+pub struct Conditions {
+    /// How deep below the surface the entity is, in meters
+    pub depth_m: f32,
+    pub sprinting: bool,
+}
+
+/// How a vital changes every tick
+struct DrainRule {
+    kind: VitalKind,
+    /// How much the vital drains this tick. Negative values regenerate it.
+    drain: fn(&Conditions) -> f32,
+    /// Damage taken every tick while the vital is depleted
+    depleted_damage: f32,
+}
+
+/// Air starts getting thin below this depth
+const BREATH_DEPTH_M: f32 = 1000.0;
+
+const RULES: [DrainRule; 3] = [
+    // A full stomach lasts about 20 minutes
+    DrainRule {
+        kind: VitalKind::Food,
+        drain: |_| 100.0 / (20.0 * 60.0 * 60.0),
+        depleted_damage: 0.02,
+    },
+    DrainRule {
+        kind: VitalKind::Stamina,
+        drain: |cond| if cond.sprinting { 0.2 } else { -0.3 },
+        depleted_damage: 0.0,
+    },
+    // The deeper, the faster we run out of breath
+    DrainRule {
+        kind: VitalKind::Breath,
+        drain: |cond| {
+            if cond.depth_m > BREATH_DEPTH_M {
+                0.005 * cond.depth_m / 1000.0
+            } else {
+                -1.0
+            }
+        },
+        depleted_damage: 0.2,
+    },
+];
+
+/// Survival stats of an entity
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Survival {
+    pub vitals: [Vital; VitalKind::ALL.len()],
+}
+
+impl Default for Survival {
+    fn default() -> Self {
+        Self {
+            vitals: [Vital::full(100.0); VitalKind::ALL.len()],
+        }
+    }
+}
+
+impl Survival {
+    pub fn get(&self, kind: VitalKind) -> &Vital {
+        &self.vitals[kind as usize]
+    }
+    pub fn get_mut(&mut self, kind: VitalKind) -> &mut Vital {
+        &mut self.vitals[kind as usize]
+    }
+    /// Apply the drain rules for one tick.
+    ///
+    /// Returns how much damage the entity takes from depleted vitals.
+    pub fn tick(&mut self, cond: &Conditions) -> f32 {
+        let mut damage = 0.0;
+        for rule in &RULES {
+            let vital = self.get_mut(rule.kind);
+            vital.change(-(rule.drain)(cond));
+            if vital.is_depleted() {
+                damage += rule.depleted_damage;
+            }
+        }
+        damage
+    }
+}
+
+#[test]
+fn test_breath_drains_only_deep() {
+    let mut surv = Survival::default();
+    let shallow = Conditions {
+        depth_m: 10.0,
+        sprinting: false,
+    };
+    let deep = Conditions {
+        depth_m: 20_000.0,
+        sprinting: false,
+    };
+    surv.tick(&shallow);
+    assert_eq!(surv.get(VitalKind::Breath).current, 100.0);
+    surv.tick(&deep);
+    assert!(surv.get(VitalKind::Breath).current < 100.0);
+    surv.get_mut(VitalKind::Breath).current = 0.0;
+    assert!(surv.tick(&deep) > 0.0);
+}
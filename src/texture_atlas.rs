@@ -1,4 +1,5 @@
 use {
+    crate::data_pack::{res_subdirs, DataPack},
     mdv_math::types::IntRect,
     sfml::{cpp::FBox, graphics::Texture},
     stampcollect_sfml::SfmlAtlasBuilder,
@@ -15,13 +16,12 @@ pub struct AtlasBundle {
 }
 
 impl AtlasBundle {
-    pub fn new(res_path: &str) -> anyhow::Result<Self> {
+    /// Later packs override graphics of earlier packs with the same name
+    pub fn new(packs: &[DataPack]) -> anyhow::Result<Self> {
         let mut atlas_builder = SfmlAtlasBuilder::with_size(4096, 4096)?;
         let mut rects = HashMap::new();
-        stampcollect_sfml::collect(
-            format!("{res_path}/graphics"),
-            &mut atlas_builder,
-            |name, rect| {
+        for dir in res_subdirs(packs, "graphics") {
+            stampcollect_sfml::collect(&dir, &mut atlas_builder, |name, rect| {
                 rects.insert(
                     dbg!(name),
                     IntRect {
@@ -31,8 +31,8 @@ impl AtlasBundle {
                         h: i32::from(rect.h),
                     },
                 );
-            },
-        );
+            });
+        }
         Ok(AtlasBundle {
             tex: atlas_builder.into_texture(),
             rects,
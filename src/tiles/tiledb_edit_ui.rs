@@ -9,7 +9,9 @@ use {
     egui_sfml::egui,
     mdv_data::{
         item::ItemId,
-        tile::{Bg, BgTileId, Mid, MidTileId, TileDb, TileDef, TileItemDrop, TileLayer},
+        tile::{
+            Bg, BgTileId, ContainerDef, Mid, MidTileId, TileDb, TileDef, TileItemDrop, TileLayer,
+        },
     },
     mdv_math::types::{ScreenSc, ScreenVec},
     std::fmt::Debug,
@@ -117,6 +119,26 @@ impl TileLayerExt for Mid {
             }
         }
         ui.checkbox(&mut def.platform, "platform");
+        ui.checkbox(&mut def.spawn_anchor, "spawn anchor (bed)");
+        ui.checkbox(&mut def.tree, "part of a tree");
+        match &mut def.container {
+            Some(container) => {
+                let mut remove = false;
+                ui.horizontal(|ui| {
+                    ui.label("Container slots");
+                    ui.add(egui::DragValue::new(&mut container.slots));
+                    remove = ui.button("Remove").clicked();
+                });
+                if remove {
+                    def.container = None;
+                }
+            }
+            None => {
+                if ui.button("Make container").clicked() {
+                    def.container = Some(ContainerDef { slots: 20 });
+                }
+            }
+        }
     }
 
     fn paint(idx: usize, cmd: &mut CmdVec) {
@@ -150,10 +172,7 @@ fn db_ui<Layer: TileLayer + TileLayerExt + Debug>(
                 .show(ui, |ui| {
                     for (i, def) in db.iter().enumerate() {
                         if ui
-                            .selectable_label(
-                                *sel_idx == i,
-                                format!("{} {}", i + 1, def.graphic_name),
-                            )
+                            .selectable_label(*sel_idx == i, format!("{} {}", i + 1, def.key))
                             .clicked()
                         {
                             *sel_idx = i;
@@ -164,6 +183,10 @@ fn db_ui<Layer: TileLayer + TileLayerExt + Debug>(
         ui.separator();
         ui.vertical(|ui| {
             if let Some(def) = db.get_mut(*sel_idx) {
+                ui.horizontal(|ui| {
+                    ui.label("Key");
+                    ui.text_edit_singleline(&mut def.key);
+                });
                 ui.horizontal(|ui| {
                     if ui.graphic_image_button(&def.tex_rect, atlas_size).clicked() {
                         graphic_picker.open = true;
@@ -219,6 +242,10 @@ fn db_ui<Layer: TileLayer + TileLayerExt + Debug>(
                     ui.label("Health");
                     ui.add(egui::DragValue::new(&mut def.health));
                 });
+                ui.horizontal(|ui| {
+                    ui.label("Min tool tier");
+                    ui.add(egui::DragValue::new(&mut def.min_tier));
+                });
                 ui.horizontal(|ui| match &mut def.item_drop {
                     Some(drop) => {
                         egui::ComboBox::new("itemdrop_combo", "Item drop")
@@ -262,6 +289,16 @@ fn db_ui<Layer: TileLayer + TileLayerExt + Debug>(
                     }
                 }
                 ui.checkbox(&mut def.uprootable, "Uprootable");
+                ui.checkbox(&mut def.unbreakable, "Unbreakable");
+                ui.horizontal(|ui| {
+                    let mut custom = def.map_color.is_some();
+                    if ui.checkbox(&mut custom, "Map color").changed() {
+                        def.map_color = custom.then_some([128, 128, 128]);
+                    }
+                    if let Some(color) = &mut def.map_color {
+                        ui.color_edit_button_srgb(color);
+                    }
+                });
                 Layer::special_ui(&mut def.layer, ui);
                 ui.label("O to paint tile at cursor");
                 if ui.input(|inp| inp.key_down(egui::Key::O)) {
@@ -1,19 +1,31 @@
 use {
     crate::{
-        math::{WorldPos, WorldRect, TILE_SIZE},
+        inventory::Inventory,
+        math::{WorldPos, WorldRect, TILE_SIZE, WORLD_EXTENT},
         time::HOUR_IN_TICKS,
     },
-    mdv_data::tile::{BgTileId, MidTileId, TileId},
+    mdv_data::{
+        item::ItemStack,
+        palette::Remap,
+        tile::{BgTileId, MidTileId, TileDb, TileId},
+        worldgen::WorldGenDb,
+    },
+    serde::{Deserialize, Serialize},
 };
 
+mod explored;
 mod gen;
 mod reg_chunk_existence;
 mod serialization;
 
+pub use self::{explored::Explored, gen::GenVersion};
 use {
-    self::serialization::save_chunk,
+    self::serialization::{
+        load_chunk_extra, load_explored_chunks, remap_region_files, save_chunk, save_region_chunks,
+    },
     crate::world::reg_chunk_existence::ExistenceBitset,
     std::{
+        collections::BTreeMap,
         fmt::Debug,
         fs::File,
         io::Seek,
@@ -75,10 +87,16 @@ pub struct World {
     pub name: String,
     pub path: PathBuf,
     pub seed: i32,
+    /// The generator new chunks are generated with
+    pub gen_version: GenVersion,
+    /// Parameters for generating new chunks
+    pub worldgen: WorldGenDb,
+    /// Chunks with tiles changed since the last [`Self::take_changed_chunks`]
+    changed_chunks: Vec<ChunkPos>,
 }
 
 impl World {
-    pub fn new(name: &str, path: PathBuf, seed: i32) -> Self {
+    pub fn new(name: &str, path: PathBuf, seed: i32, worldgen: WorldGenDb) -> Self {
         // Ensure world dir exists, as chunks could be saved at any time during gameplay
         std::fs::create_dir_all(&path).unwrap();
         Self {
@@ -87,6 +105,57 @@ impl World {
             name: name.to_string(),
             path,
             seed,
+            gen_version: GenVersion::LATEST,
+            worldgen,
+            changed_chunks: Vec::new(),
+        }
+    }
+    /// Generate new chunks with the generator version and parameters saved with the world.
+    ///
+    /// A world that doesn't have them saved yet gets `fallback` and its current parameters.
+    pub fn load_gen_stamp(&mut self, fallback: GenVersion) {
+        match gen::load_stamp(&self.path) {
+            Ok(Some((version, worldgen))) => {
+                self.gen_version = version;
+                self.worldgen = worldgen;
+            }
+            Ok(None) => {
+                self.gen_version = fallback;
+                self.save_gen_stamp();
+            }
+            Err(e) => {
+                log::error!("Failed to load world generator, generating with {fallback:?}: {e:#}");
+                self.gen_version = fallback;
+            }
+        }
+    }
+    /// Generate the chunks that don't exist yet with the latest generator and `worldgen`.
+    ///
+    /// Existing chunks stay as they are, so there will be seams where old and new chunks meet.
+    pub fn upgrade_gen(&mut self, worldgen: WorldGenDb) {
+        self.gen_version = GenVersion::LATEST;
+        self.worldgen = worldgen;
+        self.save_gen_stamp();
+    }
+    fn save_gen_stamp(&self) {
+        if let Err(e) = gen::save_stamp(&self.path, self.gen_version, &self.worldgen) {
+            log::error!("Failed to save world generator: {e:#}");
+        }
+    }
+    /// Index of the surface biome in [`WorldGenDb::biomes`] at `pos`, if there are any biomes
+    pub fn biome_at(&self, pos: TilePos) -> Option<usize> {
+        match self.gen_version {
+            GenVersion::Legacy => None,
+            GenVersion::Layered => {
+                gen::biome_idx_at(&self.worldgen, self.seed.cast_unsigned(), pos.x, pos.y)
+            }
+        }
+    }
+    /// The topmost tile row of the ground in column `x`, before anything was dug out
+    pub fn surface_at(&self, x: TPosSc) -> TPosSc {
+        match self.gen_version {
+            GenVersion::Legacy => gen::legacy_surface_y(self.seed, x),
+            GenVersion::Layered => gen::surface_y(&self.worldgen, self.seed.cast_unsigned(), x),
         }
     }
     /// Get mutable access to the tile at `pos`.
@@ -94,19 +163,120 @@ impl World {
     /// Loads or generates the containing chunk if necessary.
     pub fn tile_at_mut(&mut self, pos: TilePos) -> &mut Tile {
         let (chk, local) = pos.to_chunk_and_local();
-        let chk = match self.chunks.iter().position(|(p, _)| *p == chk) {
+        self.chunk_at_mut(chk).at_mut(local)
+    }
+    /// Get mutable access to the inventory of the container tile at `pos`.
+    ///
+    /// Returns `None` if the tile isn't a container.
+    /// Containers without an inventory yet (e.g. freshly placed) get an empty one.
+    pub fn container_at_mut(&mut self, pos: TilePos, tile_db: &TileDb) -> Option<&mut Inventory> {
+        let (chk, local) = pos.to_chunk_and_local();
+        let chk = self.chunk_at_mut(chk);
+        let mid = chk.at_mut(local).mid;
+        if mid.empty() {
+            return None;
+        }
+        let container = tile_db[mid].layer.container.as_ref()?;
+        let slots = usize::from(container.slots);
+        let inv = chk
+            .containers
+            .entry(local)
+            .or_insert_with(|| Inventory::with_slots(slots));
+        // Containers of prefabs only hold the items they were filled with
+        if inv.slots.len() < slots {
+            inv.slots.resize(slots, ItemStack::empty());
+        }
+        Some(inv)
+    }
+    /// The inventory of the container at `pos`, if its chunk is loaded
+    pub fn container_at(&self, pos: TilePos) -> Option<&Inventory> {
+        let (chk, local) = pos.to_chunk_and_local();
+        self.chunks
+            .iter()
+            .find(|(p, _)| *p == chk)
+            .and_then(|(_, chk)| chk.containers.get(&local))
+    }
+    /// Remove the inventory of the container at `pos`, e.g. because it was destroyed
+    pub fn remove_container(&mut self, pos: TilePos) -> Option<Inventory> {
+        let (chk, local) = pos.to_chunk_and_local();
+        self.chunk_at_mut(chk).containers.remove(&local)
+    }
+    /// The chunk at `pos`, if it's loaded
+    pub fn loaded_chunk(&self, pos: ChunkPos) -> Option<&Chunk> {
+        self.chunks
+            .iter()
+            .find_map(|(p, chk)| (*p == pos).then_some(chk))
+    }
+    /// The saved chunks of `region` that have explored tiles, as they were last saved
+    pub fn saved_explored_chunks(&self, region: (u8, u8)) -> Vec<(ChunkPos, Chunk)> {
+        load_explored_chunks(&self.path, region)
+    }
+    /// Mark the tiles within `radius` tiles of `center` as seen by the player.
+    ///
+    /// Returns the chunks that had tiles in the circle that weren't seen yet.
+    pub fn explore(&mut self, center: TilePos, radius: u16) -> Vec<ChunkPos> {
+        let r = TPosSc::from(radius);
+        let mut newly_explored = Vec::new();
+        for y in center.y.saturating_sub(r)..=(center.y + r).min(WORLD_EXTENT - 1) {
+            for x in center.x.saturating_sub(r)..=(center.x + r).min(WORLD_EXTENT - 1) {
+                let (dx, dy) = (x.abs_diff(center.x), y.abs_diff(center.y));
+                if dx * dx + dy * dy > r * r {
+                    continue;
+                }
+                let (chk, local) = TilePos { x, y }.to_chunk_and_local();
+                if self.chunk_at_mut(chk).explored.set(local.idx())
+                    && !newly_explored.contains(&chk)
+                {
+                    newly_explored.push(chk);
+                }
+            }
+        }
+        newly_explored
+    }
+    /// Note that the tile at `pos` changed, so the map can redraw its chunk
+    pub fn mark_changed(&mut self, pos: TilePos) {
+        let chk = pos.to_chunk();
+        if !self.changed_chunks.contains(&chk) {
+            self.changed_chunks.push(chk);
+        }
+    }
+    /// The chunks with tiles changed since the last call
+    pub fn take_changed_chunks(&mut self) -> Vec<ChunkPos> {
+        std::mem::take(&mut self.changed_chunks)
+    }
+    /// Get mutable access to the chunk at `pos`, loading or generating it if necessary
+    fn chunk_at_mut(&mut self, pos: ChunkPos) -> &mut Chunk {
+        match self.chunks.iter().position(|(p, _)| *p == pos) {
             Some(idx) => &mut self.chunks[idx].1,
             None => {
-                self.chunks
-                    .push((chk, Chunk::load_or_gen(chk, &self.path, self.seed)));
+                self.chunks.push((
+                    pos,
+                    Chunk::load_or_gen(
+                        pos,
+                        &self.path,
+                        self.seed,
+                        self.gen_version,
+                        &self.worldgen,
+                    ),
+                ));
                 &mut self.chunks.last_mut().unwrap().1
             }
-        };
-        chk.at_mut(local)
+        }
     }
     pub fn save(&self) {
         self.save_chunks();
     }
+    /// Remap the tile ids of all the saved chunks of this world,
+    /// and of the generator parameters saved with it.
+    ///
+    /// Must be called before any chunks are loaded, and before [`Self::load_gen_stamp`].
+    pub fn remap_tiles(&mut self, remap: &Remap) {
+        assert!(self.chunks.is_empty());
+        remap_region_files(&self.path, remap);
+        if let Err(e) = gen::remap_stamp(&self.path, remap) {
+            log::error!("Failed to remap world generator: {e:#}");
+        }
+    }
     pub fn save_chunks(&self) {
         for (pos, chk) in self.chunks.iter() {
             save_chunk(pos, chk, &self.path);
@@ -118,6 +288,40 @@ impl World {
             save_chunk(&pos, &chk, &self.path);
         }
     }
+    /// Generate the chunks of `region` that don't exist yet, save them, and return all the
+    /// chunks of the region. With `regen`, the existing chunks are generated again too.
+    ///
+    /// Leaves the loaded chunks alone, so it's only for worlds that aren't being played.
+    pub fn pregen_region(&self, region: (u8, u8), regen: bool) -> Vec<(ChunkPos, Chunk)> {
+        let ext = ChkPosSc::from(REGION_CHUNK_EXTENT);
+        let (reg_x, reg_y) = (
+            ChkPosSc::from(region.0) * ext,
+            ChkPosSc::from(region.1) * ext,
+        );
+        let reg_path = self.path.join(format_reg_file_name(region));
+        let chunks: Vec<_> = (0..ext)
+            .flat_map(|y| {
+                (0..ext).map(move |x| ChunkPos {
+                    x: reg_x + x,
+                    y: reg_y + y,
+                })
+            })
+            .map(|pos| {
+                let chk = if !regen && chunk_exists(&reg_path, pos) {
+                    Chunk::load_or_gen(pos, &self.path, self.seed, self.gen_version, &self.worldgen)
+                } else {
+                    Chunk::gen(pos, self.seed, self.gen_version, &self.worldgen)
+                };
+                (pos, chk)
+            })
+            .collect();
+        save_region_chunks(
+            &self.path,
+            region,
+            chunks.iter().map(|(pos, chk)| (pos, chk)),
+        );
+        chunks
+    }
 }
 
 const MAX_LOADED_CHUNKS: usize = 16;
@@ -138,21 +342,33 @@ fn format_reg_file_name((x, y): (u8, u8)) -> String {
     format!("{x}.{y}.rgn")
 }
 
+/// Region file for the variable sized data, like container inventories
+fn format_reg_extra_file_name((x, y): (u8, u8)) -> String {
+    format!("{x}.{y}.rgx")
+}
+
 const CHUNK_BYTES: usize = CHUNK_N_TILES * TILE_BYTES;
 const TILE_BYTES: usize = 2 * 2;
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TilePos {
     pub x: TPosSc,
     pub y: TPosSc,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
 pub struct ChunkLocalTilePos {
     pub x: ChkLocalTPosSc,
     pub y: ChkLocalTPosSc,
 }
 
+impl ChunkLocalTilePos {
+    /// Index of the tile in the tiles of its chunk
+    pub fn idx(self) -> usize {
+        usize::from(CHUNK_EXTENT) * usize::from(self.y) + usize::from(self.x)
+    }
+}
+
 /// Chunk-local tile position scalar. Supports up to 256 tiles per chunk.
 type ChkLocalTPosSc = u8;
 
@@ -173,17 +389,19 @@ impl TilePos {
         self.to_chunk_and_local().0
     }
 
+    /// The tile `off` tiles to the side, staying inside of the world
     pub(crate) fn x_off(&self, off: i32) -> Self {
         Self {
-            x: self.x.saturating_add_signed(off),
+            x: self.x.saturating_add_signed(off).min(WORLD_EXTENT - 1),
             y: self.y,
         }
     }
 
+    /// The tile `off` tiles down, staying inside of the world
     pub(crate) fn y_off(&self, off: i32) -> TilePos {
         Self {
             x: self.x,
-            y: self.y.saturating_add_signed(off),
+            y: self.y.saturating_add_signed(off).min(WORLD_EXTENT - 1),
         }
     }
 
@@ -252,6 +470,18 @@ fn test_chunk_local() {
     assert_eq!(chunk_local(0), 0);
 }
 
+#[test]
+fn test_tile_pos_off() {
+    let edge = TilePos {
+        x: WORLD_EXTENT - 1,
+        y: 0,
+    };
+    assert_eq!(edge.x_off(1), edge);
+    assert_eq!(edge.x_off(-1).x, WORLD_EXTENT - 2);
+    assert_eq!(edge.y_off(-1), edge);
+    assert_eq!(edge.y_off(i32::MAX).y, WORLD_EXTENT - 1);
+}
+
 #[test]
 fn test_to_chunk_and_local() {
     assert_eq!(
@@ -286,13 +516,24 @@ fn default_chunk_tiles() -> ChunkTiles {
     }; CHUNK_N_TILES]
 }
 
+/// The inventories of the container tiles of a chunk
+pub type ChunkContainers = BTreeMap<ChunkLocalTilePos, Inventory>;
+
 #[derive(Debug)]
 pub struct Chunk {
     tiles: ChunkTiles,
+    containers: ChunkContainers,
+    explored: Explored,
 }
 
 impl Chunk {
-    pub fn load_or_gen(chk: ChunkPos, world_path: &Path, seed: i32) -> Chunk {
+    pub fn load_or_gen(
+        chk: ChunkPos,
+        world_path: &Path,
+        seed: i32,
+        gen_version: GenVersion,
+        worldgen: &WorldGenDb,
+    ) -> Chunk {
         log::info!("Loading chunk {chk:?} (reg: {:?})", chk.region());
         let reg_filename = world_path.join(format_reg_file_name(chk.region()));
         if chunk_exists(&reg_filename, chk) {
@@ -306,18 +547,32 @@ impl Chunk {
                 log::error!("Decompressed data length different than REGION_BYTES");
                 return Self {
                     tiles: default_chunk_tiles(),
+                    containers: ChunkContainers::new(),
+                    explored: Explored::default(),
                 };
             }
             let local_pos = chk.local();
-            Chunk::load_from_region(&decomp_data, local_pos.0, local_pos.1)
+            let mut chunk = Chunk::load_from_region(&decomp_data, local_pos.0, local_pos.1);
+            (chunk.containers, chunk.explored) = load_chunk_extra(world_path, chk);
+            chunk
         } else {
             log::warn!("Chunk at {:?} doesn't exist, generating.", chk);
-            Chunk::gen(chk, seed)
+            Chunk::gen(chk, seed, gen_version, worldgen)
         }
     }
 
+    /// The tiles of the chunk, row by row
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    /// The tiles of the chunk the player has seen
+    pub fn explored(&self) -> &Explored {
+        &self.explored
+    }
+
     fn at_mut(&mut self, local: ChunkLocalTilePos) -> &mut Tile {
-        &mut self.tiles[CHUNK_EXTENT as usize * local.y as usize + local.x as usize]
+        &mut self.tiles[local.idx()]
     }
 }
 
@@ -0,0 +1,44 @@
+use {
+    super::CHUNK_N_TILES,
+    mdv_math::bitmanip::{nth_bit_set, set_nth_bit},
+    serde::{Deserialize, Serialize},
+};
+
+/// Which tiles of a chunk the player has seen, one bit per tile, row by row.
+///
+/// Empty until the first tile is explored, so unexplored chunks don't take any space.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct Explored(Vec<u64>);
+
+impl Explored {
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|&bits| bits == 0)
+    }
+    /// Whether the tile at index `idx` of the chunk has been seen
+    pub fn get(&self, idx: usize) -> bool {
+        self.0
+            .get(idx / 64)
+            .is_some_and(|&bits| nth_bit_set(bits, idx % 64))
+    }
+    /// Mark the tile at index `idx` of the chunk as seen. Returns whether it wasn't yet.
+    pub fn set(&mut self, idx: usize) -> bool {
+        if self.get(idx) {
+            return false;
+        }
+        self.0.resize(CHUNK_N_TILES / 64, 0);
+        set_nth_bit(&mut self.0[idx / 64], idx % 64, true);
+        true
+    }
+}
+
+#[test]
+fn test_explored() {
+    let mut explored = Explored::default();
+    assert!(explored.is_empty());
+    assert!(!explored.get(CHUNK_N_TILES - 1));
+    assert!(explored.set(130));
+    assert!(!explored.set(130));
+    assert!(explored.get(130));
+    assert!(!explored.get(129));
+    assert!(!explored.is_empty());
+}
@@ -1,81 +1,309 @@
 use {
-    super::{Chunk, ChunkPos},
+    super::{Chunk, ChunkContainers, ChunkPos, ChunkTiles, Explored, TPosSc, Tile},
     crate::{
-        data,
-        math::{WorldPos, TILE_SIZE},
-        world::{default_chunk_tiles, CHUNK_EXTENT, CHUNK_N_TILES},
+        math::{WorldPos, PX_PER_M, TILE_SIZE, WORLD_EXTENT},
+        world::{default_chunk_tiles, CHUNK_EXTENT},
     },
-    simdnoise::NoiseBuilder,
+    anyhow::Context,
+    mdv_data::{
+        palette::Remap,
+        prefab::PrefabDef,
+        worldgen::{BiomeDef, WorldGenDb},
+    },
+    mdv_math::noise::hash,
+    serde::{Deserialize, Serialize},
+    std::path::Path,
 };
 
+mod caves;
+mod decorations;
+mod legacy;
+mod prefabs;
+
+/// Salts for the seeds of the boundary noises, so they don't match any deposit noise
+const BLEND_SALT: u32 = 0xb1e4d;
+const BIOME_BLEND_SALT: u32 = 0xb10e;
+/// Salt for the seed of the hills
+const TERRAIN_SALT: u32 = 0x4111;
+/// Salt for the seed of the ragged edges of the bedrock
+const BEDROCK_SALT: u32 = 0xbed;
+/// Directory in the world directory with the generator version and parameters of the world
+const STAMP_DIR: &str = "worldgen";
+/// File in [`STAMP_DIR`] with the [`GenVersion`]
+const VERSION_FILE: &str = "version.ron";
+
+/// Versions of the world generator.
+///
+/// A world generates its new chunks with the version it was created with, so they line up
+/// with the chunks it already has. Add a new version instead of changing an existing one.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GenVersion {
+    /// Dirt and stone from noise, from before generators had versions.
+    /// Doesn't use the world generation data.
+    Legacy,
+    /// Strata, biomes, caves, decorations, prefabs and bedrock from the world generation data
+    Layered,
+}
+
+impl GenVersion {
+    pub const ALL: [Self; 2] = [Self::Legacy, Self::Layered];
+    /// The version new worlds are created with
+    pub const LATEST: Self = Self::Layered;
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Legacy => "Legacy",
+            Self::Layered => "Layered",
+        }
+    }
+}
+
+impl Default for GenVersion {
+    fn default() -> Self {
+        Self::LATEST
+    }
+}
+
 impl Chunk {
-    pub fn gen(pos: ChunkPos, seed: i32) -> Self {
-        let mut tiles = default_chunk_tiles();
-        let x = u32::from(pos.x) * u32::from(CHUNK_EXTENT);
-        let y = u32::from(pos.y) * u32::from(CHUNK_EXTENT);
-        let noise = NoiseBuilder::gradient_2d_offset(
-            x as f32,
-            CHUNK_EXTENT as usize,
-            y as f32,
-            CHUNK_EXTENT as usize,
-        )
-        .with_seed(seed)
-        .generate_scaled(0.0, 1000.0);
-        let hnoise = NoiseBuilder::gradient_1d_offset(x as f32, CHUNK_EXTENT as usize)
-            .with_seed(seed)
-            .generate_scaled(-10., 10.);
-        // TODO: Take care to generate all chunks with same seed on same world
-        assert!(noise.len() == CHUNK_N_TILES);
-        for (i, (t, noise)) in tiles.iter_mut().zip(noise).enumerate() {
-            #[expect(
-                clippy::cast_possible_truncation,
-                reason = "We aren't iterating through this many tiles"
-            )]
-            let i = i as u32;
-            let y = y + i / u32::from(CHUNK_EXTENT);
-            let local_x = i % u32::from(CHUNK_EXTENT);
-            let surf = WorldPos::SURFACE / u32::from(TILE_SIZE);
-            #[expect(clippy::cast_possible_truncation, reason = "Scaled noise")]
-            let ceil = surf.saturating_add_signed(hnoise[local_x as usize] as i32 / 4);
-            #[expect(clippy::cast_possible_truncation, reason = "Scaled noise")]
-            if y == ceil - 1 {
-                if noise as i32 % 19 == 0 {
-                    t.mid = data::tile::mid::TILES_TREE;
-                } else if noise as i32 % 17 == 0 {
-                    t.mid = data::tile::mid::TILES_SMALLROCK;
-                } else if noise as i32 % 15 == 0 {
-                    t.mid = data::tile::mid::TILES_STICK;
-                }
-            }
-            if y < ceil {
-                continue;
-            }
-            // Dirt level, just a mass of mostly dirt
-            let dirt_bottom = surf + 80;
-            #[expect(clippy::cast_possible_truncation, reason = "Scaled noise")]
-            if y < dirt_bottom.saturating_add_signed(hnoise[local_x as usize] as i32) {
-                t.mid = data::tile::mid::TILES_DIRT;
-                t.bg = data::tile::bg::TILES_DIRTBACK;
-                if y == ceil {
-                    //t.fg = FgTileId::GRASS; // Removed for now
-                } else if y > ceil + 2 && noise as i32 % 37 == 0 {
-                    t.mid = data::tile::mid::TILES_DIRT_COAL;
+    pub fn gen(pos: ChunkPos, seed: i32, version: GenVersion, worldgen: &WorldGenDb) -> Self {
+        match version {
+            GenVersion::Legacy => Self {
+                tiles: legacy::gen(pos, seed),
+                containers: ChunkContainers::new(),
+                explored: Explored::default(),
+            },
+            GenVersion::Layered => {
+                let useed = seed.cast_unsigned();
+                let mut tiles = gen_base(pos, worldgen, useed);
+                let mut containers = ChunkContainers::new();
+                prefabs::stamp_prefabs(&mut tiles, &mut containers, pos, worldgen, useed);
+                gen_bedrock(&mut tiles, &mut containers, pos, worldgen, useed);
+                Self {
+                    tiles,
+                    containers,
+                    explored: Explored::default(),
                 }
-                continue;
-            }
-            // Default "cave level" generation
-            t.bg = data::tile::bg::TILES_STONEBACK;
-            if noise < 550. {
-                t.mid = data::tile::mid::TILES_STONE;
             }
-            if noise < 120. {
-                t.mid = data::tile::mid::TILES_DIRT;
-                t.bg = data::tile::bg::TILES_DIRTBACK;
-            }
-            if noise < 40. {
-                t.mid = data::tile::mid::TILES_STONE_COAL;
+        }
+    }
+}
+
+/// The generator version and parameters saved in the world directory at `world_path`.
+///
+/// `None` if the world doesn't have them, like worlds from before they were saved.
+pub(super) fn load_stamp(world_path: &Path) -> anyhow::Result<Option<(GenVersion, WorldGenDb)>> {
+    let dir = world_path.join(STAMP_DIR);
+    if !dir.exists() {
+        return Ok(None);
+    }
+    let version = ron::from_str(&std::fs::read_to_string(dir.join(VERSION_FILE))?)?;
+    let mut worldgen = WorldGenDb::load(&dir).context("Failed to load parameters")?;
+    worldgen.merge_prefabs(PrefabDef::load_dir(&dir).context("Failed to load prefabs")?);
+    Ok(Some((version, worldgen)))
+}
+
+/// Remap the tile and item ids of the generator parameters saved in the world directory
+/// at `world_path`, if it has them
+pub(super) fn remap_stamp(world_path: &Path, remap: &Remap) -> anyhow::Result<()> {
+    if let Some((version, mut worldgen)) = load_stamp(world_path)? {
+        worldgen.remap_ids(remap);
+        save_stamp(world_path, version, &worldgen)?;
+    }
+    Ok(())
+}
+
+/// Save the generator version and parameters into the world directory at `world_path`,
+/// replacing the old ones
+pub(super) fn save_stamp(
+    world_path: &Path,
+    version: GenVersion,
+    worldgen: &WorldGenDb,
+) -> anyhow::Result<()> {
+    let dir = world_path.join(STAMP_DIR);
+    // Prefabs that were removed since shouldn't stick around
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join(VERSION_FILE), ron::to_string(&version)?)?;
+    worldgen.save(&dir)?;
+    Ok(())
+}
+
+/// Generate everything but the prefabs: terrain, strata, biomes, decorations and caves
+fn gen_base(pos: ChunkPos, worldgen: &WorldGenDb, seed: u32) -> ChunkTiles {
+    let mut tiles = default_chunk_tiles();
+    let x = u32::from(pos.x) * u32::from(CHUNK_EXTENT);
+    let y = u32::from(pos.y) * u32::from(CHUNK_EXTENT);
+    let surf = WorldPos::SURFACE / u32::from(TILE_SIZE);
+    let ceils: Vec<TPosSc> = (x..x + u32::from(CHUNK_EXTENT))
+        .map(|tx| surface_y(worldgen, seed, tx))
+        .collect();
+    for (i, t) in tiles.iter_mut().enumerate() {
+        #[expect(
+            clippy::cast_possible_truncation,
+            reason = "We aren't iterating through this many tiles"
+        )]
+        let i = i as u32;
+        let y = y + i / u32::from(CHUNK_EXTENT);
+        let local_x = i % u32::from(CHUNK_EXTENT);
+        let tx = x + local_x;
+        let ceil = ceils[local_x as usize];
+        if y < ceil {
+            continue;
+        }
+        let biome = biome_idx_at(worldgen, seed, tx, y).map(|idx| &worldgen.biomes[idx]);
+        // Strata follow the hills. Shift the depth a bit on top of that,
+        // so strata reach into each other at their boundaries.
+        let depth = tiles_to_m(f64::from(y) - f64::from(ceil))
+            + blend_shift(worldgen, seed ^ BLEND_SALT, tx, y);
+        gen_strata(t, worldgen, seed, tx, y, depth);
+        if let Some(biome) = biome.filter(|biome| depth < f64::from(biome.depth)) {
+            apply_biome(t, biome, y == ceil);
+        }
+    }
+    decorations::decorate(&mut tiles, pos, worldgen, seed);
+    caves::carve_caves(&mut tiles, pos, worldgen, seed, surf);
+    tiles
+}
+
+/// Fill in the bedrock at the bottom and the left and right edges of the world.
+///
+/// Goes over everything else, so no cave or prefab breaks through it.
+fn gen_bedrock(
+    tiles: &mut ChunkTiles,
+    containers: &mut ChunkContainers,
+    pos: ChunkPos,
+    worldgen: &WorldGenDb,
+    seed: u32,
+) {
+    let Some(bedrock) = &worldgen.bedrock else {
+        return;
+    };
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "Validated to be positive, and within the world"
+    )]
+    let to_tiles = |m: f32| m_to_tiles(f64::from(m)).round() as TPosSc;
+    let ext = u32::from(CHUNK_EXTENT);
+    let (x0, y0) = (u32::from(pos.x) * ext, u32::from(pos.y) * ext);
+    let top = WorldPos::SURFACE / u32::from(TILE_SIZE) + to_tiles(bedrock.depth);
+    let wall = to_tiles(bedrock.wall);
+    let rough = to_tiles(bedrock.roughness);
+    let right = WORLD_EXTENT.saturating_sub(wall);
+    // Nothing to do away from the edges
+    if y0 + ext + rough <= top && x0 >= wall + rough && x0 + ext + rough <= right {
+        return;
+    }
+    // Same noise as the boundaries between strata, so the bedrock fits in
+    let ragged = |along: TPosSc, side: u32| {
+        let seed = hash(seed ^ BEDROCK_SALT, side, 0);
+        let noise = worldgen.blend_noise.sample(seed, f64::from(along), 0.0);
+        to_tiles(noise * bedrock.roughness)
+    };
+    for (i, t) in tiles.iter_mut().enumerate() {
+        #[expect(
+            clippy::cast_possible_truncation,
+            reason = "We aren't iterating through this many tiles"
+        )]
+        let i = i as u32;
+        let (x, y) = (x0 + i % ext, y0 + i / ext);
+        if y >= top.saturating_sub(ragged(x, 0))
+            || x < wall + ragged(y, 1)
+            || x >= right.saturating_sub(ragged(y, 2))
+        {
+            t.mid = bedrock.mid;
+            t.bg = bedrock.bg;
+        }
+    }
+    containers.retain(|local, _| {
+        let idx = usize::from(local.y) * usize::from(CHUNK_EXTENT) + usize::from(local.x);
+        tiles[idx].mid != bedrock.mid
+    });
+}
+
+/// Index of the surface biome at tile (`x`, `y`) in [`WorldGenDb::biomes`].
+///
+/// The borders between biomes are ragged, like the boundaries between strata.
+pub(super) fn biome_idx_at(
+    worldgen: &WorldGenDb,
+    seed: u32,
+    x: TPosSc,
+    y: TPosSc,
+) -> Option<usize> {
+    let x_m = tiles_to_m(f64::from(x) - f64::from(WORLD_EXTENT / 2))
+        + blend_shift(worldgen, seed ^ BIOME_BLEND_SALT, x, y);
+    #[expect(clippy::cast_possible_truncation, reason = "Fits into f32")]
+    worldgen.biome_idx_at(seed, x_m as f32)
+}
+
+/// The topmost tile row of the ground in column `x`.
+///
+/// Only depends on the seed and the column, so hills continue seamlessly across chunks.
+pub(super) fn surface_y(worldgen: &WorldGenDb, seed: u32, x: TPosSc) -> TPosSc {
+    let terrain = &worldgen.terrain;
+    let noise = terrain.hills.sample(seed ^ TERRAIN_SALT, f64::from(x), 0.0);
+    let rise = m_to_tiles(f64::from((noise - 0.5) * 2.0 * terrain.height));
+    #[expect(
+        clippy::cast_possible_truncation,
+        reason = "Hills are much lower than the world is deep"
+    )]
+    (WorldPos::SURFACE / u32::from(TILE_SIZE)).saturating_add_signed(-(rise.round() as i32))
+}
+
+/// [`surface_y`] of worlds generated with [`GenVersion::Legacy`]
+pub(super) fn legacy_surface_y(seed: i32, x: TPosSc) -> TPosSc {
+    legacy::surface_y(seed, x)
+}
+
+fn tiles_to_m(tiles: f64) -> f64 {
+    tiles * f64::from(TILE_SIZE) / f64::from(PX_PER_M)
+}
+
+fn m_to_tiles(m: f64) -> f64 {
+    m * f64::from(PX_PER_M) / f64::from(TILE_SIZE)
+}
+
+/// How far (in meters) a boundary is shifted at (`x`, `y`)
+fn blend_shift(worldgen: &WorldGenDb, seed: u32, x: TPosSc, y: TPosSc) -> f64 {
+    let noise = worldgen
+        .blend_noise
+        .sample(seed, f64::from(x), f64::from(y));
+    f64::from((noise - 0.5) * 2.0 * worldgen.blend)
+}
+
+/// Fill `t` with the rock and deposits of the stratum at `depth` meters below the surface.
+///
+/// Only depends on the position, so chunks can be generated in any order.
+fn gen_strata(t: &mut Tile, worldgen: &WorldGenDb, seed: u32, x: TPosSc, y: TPosSc, depth: f64) {
+    #[expect(clippy::cast_possible_truncation, reason = "Depth fits into f32")]
+    let Some(idx) = worldgen.stratum_idx_at(depth as f32) else {
+        return;
+    };
+    let stratum = &worldgen.strata[idx];
+    t.mid = stratum.rock;
+    t.bg = stratum.bg;
+    for (i, deposit) in (0u32..).zip(&stratum.deposits) {
+        // Seeded by the tile and position in the list, so similar deposits of
+        // neighboring strata (e.g. caves) line up at the boundary
+        let seed = hash(seed, u32::from(deposit.mid.0), i);
+        if deposit.noise.sample(seed, f64::from(x), f64::from(y)) > deposit.threshold {
+            t.mid = deposit.mid;
+            if let Some(bg) = deposit.bg {
+                t.bg = bg;
             }
         }
-        Self { tiles }
+    }
+}
+
+/// Turn a tile of the strata into its variant in `biome`
+fn apply_biome(t: &mut Tile, biome: &BiomeDef, top: bool) {
+    if t.mid.empty() {
+        return;
+    }
+    if top {
+        t.mid = biome.surface;
+    } else if let Some(swap) = biome.swaps.iter().find(|swap| swap.from == t.mid) {
+        t.mid = swap.to;
     }
 }
@@ -0,0 +1,274 @@
+use {
+    super::tiles_to_m,
+    crate::world::{ChunkPos, ChunkTiles, CHUNK_EXTENT},
+    mdv_data::{
+        tile::MidTileId,
+        worldgen::{Caverns, Shafts, WorldGenDb, Worms},
+    },
+    mdv_math::noise::{hash, hash_f32, NoiseParams},
+    std::f64::consts::TAU,
+};
+
+/// Caves start in cells of this many tiles. Same as the chunk size, so cells line up with chunks.
+pub(super) const CELL: i64 = CHUNK_EXTENT as i64;
+/// How many cells away from its starting cell a cave can reach.
+///
+/// Caves get cut off at this distance, so every chunk sees exactly the same parts of a cave,
+/// no matter which chunk is generated first.
+const REACH: i64 = 2;
+/// Salt for the seed of the caves, so they don't line up with the noises of the strata
+const CAVE_SALT: u32 = 0xca4e;
+
+/// Carve out the parts of all the caves that reach into the chunk at `pos`.
+///
+/// The caves of a cell only depend on the seed and the cell position,
+/// so caves continue seamlessly across chunk boundaries.
+pub(super) fn carve_caves(
+    tiles: &mut ChunkTiles,
+    pos: ChunkPos,
+    worldgen: &WorldGenDb,
+    seed: u32,
+    surf: u32,
+) {
+    let (chk_x, chk_y) = (i64::from(pos.x), i64::from(pos.y));
+    for cell_y in chk_y - REACH..=chk_y + REACH {
+        for cell_x in chk_x - REACH..=chk_x + REACH {
+            // Caves follow the parameters of the stratum at the middle of their cell
+            let mid_y = cell_y * CELL + CELL / 2;
+            #[expect(clippy::cast_possible_truncation, reason = "Depth fits into f32")]
+            let depth = tiles_to_m((mid_y - i64::from(surf)) as f64) as f32;
+            let Some(idx) = worldgen.stratum_idx_at(depth) else {
+                return;
+            };
+            let caves = &worldgen.strata[idx].caves;
+            let mut carver = Carver {
+                tiles: &mut *tiles,
+                x: chk_x * CELL,
+                y: chk_y * CELL,
+                reach: [
+                    (cell_x - REACH) * CELL,
+                    (cell_y - REACH) * CELL,
+                    (cell_x + REACH + 1) * CELL,
+                    (cell_y + REACH + 1) * CELL,
+                ],
+            };
+            #[expect(
+                clippy::cast_possible_truncation,
+                clippy::cast_sign_loss,
+                reason = "Cell positions wrap around, which is fine for seeding"
+            )]
+            let mut rng = CellRng::new(hash(seed ^ CAVE_SALT, cell_x as u32, cell_y as u32));
+            let origin = (cell_x * CELL, cell_y * CELL);
+            if let Some(caverns) = &caves.caverns {
+                for _ in 0..rng.count(caverns.per_cell) {
+                    carve_cavern(&mut carver, &mut rng, origin, caverns);
+                }
+            }
+            if let Some(worms) = &caves.worms {
+                for _ in 0..rng.count(worms.per_cell) {
+                    carve_worm(&mut carver, &mut rng, origin, worms);
+                }
+            }
+            if let Some(shafts) = &caves.shafts {
+                for _ in 0..rng.count(shafts.per_cell) {
+                    carve_shaft(&mut carver, &mut rng, origin, shafts);
+                }
+            }
+        }
+    }
+}
+
+fn carve_worm(carver: &mut Carver, rng: &mut CellRng, origin: (i64, i64), worms: &Worms) {
+    let (mut x, mut y) = rng.point_in_cell(origin);
+    let mut angle = rng.next_f64() * TAU;
+    let radius = f64::from(worms.radius);
+    let step = (radius / 2.0).max(1.0);
+    for _ in 0..worms.length {
+        // Tunnels widen and narrow a bit along the way
+        carver.disk(x, y, radius * rng.range(0.8, 1.2));
+        angle += rng.range(-1.0, 1.0) * f64::from(worms.turn);
+        x += angle.cos() * step;
+        // Flatten the tunnels a bit, so they're easier to walk through
+        y += angle.sin() * step * 0.5;
+        if !carver.within_reach(x, y) {
+            break;
+        }
+    }
+}
+
+fn carve_cavern(carver: &mut Carver, rng: &mut CellRng, origin: (i64, i64), caverns: &Caverns) {
+    let (cx, cy) = rng.point_in_cell(origin);
+    let rx = f64::from(caverns.size[0]) * rng.range(0.25, 0.5);
+    let ry = f64::from(caverns.size[1]) * rng.range(0.25, 0.5);
+    #[expect(clippy::cast_possible_truncation, reason = "Small radius")]
+    let scale = (rx.min(ry) / 2.0) as f32;
+    let edge = NoiseParams {
+        scale,
+        octaves: 3,
+        persistence: 0.5,
+    };
+    let edge_seed = rng.next_u32();
+    carver.area(cx - rx, cy - ry, cx + rx, cy + ry, |x, y| {
+        let (dx, dy) = ((x - cx) / rx, (y - cy) / ry);
+        // The edge is rough, reaching between 60% and 100% of the radius
+        let noise = f64::from(edge.sample(edge_seed, x, y));
+        dx * dx + dy * dy < (0.6 + 0.4 * noise).powi(2)
+    });
+}
+
+fn carve_shaft(carver: &mut Carver, rng: &mut CellRng, origin: (i64, i64), shafts: &Shafts) {
+    let (cx, top) = rng.point_in_cell(origin);
+    let len = f64::from(shafts.length) * rng.range(0.5, 1.0);
+    let half_width = f64::from(shafts.width) / 2.0;
+    let wobble = NoiseParams {
+        scale: 24.0,
+        octaves: 2,
+        persistence: 0.5,
+    };
+    let wobble_seed = rng.next_u32();
+    carver.area(
+        cx - half_width - 4.0,
+        top,
+        cx + half_width + 4.0,
+        top + len,
+        |x, y| {
+            // Sway up to 4 tiles to the sides
+            let mid = cx + (f64::from(wobble.sample(wobble_seed, cx, y)) - 0.5) * 8.0;
+            (x - mid).abs() <= half_width
+        },
+    );
+}
+
+/// Carves the parts of caves that fall into one chunk
+struct Carver<'a> {
+    tiles: &'a mut ChunkTiles,
+    /// Position of the chunk in tiles
+    x: i64,
+    y: i64,
+    /// The area (left, top, right, bottom) the caves of the current cell are cut off at
+    reach: [i64; 4],
+}
+
+impl Carver<'_> {
+    fn within_reach(&self, x: f64, y: f64) -> bool {
+        let [left, top, right, bottom] = self.reach.map(|v| v as f64);
+        (left..right).contains(&x) && (top..bottom).contains(&y)
+    }
+    fn disk(&mut self, cx: f64, cy: f64, r: f64) {
+        self.area(cx - r, cy - r, cx + r, cy + r, |x, y| {
+            (x - cx).powi(2) + (y - cy).powi(2) <= r * r
+        });
+    }
+    /// Empty the tiles in the (`left`, `top`, `right`, `bottom`) area for which `inside` is true
+    #[expect(
+        clippy::cast_possible_truncation,
+        reason = "Cave coordinates are within the world"
+    )]
+    fn area(
+        &mut self,
+        left: f64,
+        top: f64,
+        right: f64,
+        bottom: f64,
+        mut inside: impl FnMut(f64, f64) -> bool,
+    ) {
+        let ext = i64::from(CHUNK_EXTENT);
+        let [reach_left, reach_top, reach_right, reach_bottom] = self.reach;
+        let x_min = (left.floor() as i64).max(self.x).max(reach_left);
+        let y_min = (top.floor() as i64).max(self.y).max(reach_top);
+        let x_max = (right.ceil() as i64)
+            .min(self.x + ext - 1)
+            .min(reach_right - 1);
+        let y_max = (bottom.ceil() as i64)
+            .min(self.y + ext - 1)
+            .min(reach_bottom - 1);
+        for y in y_min..=y_max {
+            for x in x_min..=x_max {
+                if inside(x as f64, y as f64) {
+                    let idx = (y - self.y) * ext + (x - self.x);
+                    self.tiles[usize::try_from(idx).unwrap()].mid = MidTileId::EMPTY;
+                }
+            }
+        }
+    }
+}
+
+/// Deterministic random numbers for the features (caves, prefabs) of one cell
+pub(super) struct CellRng {
+    seed: u32,
+    n: u32,
+}
+
+impl CellRng {
+    pub(super) fn new(seed: u32) -> Self {
+        Self { seed, n: 0 }
+    }
+    fn next_u32(&mut self) -> u32 {
+        self.n += 1;
+        hash(self.seed, self.n, 0)
+    }
+    /// Random number in the 0..1 range
+    pub(super) fn next_f64(&mut self) -> f64 {
+        self.n += 1;
+        f64::from(hash_f32(self.seed, self.n, 0))
+    }
+    fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + self.next_f64() * (max - min)
+    }
+    fn point_in_cell(&mut self, (x, y): (i64, i64)) -> (f64, f64) {
+        let cell = CELL as f64;
+        (
+            x as f64 + self.next_f64() * cell,
+            y as f64 + self.next_f64() * cell,
+        )
+    }
+    /// How many features to place when there are `avg` per cell on average
+    pub(super) fn count(&mut self, avg: f32) -> u32 {
+        let mut count = 0;
+        let mut left = f64::from(avg);
+        while left > 0.0 {
+            if left >= 1.0 || self.next_f64() < left {
+                count += 1;
+            }
+            left -= 1.0;
+        }
+        count
+    }
+}
+
+#[test]
+fn test_caves_across_chunk_borders() {
+    let worldgen = WorldGenDb::load(concat!(env!("CARGO_MANIFEST_DIR"), "/data")).unwrap();
+    let seed = 0x5eed;
+    let ext = usize::from(CHUNK_EXTENT);
+    let same = |a: &ChunkTiles, b: &ChunkTiles| {
+        a.iter().zip(b).all(|(a, b)| a.bg == b.bg && a.mid == b.mid)
+    };
+    let mut crossings = 0;
+    // Deep enough for worms and caverns
+    for chk_x in 970..980 {
+        let [left, right] = [chk_x, chk_x + 1].map(|x| ChunkPos { x, y: 334 });
+        let left_first = [left, right].map(|pos| super::gen_base(pos, &worldgen, seed));
+        let right_first = [right, left].map(|pos| super::gen_base(pos, &worldgen, seed));
+        assert!(same(&left_first[0], &right_first[1]) && same(&left_first[1], &right_first[0]));
+        let [left, right] = left_first;
+        let open_left: Vec<bool> = (0..ext)
+            .map(|y| left[y * ext + ext - 1].mid.empty())
+            .collect();
+        let open_right: Vec<bool> = (0..ext).map(|y| right[y * ext].mid.empty()).collect();
+        // Every tunnel through the border continues on the other side.
+        // Caves only grazing the border can open up just a tile or two on one side.
+        for (this, other) in [(&open_left, &open_right), (&open_right, &open_left)] {
+            let mut y = 0;
+            while y < ext {
+                let len = this[y..].iter().take_while(|open| **open).count();
+                if len >= 3 {
+                    assert!(other[y..y + len].contains(&true), "{chk_x}, {y}");
+                    crossings += 1;
+                }
+                y += len.max(1);
+            }
+        }
+    }
+    assert!(crossings > 0);
+}
@@ -0,0 +1,113 @@
+use {
+    super::{biome_idx_at, surface_y},
+    crate::world::{ChunkPos, ChunkTiles, TPosSc, CHUNK_EXTENT},
+    mdv_data::{
+        tile::MidTileId,
+        worldgen::{DecorationKind, WorldGenDb},
+    },
+    mdv_math::noise::{hash, hash_f32},
+};
+
+/// Salt for the seed of the decorations, so they don't follow the hills
+const DECOR_SALT: u32 = 0xdec0;
+
+/// Place the parts of the surface decorations (trees, grass, rocks, ...) that reach into the
+/// chunk at `pos`.
+///
+/// What grows on a column only depends on the seed and the column. Decorations are placed
+/// column by column from left to right, and only into empty tiles, so overlapping trees come
+/// out the same no matter which chunk is generated first.
+pub(super) fn decorate(tiles: &mut ChunkTiles, pos: ChunkPos, worldgen: &WorldGenDb, seed: u32) {
+    let ext = TPosSc::from(CHUNK_EXTENT);
+    let mut stamp = Stamp {
+        tiles,
+        x: i64::from(pos.x) * i64::from(ext),
+        y: i64::from(pos.y) * i64::from(ext),
+    };
+    let (side_reach, up_reach) = reach(worldgen);
+    let x0 = TPosSc::from(pos.x) * ext;
+    let y0 = TPosSc::from(pos.y) * ext;
+    for x in x0.saturating_sub(side_reach)..x0 + ext + side_reach {
+        let top = surface_y(worldgen, seed, x);
+        // Decorations sit on top of the ground, and can't reach this chunk from here
+        if top <= y0 || top > y0 + ext + up_reach {
+            continue;
+        }
+        let Some(biome) = biome_idx_at(worldgen, seed, x, top).map(|idx| &worldgen.biomes[idx])
+        else {
+            continue;
+        };
+        let Some(decoration) = biome.pick_decoration(hash_f32(seed ^ DECOR_SALT, x, 0)) else {
+            continue;
+        };
+        let (x, top) = (i64::from(x), i64::from(top));
+        match decoration.kind {
+            DecorationKind::Tile(mid) => stamp.put(x, top - 1, mid),
+            DecorationKind::Tree {
+                trunk,
+                leaves,
+                height: [min, max],
+                canopy,
+            } => {
+                let extra = hash(seed ^ DECOR_SALT, u32::try_from(x).unwrap(), 1)
+                    % (u32::from(max.saturating_sub(min)) + 1);
+                let height = i64::from(min) + i64::from(extra);
+                for dy in 1..=height {
+                    stamp.put(x, top - dy, trunk);
+                }
+                // Leaves grow around the top of the trunk, but not over it
+                let crown = top - height;
+                let r = i64::from(canopy);
+                for dy in -r..=r {
+                    for dx in -r..=r {
+                        // A bit more than r², so the canopy isn't pointy at the sides
+                        if dx * dx + dy * dy <= r * r + r {
+                            stamp.put(x + dx, crown + dy, leaves);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// How far (sideways, upwards) from the column it grows on a decoration can reach
+fn reach(worldgen: &WorldGenDb) -> (TPosSc, TPosSc) {
+    let trees = worldgen
+        .biomes
+        .iter()
+        .flat_map(|biome| &biome.decorations)
+        .filter_map(|decoration| match decoration.kind {
+            DecorationKind::Tile(_) => None,
+            DecorationKind::Tree { height, canopy, .. } => Some((height[1], canopy)),
+        });
+    trees.fold((0, 1), |(side, up), (height, canopy)| {
+        (
+            side.max(TPosSc::from(canopy)),
+            up.max(TPosSc::from(height) + TPosSc::from(canopy)),
+        )
+    })
+}
+
+/// Places decoration tiles into one chunk
+struct Stamp<'a> {
+    tiles: &'a mut ChunkTiles,
+    /// Position of the chunk in tiles
+    x: i64,
+    y: i64,
+}
+
+impl Stamp<'_> {
+    /// Place `mid` at (`x`, `y`) if that's inside the chunk, and nothing is there yet
+    fn put(&mut self, x: i64, y: i64, mid: MidTileId) {
+        let ext = i64::from(CHUNK_EXTENT);
+        let (local_x, local_y) = (x - self.x, y - self.y);
+        if !(0..ext).contains(&local_x) || !(0..ext).contains(&local_y) {
+            return;
+        }
+        let t = &mut self.tiles[usize::try_from(local_y * ext + local_x).unwrap()];
+        if t.mid.empty() {
+            t.mid = mid;
+        }
+    }
+}
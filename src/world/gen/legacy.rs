@@ -0,0 +1,125 @@
+use {
+    crate::{
+        data,
+        math::{WorldPos, TILE_SIZE},
+        world::{default_chunk_tiles, ChunkPos, ChunkTiles, TPosSc, CHUNK_EXTENT, CHUNK_N_TILES},
+    },
+    simdnoise::NoiseBuilder,
+};
+
+/// Generate the chunk at `pos` like worlds were generated before generators had versions.
+///
+/// The noise is scaled per chunk, so neighboring chunks don't quite line up.
+/// Don't fix that here, the new chunks of old worlds have to match their old chunks.
+pub(super) fn gen(pos: ChunkPos, seed: i32) -> ChunkTiles {
+    let mut tiles = default_chunk_tiles();
+    let x = u32::from(pos.x) * u32::from(CHUNK_EXTENT);
+    let y = u32::from(pos.y) * u32::from(CHUNK_EXTENT);
+    let noise = NoiseBuilder::gradient_2d_offset(
+        x as f32,
+        usize::from(CHUNK_EXTENT),
+        y as f32,
+        usize::from(CHUNK_EXTENT),
+    )
+    .with_seed(seed)
+    .generate_scaled(0.0, 1000.0);
+    let hnoise = hills(x, seed);
+    assert!(noise.len() == CHUNK_N_TILES);
+    for (i, (t, noise)) in tiles.iter_mut().zip(noise).enumerate() {
+        #[expect(
+            clippy::cast_possible_truncation,
+            reason = "We aren't iterating through this many tiles"
+        )]
+        let i = i as u32;
+        let y = y + i / u32::from(CHUNK_EXTENT);
+        let local_x = i % u32::from(CHUNK_EXTENT);
+        let surf = WorldPos::SURFACE / u32::from(TILE_SIZE);
+        #[expect(clippy::cast_possible_truncation, reason = "Scaled noise")]
+        let ceil = surf.saturating_add_signed(hnoise[local_x as usize] as i32 / 4);
+        #[expect(clippy::cast_possible_truncation, reason = "Scaled noise")]
+        if y == ceil - 1 {
+            if noise as i32 % 19 == 0 {
+                t.mid = data::tile::mid::TREE;
+            } else if noise as i32 % 17 == 0 {
+                t.mid = data::tile::mid::SMALLROCK;
+            } else if noise as i32 % 15 == 0 {
+                t.mid = data::tile::mid::STICK;
+            }
+        }
+        if y < ceil {
+            continue;
+        }
+        // Dirt level, just a mass of mostly dirt
+        let dirt_bottom = surf + 80;
+        #[expect(clippy::cast_possible_truncation, reason = "Scaled noise")]
+        if y < dirt_bottom.saturating_add_signed(hnoise[local_x as usize] as i32) {
+            t.mid = data::tile::mid::DIRT;
+            t.bg = data::tile::bg::DIRTBACK;
+            if y > ceil + 2 && noise as i32 % 37 == 0 {
+                t.mid = data::tile::mid::DIRT_COAL;
+            }
+            continue;
+        }
+        // Default "cave level" generation
+        t.bg = data::tile::bg::STONEBACK;
+        if noise < 550. {
+            t.mid = data::tile::mid::STONE;
+        }
+        if noise < 120. {
+            t.mid = data::tile::mid::DIRT;
+            t.bg = data::tile::bg::DIRTBACK;
+        }
+        if noise < 40. {
+            t.mid = data::tile::mid::STONE_COAL;
+        }
+    }
+    tiles
+}
+
+/// The topmost tile row of the ground in column `x`
+pub(super) fn surface_y(seed: i32, x: TPosSc) -> TPosSc {
+    let ext = u32::from(CHUNK_EXTENT);
+    let hnoise = hills(x - x % ext, seed);
+    let surf = WorldPos::SURFACE / u32::from(TILE_SIZE);
+    #[expect(clippy::cast_possible_truncation, reason = "Scaled noise")]
+    surf.saturating_add_signed(hnoise[(x % ext) as usize] as i32 / 4)
+}
+
+/// Height of the hills for the columns of the chunk starting at column `x`
+fn hills(x: TPosSc, seed: i32) -> Vec<f32> {
+    NoiseBuilder::gradient_1d_offset(x as f32, usize::from(CHUNK_EXTENT))
+        .with_seed(seed)
+        .generate_scaled(-10., 10.)
+}
+
+#[test]
+fn test_matches_old_worlds() {
+    use {
+        data::tile::{bg, mid},
+        mdv_data::tile::TileId,
+        mdv_math::noise::hash,
+    };
+    // Tiles by what they are rather than by id, so reordering the tile database doesn't matter
+    let mids = [
+        TileId::EMPTY,
+        mid::DIRT,
+        mid::STONE,
+        mid::DIRT_COAL,
+        mid::STONE_COAL,
+        mid::TREE,
+        mid::SMALLROCK,
+        mid::STICK,
+    ];
+    let bgs = [TileId::EMPTY, bg::DIRTBACK, bg::STONEBACK];
+    let mut sum = 0;
+    for pos in [ChunkPos { x: 976, y: 328 }, ChunkPos { x: 977, y: 329 }] {
+        for t in gen(pos, 1234) {
+            let m = mids.iter().position(|id| *id == t.mid).unwrap();
+            let b = bgs.iter().position(|id| *id == t.bg).unwrap();
+            sum = hash(sum, u32::try_from(m).unwrap(), u32::try_from(b).unwrap());
+        }
+    }
+    // Generated by the world generator from before generators had versions.
+    // If this changes, the new chunks of old worlds won't line up with their existing chunks.
+    assert_eq!(sum, 220_078_157);
+}
@@ -0,0 +1,351 @@
+use {
+    super::{
+        caves::{CellRng, CELL},
+        gen_base, m_to_tiles, surface_y,
+    },
+    crate::{
+        inventory::Inventory,
+        math::WORLD_EXTENT,
+        world::{
+            ChunkContainers, ChunkLocalTilePos, ChunkPos, ChunkTiles, TPosSc, Tile, CHUNK_EXTENT,
+        },
+    },
+    mdv_data::{
+        prefab::{Frequency, PrefabDef},
+        tile::MidTileId,
+        worldgen::{DecorationKind, WorldGenDb},
+    },
+    mdv_math::noise::hash,
+    std::collections::HashMap,
+};
+
+/// Salt for the seeds of the prefabs, so they don't line up with the caves
+const PREFAB_SALT: u32 = 0x9ef4b;
+/// How far (in tiles) below where it would go a prefab looks for a floor to sit on
+const FLOOR_SEARCH: i64 = 48;
+
+/// Stamp the parts of all the prefabs that reach into the chunk at `pos`.
+///
+/// Where prefabs go only depends on the seed, and on the tiles generated before the prefabs,
+/// which neighboring chunks are generated for if needed.
+/// Prefabs are stamped in the same order for every chunk, so overlapping prefabs come out
+/// the same no matter which chunk is generated first.
+pub(super) fn stamp_prefabs(
+    tiles: &mut ChunkTiles,
+    containers: &mut ChunkContainers,
+    pos: ChunkPos,
+    worldgen: &WorldGenDb,
+    seed: u32,
+) {
+    if worldgen.prefabs.is_empty() {
+        return;
+    }
+    let mut base = BaseTiles::new(worldgen, seed);
+    base.chunks.insert(pos, Box::new(*tiles));
+    let ext = i64::from(CHUNK_EXTENT);
+    let (chk_x, chk_y) = (i64::from(pos.x) * ext, i64::from(pos.y) * ext);
+    for prefab in &worldgen.prefabs {
+        let w = i64::try_from(prefab.width()).unwrap();
+        let h = i64::try_from(prefab.height()).unwrap();
+        let search = if prefab.placement.on_floor {
+            FLOOR_SEARCH
+        } else {
+            0
+        };
+        // The bottom middle of prefabs that can reach into the chunk is within these bounds
+        let xs = chk_x - w..chk_x + ext + w;
+        let ys = chk_y - search..chk_y + ext + h;
+        for (x, y) in candidates(prefab, worldgen, seed, &xs, &ys) {
+            if !xs.contains(&x) || !ys.contains(&y) {
+                continue;
+            }
+            let left = x - w / 2;
+            let bottom = if prefab.placement.on_floor {
+                match base.find_floor(left, w, y) {
+                    Some(bottom) => bottom,
+                    None => continue,
+                }
+            } else {
+                y
+            };
+            let mut stamp = Stamp {
+                tiles: &mut *tiles,
+                containers: &mut *containers,
+                x: chk_x,
+                y: chk_y,
+            };
+            stamp.prefab(prefab, left, bottom - h + 1);
+        }
+    }
+}
+
+/// Possible bottom middle positions of `prefab`, for the chunk covering `xs` and `ys`
+fn candidates(
+    prefab: &PrefabDef,
+    worldgen: &WorldGenDb,
+    seed: u32,
+    xs: &std::ops::Range<i64>,
+    ys: &std::ops::Range<i64>,
+) -> Vec<(i64, i64)> {
+    let placement = &prefab.placement;
+    #[expect(
+        clippy::cast_possible_truncation,
+        reason = "Depths are within the world"
+    )]
+    let [top, bottom] = placement
+        .depth
+        .map(|depth| m_to_tiles(f64::from(depth)).round() as i64);
+    let surface = |x: i64| {
+        let x = TPosSc::try_from(x.clamp(0, i64::from(WORLD_EXTENT) - 1)).unwrap();
+        i64::from(surface_y(worldgen, seed, x))
+    };
+    let prefab_seed = hash(seed ^ PREFAB_SALT, name_hash(&prefab.name), 0);
+    let mut candidates = Vec::new();
+    match placement.frequency {
+        Frequency::PerCell(per_cell) => {
+            // Cells follow the surface, so the rows that can reach into the chunk depend on
+            // how high and low the surface goes across it
+            let (surface_min, surface_max) = xs
+                .clone()
+                .map(surface)
+                .fold((i64::MAX, i64::MIN), |(min, max), y| {
+                    (min.min(y), max.max(y))
+                });
+            let rows = (ys.start - surface_max).max(top).div_euclid(CELL)
+                ..=(ys.end - surface_min).min(bottom).div_euclid(CELL);
+            for cell_x in xs.start.div_euclid(CELL)..=xs.end.div_euclid(CELL) {
+                for row in rows.clone() {
+                    #[expect(
+                        clippy::cast_possible_truncation,
+                        clippy::cast_sign_loss,
+                        reason = "Cell positions wrap around, which is fine for seeding"
+                    )]
+                    let mut rng = CellRng::new(hash(prefab_seed, cell_x as u32, row as u32));
+                    for _ in 0..rng.count(per_cell) {
+                        let x = cell_x * CELL + offset(&mut rng, CELL);
+                        let depth_top = (row * CELL).max(top);
+                        let depth_bottom = ((row + 1) * CELL).min(bottom + 1);
+                        let depth = depth_top + offset(&mut rng, depth_bottom - depth_top);
+                        candidates.push((x, surface(x) + depth));
+                    }
+                }
+            }
+        }
+        Frequency::Unique { spread } => {
+            let mut rng = CellRng::new(prefab_seed);
+            #[expect(
+                clippy::cast_possible_truncation,
+                reason = "Spread is within the world"
+            )]
+            let spread = m_to_tiles(f64::from(spread)).round() as i64;
+            let x = i64::from(WORLD_EXTENT / 2) - spread + offset(&mut rng, spread * 2 + 1);
+            let depth = top + offset(&mut rng, bottom - top + 1);
+            candidates.push((x, surface(x) + depth));
+        }
+    }
+    candidates
+}
+
+/// Random offset in the `0..len` range
+#[expect(clippy::cast_possible_truncation, reason = "Offsets are small")]
+fn offset(rng: &mut CellRng, len: i64) -> i64 {
+    (rng.next_f64() * len.max(0) as f64) as i64
+}
+
+/// Seed prefabs by name, so adding a prefab doesn't move the others around
+fn name_hash(name: &str) -> u32 {
+    name.bytes()
+        .fold(0, |acc, byte| hash(acc, u32::from(byte), 0))
+}
+
+/// The tiles of the world before the prefabs are stamped
+struct BaseTiles<'a> {
+    worldgen: &'a WorldGenDb,
+    seed: u32,
+    chunks: HashMap<ChunkPos, Box<ChunkTiles>>,
+    /// Tiles that don't count as a floor to sit on, like trees and grass
+    decorations: Vec<MidTileId>,
+}
+
+impl<'a> BaseTiles<'a> {
+    fn new(worldgen: &'a WorldGenDb, seed: u32) -> Self {
+        let decorations = worldgen
+            .biomes
+            .iter()
+            .flat_map(|biome| &biome.decorations)
+            .flat_map(|decoration| match decoration.kind {
+                DecorationKind::Tile(id) => vec![id],
+                DecorationKind::Tree { trunk, leaves, .. } => vec![trunk, leaves],
+            })
+            .collect();
+        Self {
+            worldgen,
+            seed,
+            chunks: HashMap::new(),
+            decorations,
+        }
+    }
+    /// The tile at (`x`, `y`), generating its chunk if needed. `None` outside of the world.
+    fn tile(&mut self, x: i64, y: i64) -> Option<Tile> {
+        let ext = i64::from(CHUNK_EXTENT);
+        let pos = ChunkPos {
+            x: x.div_euclid(ext).try_into().ok()?,
+            y: y.div_euclid(ext).try_into().ok()?,
+        };
+        let tiles = self
+            .chunks
+            .entry(pos)
+            .or_insert_with(|| Box::new(gen_base(pos, self.worldgen, self.seed)));
+        let idx = y.rem_euclid(ext) * ext + x.rem_euclid(ext);
+        Some(tiles[usize::try_from(idx).unwrap()])
+    }
+    fn is_floor(&mut self, x: i64, y: i64) -> bool {
+        self.tile(x, y)
+            .is_some_and(|t| !t.mid.empty() && !self.decorations.contains(&t.mid))
+    }
+    /// The row at or below `y` for a prefab `w` tiles wide starting at `left` to sit in.
+    ///
+    /// The middle of the prefab has to be right on top of the floor,
+    /// and at least half of the prefab has to rest on it.
+    fn find_floor(&mut self, left: i64, w: i64, y: i64) -> Option<i64> {
+        let mid = left + w / 2;
+        (y..y + FLOOR_SEARCH).find(|&bottom| {
+            if self.is_floor(mid, bottom) || !self.is_floor(mid, bottom + 1) {
+                return false;
+            }
+            let resting = (left..left + w)
+                .filter(|&x| self.is_floor(x, bottom + 1))
+                .count();
+            resting * 2 >= usize::try_from(w).unwrap()
+        })
+    }
+}
+
+/// Places prefab tiles into one chunk
+struct Stamp<'a> {
+    tiles: &'a mut ChunkTiles,
+    containers: &'a mut ChunkContainers,
+    /// Position of the chunk in tiles
+    x: i64,
+    y: i64,
+}
+
+impl Stamp<'_> {
+    /// Stamp the parts of `prefab` that fall into the chunk, with its top left at (`left`, `top`)
+    fn prefab(&mut self, prefab: &PrefabDef, left: i64, top: i64) {
+        for (y, row) in (top..).zip(&prefab.rows) {
+            for (x, ch) in (left..).zip(row.chars()) {
+                let Some(key) = prefab.key(ch) else {
+                    continue;
+                };
+                let Some(local) = self.local(x, y) else {
+                    continue;
+                };
+                let idx = usize::from(CHUNK_EXTENT) * usize::from(local.y) + usize::from(local.x);
+                let t = &mut self.tiles[idx];
+                if let Some(bg) = key.bg {
+                    t.bg = bg;
+                }
+                if let Some(mid) = key.mid {
+                    t.mid = mid;
+                }
+            }
+        }
+        for container in &prefab.containers {
+            let [x, y] = container.pos.map(i64::from);
+            if let Some(local) = self.local(left + x, top + y) {
+                self.containers.insert(
+                    local,
+                    Inventory {
+                        slots: container.items.clone(),
+                        grabbed: None,
+                    },
+                );
+            }
+        }
+    }
+    fn local(&self, x: i64, y: i64) -> Option<ChunkLocalTilePos> {
+        Some(ChunkLocalTilePos {
+            x: (x - self.x)
+                .try_into()
+                .ok()
+                .filter(|&x: &u8| u16::from(x) < CHUNK_EXTENT)?,
+            y: (y - self.y)
+                .try_into()
+                .ok()
+                .filter(|&y: &u8| u16::from(y) < CHUNK_EXTENT)?,
+        })
+    }
+}
+
+#[test]
+fn test_prefabs_across_chunk_seams() {
+    use mdv_data::prefab::{Placement, PrefabKey};
+    let mut worldgen = WorldGenDb::load(concat!(env!("CARGO_MANIFEST_DIR"), "/data")).unwrap();
+    let marker = MidTileId::from_idx(u16::MAX);
+    // Wide, and crowded around the hilly surface, so plenty of them straddle the seams
+    let prefab = PrefabDef {
+        name: "seam".into(),
+        legend: vec![PrefabKey {
+            ch: '#',
+            color: None,
+            bg: None,
+            mid: Some(marker),
+        }],
+        rows: vec!["#".repeat(24); 3],
+        image: None,
+        containers: Vec::new(),
+        placement: Placement {
+            depth: [-10.0, 10.0],
+            frequency: Frequency::PerCell(16.0),
+            on_floor: false,
+        },
+    };
+    worldgen.prefabs = vec![prefab.clone()];
+    // Steep hills, so the surface goes up and down a lot across a chunk
+    worldgen.terrain.hills.scale = 24.0;
+    let seed = 0x5eed;
+    let ext = i64::from(CHUNK_EXTENT);
+    let (w, h) = (24, 3);
+    let mut straddling = 0;
+    for chk_x in 960..970 {
+        let seam = (chk_x + 1) * ext;
+        let surface = i64::from(surface_y(&worldgen, seed, TPosSc::try_from(seam).unwrap()));
+        for chk_y in surface.div_euclid(ext) - 1..=surface.div_euclid(ext) + 1 {
+            // Each side stamps its own part of the prefabs
+            let sides = [chk_x, chk_x + 1].map(|x| {
+                let pos = ChunkPos {
+                    x: x.try_into().unwrap(),
+                    y: chk_y.try_into().unwrap(),
+                };
+                let mut tiles = gen_base(pos, &worldgen, seed);
+                stamp_prefabs(
+                    &mut tiles,
+                    &mut ChunkContainers::new(),
+                    pos,
+                    &worldgen,
+                    seed,
+                );
+                tiles
+            });
+            // Every row of cells, so this doesn't depend on what the chunks consider
+            let all_xs = chk_x * ext - w..seam + ext + w;
+            let all_ys = (chk_y - 8) * ext..(chk_y + 8) * ext;
+            let (chunks_xs, chunk_ys) = (chk_x * ext..seam + ext, chk_y * ext..(chk_y + 1) * ext);
+            for (x, bottom) in candidates(&prefab, &worldgen, seed, &all_xs, &all_ys) {
+                let left = x - w / 2;
+                if left < seam && left + w > seam {
+                    straddling += 1;
+                }
+                for y in (bottom - h + 1..=bottom).filter(|y| chunk_ys.contains(y)) {
+                    for x in (left..left + w).filter(|x| chunks_xs.contains(x)) {
+                        let tiles = &sides[usize::from(x >= seam)];
+                        let idx = (y - chk_y * ext) * ext + x.rem_euclid(ext);
+                        assert_eq!(tiles[usize::try_from(idx).unwrap()].mid, marker, "{x}, {y}");
+                    }
+                }
+            }
+        }
+    }
+    assert!(straddling > 0);
+}
@@ -1,18 +1,144 @@
 use {
-    super::{default_chunk_tiles, loc_byte_idx_xy, Chunk, ChunkPos},
+    super::{
+        default_chunk_tiles, explored::Explored, loc_byte_idx_xy, Chunk, ChunkContainers, ChunkPos,
+        REGION_CHUNK_EXTENT,
+    },
     crate::world::{
-        format_reg_file_name, loc_byte_idx, loc_idx, reg_chunk_existence::ExistenceBitset,
-        REGION_BYTES, TILE_BYTES,
+        format_reg_extra_file_name, format_reg_file_name, loc_byte_idx, loc_idx,
+        reg_chunk_existence::ExistenceBitset, REGION_BYTES, TILE_BYTES,
     },
+    mdv_data::palette::Remap,
+    serde::{Deserialize, Serialize},
     std::{
+        collections::BTreeMap,
         fs::OpenOptions,
         io::{Seek, Write},
         path::Path,
     },
 };
 
+/// The variable sized data of a region, stored next to the region file
+#[derive(Serialize, Deserialize, Default)]
+struct RegionExtra {
+    /// Container inventories of each chunk, keyed by the chunk's index in the region
+    containers: BTreeMap<u8, ChunkContainers>,
+    /// The tiles of each chunk the player has seen, keyed like [`Self::containers`]
+    #[serde(default)]
+    explored: BTreeMap<u8, Explored>,
+}
+
+impl RegionExtra {
+    fn load(path: &Path) -> Self {
+        if !path.exists() {
+            return Self::default();
+        }
+        match std::fs::read(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| Ok(rmp_serde::from_slice(&data)?))
+        {
+            Ok(extra) => extra,
+            Err(e) => {
+                log::error!("Failed to load {}: {e}", path.display());
+                Self::default()
+            }
+        }
+    }
+    fn save(&self, path: &Path) {
+        let result = rmp_serde::to_vec(self)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| Ok(std::fs::write(path, data)?));
+        if let Err(e) = result {
+            log::error!("Failed to save {}: {e}", path.display());
+        }
+    }
+}
+
+/// The container inventories and the explored tiles of the chunk at `pos`
+pub(super) fn load_chunk_extra(world_dir: &Path, pos: ChunkPos) -> (ChunkContainers, Explored) {
+    let path = world_dir.join(format_reg_extra_file_name(pos.region()));
+    let (loc_x, loc_y) = pos.local();
+    let loc_idx = loc_idx(loc_y, loc_x);
+    let mut extra = RegionExtra::load(&path);
+    (
+        extra.containers.remove(&loc_idx).unwrap_or_default(),
+        extra.explored.remove(&loc_idx).unwrap_or_default(),
+    )
+}
+
+/// The saved chunks of `region` that have explored tiles.
+///
+/// Their containers aren't loaded, they are only for looking at.
+pub(super) fn load_explored_chunks(world_dir: &Path, region: (u8, u8)) -> Vec<(ChunkPos, Chunk)> {
+    let extra = RegionExtra::load(&world_dir.join(format_reg_extra_file_name(region)));
+    if extra.explored.is_empty() {
+        return Vec::new();
+    }
+    let data = match std::fs::read(world_dir.join(format_reg_file_name(region))) {
+        // Skip the existence bitset
+        Ok(raw) if raw.len() > 8 => zstd::decode_all(&raw[8..]).unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    if data.len() != REGION_BYTES {
+        log::error!("Failed to load explored chunks of region {region:?}");
+        return Vec::new();
+    }
+    let ext = u16::from(REGION_CHUNK_EXTENT);
+    extra
+        .explored
+        .into_iter()
+        .map(|(loc_idx, explored)| {
+            let (loc_x, loc_y) = (loc_idx % REGION_CHUNK_EXTENT, loc_idx / REGION_CHUNK_EXTENT);
+            let pos = ChunkPos {
+                x: u16::from(region.0) * ext + u16::from(loc_x),
+                y: u16::from(region.1) * ext + u16::from(loc_y),
+            };
+            let mut chunk = Chunk::load_from_region(&data, loc_x, loc_y);
+            chunk.explored = explored;
+            (pos, chunk)
+        })
+        .collect()
+}
+
+fn save_region_extra<'a>(
+    world_dir: &Path,
+    region: (u8, u8),
+    chunks: impl Iterator<Item = (&'a ChunkPos, &'a Chunk)>,
+) {
+    let path = world_dir.join(format_reg_extra_file_name(region));
+    let exists = path.exists();
+    let mut extra = RegionExtra::load(&path);
+    for (pos, chk) in chunks {
+        let (loc_x, loc_y) = pos.local();
+        let loc_idx = loc_idx(loc_y, loc_x);
+        if chk.containers.is_empty() {
+            extra.containers.remove(&loc_idx);
+        } else {
+            extra.containers.insert(loc_idx, chk.containers.clone());
+        }
+        if chk.explored.is_empty() {
+            extra.explored.remove(&loc_idx);
+        } else {
+            extra.explored.insert(loc_idx, chk.explored.clone());
+        }
+    }
+    // Don't litter the world with extra files for regions that never had anything in them
+    if extra.containers.is_empty() && extra.explored.is_empty() && !exists {
+        return;
+    }
+    extra.save(&path);
+}
+
 pub(super) fn save_chunk(pos: &ChunkPos, chk: &Chunk, world_dir: &Path) {
-    let reg_file_name = world_dir.join(format_reg_file_name(pos.region()));
+    save_region_chunks(world_dir, pos.region(), std::iter::once((pos, chk)));
+}
+
+/// Save `chunks`, which all have to be in `region`, reading and writing the region file once
+pub(super) fn save_region_chunks<'a>(
+    world_dir: &Path,
+    region: (u8, u8),
+    chunks: impl Iterator<Item = (&'a ChunkPos, &'a Chunk)> + Clone,
+) {
+    let reg_file_name = world_dir.join(format_reg_file_name(region));
     let reg_file_exists = Path::new(&reg_file_name).exists();
     if !reg_file_exists {
         log::warn!("Region file doesn't exist. Going to create one.");
@@ -40,14 +166,17 @@ pub(super) fn save_chunk(pos: &ChunkPos, chk: &Chunk, world_dir: &Path) {
         log::error!("Failed to save chunk: Region tile data length is not REGION_BYTES");
         return;
     }
-    let (loc_x, loc_y) = pos.local();
-    let loc_idx = loc_idx(loc_y, loc_x);
-    mdv_math::bitmanip::set_nth_bit(&mut existence_bitset.0, loc_idx as usize, true);
-    let byte_idx = loc_byte_idx(loc_idx);
-    for (i, tile) in chk.tiles.iter().enumerate() {
-        let off = byte_idx + (i * TILE_BYTES);
-        region_tile_data[off..off + 2].copy_from_slice(&tile.bg.0.to_le_bytes());
-        region_tile_data[off + 2..off + 4].copy_from_slice(&tile.mid.0.to_le_bytes());
+    for (pos, chk) in chunks.clone() {
+        debug_assert_eq!(pos.region(), region);
+        let (loc_x, loc_y) = pos.local();
+        let loc_idx = loc_idx(loc_y, loc_x);
+        mdv_math::bitmanip::set_nth_bit(&mut existence_bitset.0, loc_idx as usize, true);
+        let byte_idx = loc_byte_idx(loc_idx);
+        for (i, tile) in chk.tiles.iter().enumerate() {
+            let off = byte_idx + (i * TILE_BYTES);
+            region_tile_data[off..off + 2].copy_from_slice(&tile.bg.0.to_le_bytes());
+            region_tile_data[off + 2..off + 4].copy_from_slice(&tile.mid.0.to_le_bytes());
+        }
     }
     f.rewind().unwrap();
     f.write_all(&u64::to_le_bytes(existence_bitset.0)[..])
@@ -58,10 +187,63 @@ pub(super) fn save_chunk(pos: &ChunkPos, chk: &Chunk, world_dir: &Path) {
     let cursor = f.stream_position().unwrap();
     f.set_len(cursor).unwrap();
     log::info!("{result:?}");
+    save_region_extra(world_dir, region, chunks);
 }
 
 const COMP_LEVEL: i32 = 9;
 
+/// Rewrite the tile and item ids of every region file in `world_dir` according to `remap`
+pub(super) fn remap_region_files(world_dir: &Path, remap: &Remap) {
+    let Ok(read_dir) = std::fs::read_dir(world_dir) else {
+        log::error!("Failed to read world dir {}", world_dir.display());
+        return;
+    };
+    for en in read_dir.flatten() {
+        let path = en.path();
+        if path.extension().is_some_and(|ext| ext == "rgx") {
+            log::info!("Remapping item ids of region {}", path.display());
+            let mut extra = RegionExtra::load(&path);
+            for inv in extra
+                .containers
+                .values_mut()
+                .flat_map(|chk| chk.values_mut())
+            {
+                inv.remap_ids(&remap.items);
+            }
+            extra.save(&path);
+            continue;
+        }
+        if path.extension().map_or(true, |ext| ext != "rgn") {
+            continue;
+        }
+        log::info!("Remapping tile ids of region {}", path.display());
+        let mut f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .unwrap();
+        let existence_bitset = ExistenceBitset::read_from_file(&mut f);
+        let mut region_tile_data = zstd::decode_all(&mut f).unwrap();
+        if region_tile_data.len() != REGION_BYTES {
+            log::error!("Failed to remap region: Region tile data length is not REGION_BYTES");
+            continue;
+        }
+        for tile in region_tile_data.chunks_exact_mut(TILE_BYTES) {
+            let bg = u16::from_le_bytes([tile[0], tile[1]]);
+            let mid = u16::from_le_bytes([tile[2], tile[3]]);
+            tile[0..2].copy_from_slice(&remap.bg.get(bg).to_le_bytes());
+            tile[2..4].copy_from_slice(&remap.mid.get(mid).to_le_bytes());
+        }
+        f.rewind().unwrap();
+        f.write_all(&u64::to_le_bytes(existence_bitset.0)[..])
+            .unwrap();
+        f.write_all(&zstd::encode_all(&region_tile_data[..], COMP_LEVEL).unwrap())
+            .unwrap();
+        let cursor = f.stream_position().unwrap();
+        f.set_len(cursor).unwrap();
+    }
+}
+
 impl Chunk {
     pub fn load_from_region(data: &[u8], x: u8, y: u8) -> Self {
         let byte_idx = loc_byte_idx_xy(x, y);
@@ -71,7 +253,11 @@ impl Chunk {
             t.bg.0 = u16::from_le_bytes(data[off..off + 2].try_into().unwrap());
             t.mid.0 = u16::from_le_bytes(data[off + 2..off + 4].try_into().unwrap());
         }
-        Self { tiles }
+        Self {
+            tiles,
+            containers: ChunkContainers::new(),
+            explored: Explored::default(),
+        }
     }
 }
 
@@ -83,13 +269,32 @@ fn test_chunk_seri() {
     let _ = std::fs::create_dir("testworld");
     let mut chk = Chunk {
         tiles: super::default_chunk_tiles(),
+        containers: ChunkContainers::new(),
+        explored: Explored::default(),
     };
     for t in &mut chk.tiles {
-        t.bg = crate::data::tile::bg::TILES_DIRTBACK;
+        t.bg = crate::data::tile::bg::DIRTBACK;
     }
+    chk.containers.insert(
+        super::ChunkLocalTilePos { x: 4, y: 5 },
+        crate::inventory::Inventory::with_slots(8),
+    );
+    chk.explored.set(42);
     save_chunk(&ChunkPos { x: 2, y: 0 }, &chk, "testworld".as_ref());
     save_chunk(&ChunkPos { x: 3, y: 0 }, &chk, "testworld".as_ref());
     let raw = std::fs::read("testworld/0.0.rgn").unwrap();
     zstd::decode_all(&raw[8..]).unwrap();
+    let (containers, explored) = load_chunk_extra("testworld".as_ref(), ChunkPos { x: 3, y: 0 });
+    assert!(explored.get(42) && !explored.get(43));
+    assert_eq!(
+        containers[&super::ChunkLocalTilePos { x: 4, y: 5 }]
+            .slots
+            .len(),
+        8
+    );
+    let explored_chunks = load_explored_chunks("testworld".as_ref(), (0, 0));
+    assert_eq!(explored_chunks.len(), 2);
+    assert_eq!(explored_chunks[1].0, ChunkPos { x: 3, y: 0 });
+    assert!(explored_chunks[1].1.tiles[0].bg == crate::data::tile::bg::DIRTBACK);
     std::fs::remove_dir_all("testworld").unwrap();
 }